@@ -1,10 +1,9 @@
-use crate::util::DATA_DIR;
-use rand;
+use crate::util;
 use rand::prelude::IndexedRandom;
 use std::fs::read_to_string;
 
 fn read_affirmations() -> String {
-    let lines = read_to_string(DATA_DIR.to_owned() + "/growth").unwrap();
+    let lines = read_to_string(util::data_path("growth")).unwrap();
     let strings: Vec<String> = lines.lines().map(String::from).collect();
 
     let mut rng = rand::rng();
@@ -22,7 +21,7 @@ fn read_affirmations() -> String {
 }
 
 fn read_suggestions() -> String {
-    let lines = read_to_string(DATA_DIR.to_owned() + "/suggestions").unwrap();
+    let lines = read_to_string(util::data_path("suggestions")).unwrap();
     let strings: Vec<String> = lines.lines().map(String::from).collect();
 
     let mut rng = rand::rng();
@@ -47,6 +46,5 @@ pub fn get_affirmations() -> String {
 
     let data = data.to_owned() + &affirmations;
 
-    let data = data + &read_suggestions();
-    data
+    data + &read_suggestions()
 }
@@ -0,0 +1,203 @@
+//! Audit log of every write to a logged entry
+//!
+//! Rows are stored as JSON snapshots taken before and after the change, which
+//! is all `undo` needs to put things back. Changes one command makes together
+//! (a debt snapshot, an import) share a batch and are undone together.
+
+use crate::clock;
+use rusqlite::types::{Value, ValueRef};
+use rusqlite::{Connection, OptionalExtension, Result};
+use serde_json::{Map, Value as Json};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    Insert,
+    Update,
+    Delete,
+}
+
+impl Action {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Action::Insert => "insert",
+            Action::Update => "update",
+            Action::Delete => "delete",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Action> {
+        match s {
+            "insert" => Some(Action::Insert),
+            "update" => Some(Action::Update),
+            "delete" => Some(Action::Delete),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Change {
+    pub id: i64,
+    pub date_time: String,
+    pub kind: String,
+    pub entry_id: i64,
+    pub action: Action,
+    pub before: Option<Json>,
+    pub undone: bool,
+    pub batch: i64,
+}
+
+/// Changes `undo` reverts as one. The id is taken when the first change is
+/// recorded, so batches never overlap however they are nested.
+#[derive(Debug, Default)]
+pub struct Batch {
+    id: Option<i64>,
+}
+
+impl Batch {
+    pub fn new() -> Self {
+        Batch::default()
+    }
+
+    pub fn record(
+        &mut self,
+        conn: &Connection,
+        kind: &str,
+        entry_id: i64,
+        action: Action,
+        before: Option<&Json>,
+        after: Option<&Json>,
+    ) -> Result<()> {
+        let batch = match self.id {
+            Some(id) => id,
+            None => {
+                let id: i64 =
+                    conn.query_row("SELECT COALESCE(MAX(batch), 0) + 1 FROM audit", [], |row| {
+                        row.get(0)
+                    })?;
+                *self.id.insert(id)
+            }
+        };
+        conn.execute(
+            "INSERT INTO audit (date_time, kind, entry_id, action, before, after, batch) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            (
+                clock::timestamp(),
+                kind,
+                entry_id,
+                action.as_str(),
+                before.map(Json::to_string),
+                after.map(Json::to_string),
+                batch,
+            ),
+        )?;
+        Ok(())
+    }
+
+    /// Record the insert of a freshly written table row
+    pub fn record_insert(
+        &mut self,
+        conn: &Connection,
+        kind: &str,
+        table: &str,
+        id: i64,
+    ) -> Result<()> {
+        let after = row_json(conn, table, id)?;
+        self.record(conn, kind, id, Action::Insert, None, after.as_ref())
+    }
+}
+
+/// Record a change on its own
+pub fn record(
+    conn: &Connection,
+    kind: &str,
+    entry_id: i64,
+    action: Action,
+    before: Option<&Json>,
+    after: Option<&Json>,
+) -> Result<()> {
+    Batch::new().record(conn, kind, entry_id, action, before, after)
+}
+
+/// Snapshot a table row as a JSON object keyed by column name
+pub fn row_json(conn: &Connection, table: &str, id: i64) -> Result<Option<Json>> {
+    let mut stmt = conn.prepare(&format!("SELECT * FROM {table} WHERE id = ?1"))?;
+    let names: Vec<String> = stmt.column_names().iter().map(|s| s.to_string()).collect();
+
+    stmt.query_row([id], |row| {
+        let mut map = Map::new();
+        for (idx, name) in names.iter().enumerate() {
+            map.insert(name.clone(), to_json(row.get_ref(idx)?));
+        }
+        Ok(Json::Object(map))
+    })
+    .optional()
+}
+
+/// Record the insert of a freshly written table row on its own
+pub fn record_insert(conn: &Connection, kind: &str, table: &str, id: i64) -> Result<()> {
+    Batch::new().record_insert(conn, kind, table, id)
+}
+
+pub fn to_json(value: ValueRef) -> Json {
+    match value {
+        ValueRef::Null | ValueRef::Blob(_) => Json::Null,
+        ValueRef::Integer(i) => Json::from(i),
+        ValueRef::Real(f) => Json::from(f),
+        ValueRef::Text(t) => Json::from(String::from_utf8_lossy(t).into_owned()),
+    }
+}
+
+pub fn to_sql(value: &Json) -> Value {
+    match value {
+        Json::Null => Value::Null,
+        Json::Bool(b) => Value::Integer(*b as i64),
+        Json::Number(n) => match n.as_i64() {
+            Some(i) => Value::Integer(i),
+            None => Value::Real(n.as_f64().unwrap_or_default()),
+        },
+        Json::String(s) => Value::Text(s.clone()),
+        other => Value::Text(other.to_string()),
+    }
+}
+
+fn change_from_row(row: &rusqlite::Row) -> Result<Change> {
+    let parse = |s: Option<String>| s.and_then(|s| serde_json::from_str(&s).ok());
+    let action: String = row.get(4)?;
+    Ok(Change {
+        id: row.get(0)?,
+        date_time: row.get(1)?,
+        kind: row.get(2)?,
+        entry_id: row.get(3)?,
+        action: Action::parse(&action).unwrap_or(Action::Update),
+        before: parse(row.get(5)?),
+        undone: row.get(6)?,
+        batch: row.get(7)?,
+    })
+}
+
+const CHANGE_COLUMNS: &str = "id, date_time, kind, entry_id, action, before, undone, batch";
+
+/// Changes of the most recent batch not undone yet, newest first
+pub fn last_batch(conn: &Connection) -> Result<Vec<Change>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {CHANGE_COLUMNS} FROM audit WHERE undone = 0 AND batch = (
+            SELECT batch FROM audit WHERE undone = 0 ORDER BY id DESC LIMIT 1
+        ) ORDER BY id DESC"
+    ))?;
+    let iter = stmt.query_map([], change_from_row)?;
+    iter.collect()
+}
+
+pub fn mark_undone(conn: &Connection, id: i64) -> Result<()> {
+    conn.execute("UPDATE audit SET undone = 1 WHERE id = ?1", [id])?;
+    Ok(())
+}
+
+/// Latest changes, newest first
+pub fn history(conn: &Connection, limit: u32) -> Result<Vec<Change>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {CHANGE_COLUMNS} FROM audit ORDER BY id DESC LIMIT ?1"
+    ))?;
+    let iter = stmt.query_map([limit], change_from_row)?;
+    iter.collect()
+}
@@ -0,0 +1,269 @@
+//! Walk-forward comparison of the weight models
+//!
+//! Each model is run through the history one day at a time. After each day
+//! it predicts the scale reading 1 and 7 days ahead from the intake logged
+//! in between, before it sees those weigh-ins; every reading is then scored
+//! against the prediction. The interval columns are how often the reading
+//! fell inside the model's own 80% and 95% prediction intervals, so a well
+//! calibrated model shows about 80% and 95%. Bodycomp only starts at the
+//! first BF% reading, so every model is scored over the same dates: from
+//! the warm-up after the latest start.
+
+use crate::cli::Format;
+use crate::config;
+use crate::kalman_bf::{self, KalmanBF};
+use crate::model1::{self, Day, Kalman2D, Kalman3D, display_weight, weight_unit};
+use crate::output;
+use crate::weight;
+use anyhow::{Result, bail};
+use chrono::NaiveDate;
+use serde::Serialize;
+
+/// Days ahead each prediction is scored at
+const HORIZONS: [usize; 2] = [1, 7];
+
+/// z-scores of the central 80% and 95% intervals
+const Z80: f64 = 1.2816;
+const Z95: f64 = 1.96;
+
+enum Model {
+    TwoState(Kalman2D),
+    ThreeState(Kalman3D),
+    BodyComp(KalmanBF),
+}
+
+impl Model {
+    fn name(&self) -> &'static str {
+        match self {
+            Model::TwoState(_) => "2-state",
+            Model::ThreeState(_) => "3-state",
+            Model::BodyComp(_) => "bodycomp",
+        }
+    }
+
+    fn step(&mut self, day: &Day) {
+        match self {
+            Model::TwoState(kf) => kf.step(day),
+            Model::ThreeState(kf) => kf.step(day),
+            Model::BodyComp(kf) => {
+                kf.step(day);
+            }
+        }
+    }
+
+    fn predict_weight(&self, ahead: &[Day]) -> (f64, f64) {
+        match self {
+            Model::TwoState(kf) => kf.predict_weight(ahead),
+            Model::ThreeState(kf) => kf.predict_weight(ahead),
+            Model::BodyComp(kf) => kf.predict_weight(ahead),
+        }
+    }
+}
+
+/// Error totals for one model at one horizon, in kg
+#[derive(Default)]
+struct Score {
+    n: usize,
+    sum_sq: f64,
+    sum_abs: f64,
+    sum: f64,
+    in80: usize,
+    in95: usize,
+}
+
+impl Score {
+    fn add(&mut self, reading: f64, (mean, var): (f64, f64)) {
+        let err = reading - mean;
+        let sd = var.sqrt();
+        self.n += 1;
+        self.sum_sq += err * err;
+        self.sum_abs += err.abs();
+        self.sum += err;
+        if err.abs() <= Z80 * sd {
+            self.in80 += 1;
+        }
+        if err.abs() <= Z95 * sd {
+            self.in95 += 1;
+        }
+    }
+
+    fn rmse(&self) -> f64 {
+        (self.sum_sq / self.n as f64).sqrt()
+    }
+}
+
+/// Run `model` forward from day `start` (already applied), scoring every
+/// prediction made on day `scored_from` or later
+fn walk(model: &mut Model, days: &[Day], start: usize, scored_from: usize) -> Vec<Score> {
+    let mut scores: Vec<Score> = HORIZONS.iter().map(|_| Score::default()).collect();
+
+    for t in start..days.len() {
+        if t > start {
+            model.step(&days[t]);
+        }
+        if t < scored_from {
+            continue;
+        }
+        for (score, &h) in scores.iter_mut().zip(&HORIZONS) {
+            let Some(target) = days.get(t + h) else {
+                continue;
+            };
+            if target.weights_kg.is_empty() {
+                continue;
+            }
+            let prediction = model.predict_weight(&days[t + 1..=t + h]);
+            for &z in &target.weights_kg {
+                score.add(z, prediction);
+            }
+        }
+    }
+
+    scores
+}
+
+/// Scores for one model at one horizon, errors in the logged unit
+#[derive(Serialize)]
+pub struct ScoreRow {
+    pub model: &'static str,
+    pub days_ahead: usize,
+    pub n: usize,
+    pub rmse: Option<f64>,
+    pub mae: Option<f64>,
+    pub bias: Option<f64>,
+    pub coverage_80: Option<f64>,
+    pub coverage_95: Option<f64>,
+}
+
+impl ScoreRow {
+    fn new(model: &'static str, days_ahead: usize, score: &Score) -> Self {
+        let n = score.n as f64;
+        let scored = score.n > 0;
+        ScoreRow {
+            model,
+            days_ahead,
+            n: score.n,
+            rmse: scored.then(|| display_weight(score.rmse())),
+            mae: scored.then(|| display_weight(score.sum_abs / n)),
+            bias: scored.then(|| display_weight(score.sum / n)),
+            coverage_80: scored.then(|| score.in80 as f64 / n),
+            coverage_95: scored.then(|| score.in95 as f64 / n),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct BacktestReport {
+    pub unit: &'static str,
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub warmup: usize,
+    /// First day predictions are scored from, the same for every model
+    pub scored_from: NaiveDate,
+    pub scores: Vec<ScoreRow>,
+    /// Model with the lowest RMSE at the longest horizon
+    pub best: Option<&'static str>,
+}
+
+fn print_report(report: &BacktestReport) {
+    println!(
+        "Backtest {} to {}, scoring predictions from {} ({}-day warm-up); errors in {}",
+        report.from, report.to, report.scored_from, report.warmup, report.unit
+    );
+    println!("model     ahead     n    RMSE    MAE    bias    80%    95%");
+    for row in &report.scores {
+        let h = row.days_ahead;
+        let (Some(rmse), Some(mae), Some(bias), Some(c80), Some(c95)) = (
+            row.rmse,
+            row.mae,
+            row.bias,
+            row.coverage_80,
+            row.coverage_95,
+        ) else {
+            println!("{:<9} {h} day   no predictions to score", row.model);
+            continue;
+        };
+        println!(
+            "{:<9} {h} day {:>5}  {:>6.2} {:>6.2} {:>+6.2} {:>5.1}% {:>5.1}%",
+            row.model,
+            row.n,
+            rmse,
+            mae,
+            bias,
+            100.0 * c80,
+            100.0 * c95
+        );
+    }
+    if let Some(name) = report.best {
+        println!(
+            "Lowest {}-day-ahead RMSE: {name}",
+            HORIZONS[HORIZONS.len() - 1]
+        );
+    }
+}
+
+/// `model backtest`
+pub fn run(warmup: usize, format: Format) -> Result<()> {
+    let days = model1::calendar_days(&weight::model_rows()?)?;
+    if days.len() <= warmup + HORIZONS[0] {
+        bail!(
+            "only {} days logged; the backtest needs more than the {warmup}-day warm-up",
+            days.len()
+        );
+    }
+
+    let initial_w_kg = config::get().model.initial_weight_kg;
+    let mut models = Vec::new();
+    let mut two = Kalman2D::new(initial_w_kg);
+    two.step(&days[0]);
+    models.push((0, Model::TwoState(two)));
+    let mut three = Kalman3D::new(initial_w_kg);
+    three.step(&days[0]);
+    models.push((0, Model::ThreeState(three)));
+    match kalman_bf::start(&days) {
+        Some((start, kf)) => models.push((start, Model::BodyComp(kf))),
+        None => eprintln!("No BF% readings logged; leaving out the bodycomp model"),
+    }
+
+    // the latest start plus the warm-up, so the RMSEs compare like with like
+    let last_start = models.iter().map(|(start, _)| *start).max().unwrap_or(0);
+    let scored_from = last_start + warmup;
+    if scored_from + HORIZONS[0] >= days.len() {
+        bail!(
+            "the last model starts on {}, leaving nothing to score after the {warmup}-day warm-up",
+            days[last_start].date
+        );
+    }
+
+    let mut scores = Vec::new();
+    let mut best: Option<(&'static str, f64)> = None;
+    for (start, mut model) in models {
+        for (score, h) in walk(&mut model, &days, start, scored_from)
+            .iter()
+            .zip(HORIZONS)
+        {
+            if h == HORIZONS[HORIZONS.len() - 1]
+                && score.n > 0
+                && best.is_none_or(|(_, rmse)| score.rmse() < rmse)
+            {
+                best = Some((model.name(), score.rmse()));
+            }
+            scores.push(ScoreRow::new(model.name(), h, score));
+        }
+    }
+
+    let report = BacktestReport {
+        unit: weight_unit(),
+        from: days[0].date,
+        to: days[days.len() - 1].date,
+        warmup,
+        scored_from: days[scored_from].date,
+        scores,
+        best: best.map(|(name, _)| name),
+    };
+    match format {
+        Format::Table => print_report(&report),
+        Format::Json => output::json(&report)?,
+        Format::Csv => output::csv(&report.scores)?,
+    }
+    Ok(())
+}
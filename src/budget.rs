@@ -0,0 +1,236 @@
+//! Monthly spend budgets
+//!
+//! Each `[[budget.categories]]` entry allots an amount per calendar month to
+//! one `spend` category. At the end of a month the leftover is carried into
+//! the next one according to the category's rollover rule, counting from
+//! `budget.start`. `budget status` compares this month's spend with what is
+//! allotted and projects the month-end total from the spend so far; the
+//! vault note gets a warning when a category is over or on pace to be.
+
+use crate::cli::Format;
+use crate::clock;
+use crate::config::{self, CategoryBudget, Rollover};
+use crate::db;
+use crate::output;
+use crate::recurring;
+use anyhow::{Result, bail};
+use chrono::{Datelike, Months, NaiveDate};
+use rusqlite::Connection;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// First day of the month `date` falls in
+fn month_start(date: NaiveDate) -> NaiveDate {
+    date.with_day(1).unwrap_or(date)
+}
+
+fn next_month(month: NaiveDate) -> NaiveDate {
+    month + Months::new(1)
+}
+
+/// Spend per (category, month), months keyed by their first day
+fn monthly_spend(conn: &Connection) -> Result<HashMap<(String, NaiveDate), f64>> {
+    let mut stmt = conn.prepare(
+        "SELECT category, strftime('%Y-%m-01', date_time), SUM(amount) FROM spend GROUP BY 1, 2",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, f64>(2)?,
+        ))
+    })?;
+
+    let mut spend = HashMap::new();
+    for row in rows {
+        let (category, month, amount) = row?;
+        let month = NaiveDate::parse_from_str(&month, "%Y-%m-%d")?;
+        spend.insert((category, month), amount);
+    }
+    Ok(spend)
+}
+
+/// Amount carried into `month` from the months before it
+fn carried(
+    budget: &CategoryBudget,
+    spend: &HashMap<(String, NaiveDate), f64>,
+    start: NaiveDate,
+    month: NaiveDate,
+) -> f64 {
+    let mut carry = 0.0;
+    let mut m = month_start(start);
+    while m < month {
+        let spent = spend
+            .get(&(budget.category.clone(), m))
+            .copied()
+            .unwrap_or(0.0);
+        let left = budget.monthly + carry - spent;
+        carry = match budget.rollover {
+            Rollover::None => 0.0,
+            Rollover::Unspent => left.max(0.0),
+            Rollover::Full => left,
+        };
+        if let Some(cap) = budget.max_rollover {
+            carry = carry.min(cap);
+        }
+        m = next_month(m);
+    }
+    carry
+}
+
+/// One category this month
+#[derive(Serialize)]
+pub struct CategoryStatus {
+    pub category: String,
+    pub monthly: f64,
+    /// From earlier months; negative when overspending carried over
+    pub carried: f64,
+    pub allotted: f64,
+    pub spent: f64,
+    pub remaining: f64,
+    /// What can still go out each day, today included
+    pub per_day: f64,
+    /// Month-end spend at this month's daily rate so far
+    pub projected: f64,
+    pub over: bool,
+    pub on_pace_over: bool,
+}
+
+#[derive(Serialize)]
+pub struct BudgetReport {
+    pub month: String,
+    pub days_in_month: u32,
+    /// Days left in the month, today included
+    pub days_left: u32,
+    pub categories: Vec<CategoryStatus>,
+    /// This month's spend in categories without a budget
+    pub unbudgeted: f64,
+}
+
+fn build_report(conn: &Connection, today: NaiveDate) -> Result<BudgetReport> {
+    let cfg = &config::get().budget;
+    let spend = monthly_spend(conn)?;
+    let month = month_start(today);
+    let days_in_month = (next_month(month) - month).num_days() as u32;
+    let days_left = days_in_month - today.day() + 1;
+    let start = cfg.start.unwrap_or(month);
+
+    let categories = cfg
+        .categories
+        .iter()
+        .map(|budget| {
+            let carried = if start <= month {
+                carried(budget, &spend, start, month)
+            } else {
+                0.0
+            };
+            let allotted = budget.monthly + carried;
+            let spent = spend
+                .get(&(budget.category.clone(), month))
+                .copied()
+                .unwrap_or(0.0);
+            let remaining = allotted - spent;
+            let projected = spent / today.day() as f64 * days_in_month as f64;
+            CategoryStatus {
+                category: budget.category.clone(),
+                monthly: budget.monthly,
+                carried,
+                allotted,
+                spent,
+                remaining,
+                per_day: remaining.max(0.0) / days_left as f64,
+                projected,
+                over: spent > allotted,
+                on_pace_over: projected > allotted,
+            }
+        })
+        .collect();
+
+    let unbudgeted = spend
+        .iter()
+        .filter(|((category, m), _)| {
+            *m == month && !cfg.categories.iter().any(|b| &b.category == category)
+        })
+        .map(|(_, amount)| amount)
+        .sum();
+
+    Ok(BudgetReport {
+        month: month.format("%Y-%m").to_string(),
+        days_in_month,
+        days_left,
+        categories,
+        unbudgeted,
+    })
+}
+
+fn print_report(report: &BudgetReport) {
+    println!(
+        "{}: {} of {} days left",
+        report.month, report.days_left, report.days_in_month
+    );
+    println!("category          budget   carried     spent      left   per day  projected");
+    for c in &report.categories {
+        let flag = if c.over {
+            "  over budget"
+        } else if c.on_pace_over {
+            "  on pace to go over"
+        } else {
+            ""
+        };
+        println!(
+            "{:<15} {:>8.2}  {:>8.2}  {:>8.2}  {:>8.2}  {:>8.2}   {:>8.2}{flag}",
+            c.category, c.monthly, c.carried, c.spent, c.remaining, c.per_day, c.projected
+        );
+    }
+    if report.unbudgeted > 0.0 {
+        println!(
+            "Spend outside the budgeted categories: ${:.2}",
+            report.unbudgeted
+        );
+    }
+}
+
+/// `budget status`
+pub fn status(format: Format) -> Result<()> {
+    if config::get().budget.categories.is_empty() {
+        bail!("no budgets set; add [[budget.categories]] entries to the config");
+    }
+    let mut conn = db::open()?;
+    recurring::catch_up(&mut conn)?;
+    let report = build_report(&conn, clock::today())?;
+    match format {
+        Format::Table => print_report(&report),
+        Format::Json => output::json(&report)?,
+        Format::Csv => output::csv(&report.categories)?,
+    }
+    Ok(())
+}
+
+/// Warning for the vault note when a category is over or on pace to be
+pub fn vault_line() -> Option<String> {
+    if config::get().budget.categories.is_empty() {
+        return None;
+    }
+    let conn = db::open().ok()?;
+    let report = build_report(&conn, clock::today()).ok()?;
+    let warnings: Vec<String> = report
+        .categories
+        .iter()
+        .filter_map(|c| {
+            if c.over {
+                Some(format!(
+                    "{} ${:.0} of ${:.0}",
+                    c.category, c.spent, c.allotted
+                ))
+            } else if c.on_pace_over {
+                Some(format!(
+                    "{} on pace for ${:.0} of ${:.0}",
+                    c.category, c.projected, c.allotted
+                ))
+            } else {
+                None
+            }
+        })
+        .collect();
+    (!warnings.is_empty()).then(|| format!("- Over budget: {}", warnings.join("; ")))
+}
@@ -0,0 +1,199 @@
+//! Daily calorie target
+//!
+//! Built from the 2-state filter's TDEE: the deficit that loses
+//! `weekly_loss_pct` of the current weight a week, taken off a TDEE shaded
+//! down by its uncertainty. The target moves at most `max_change_per_week`
+//! from the last one stored, and every `diet_weeks` weeks (counted from the
+//! first stored target) it goes to maintenance for `break_weeks` weeks.
+//! `model run` stores one row per day in `calorie_target`; the vault note
+//! shows the latest.
+
+use crate::config;
+use crate::db;
+use crate::model1::{KCAL_PER_KG, Kalman2D, display_weight, weight_unit};
+use anyhow::Result;
+use chrono::{Days, NaiveDate};
+use rusqlite::{Connection, OptionalExtension};
+use serde::Serialize;
+
+/// Targets are rounded to this many kcal
+const ROUND_TO: f64 = 10.0;
+
+#[derive(Serialize)]
+pub struct Plan {
+    pub date: NaiveDate,
+    pub target: f64,
+    pub tdee: f64,
+    pub tdee_sd: f64,
+    pub weight_kg: f64,
+    pub diet_break: bool,
+    /// Target before the rate limit, when the limit changed it
+    pub wanted: Option<f64>,
+}
+
+fn parse_date(idx: usize, s: String) -> rusqlite::Result<NaiveDate> {
+    NaiveDate::parse_from_str(&s, "%Y-%m-%d").map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(idx, rusqlite::types::Type::Text, e.into())
+    })
+}
+
+// Last non-break target stored before `date`
+fn previous(conn: &Connection, date: NaiveDate) -> rusqlite::Result<Option<(NaiveDate, f64)>> {
+    conn.query_row(
+        "SELECT date, target FROM calorie_target WHERE date < ?1 AND diet_break = 0 ORDER BY date DESC LIMIT 1",
+        [date.to_string()],
+        |row| Ok((parse_date(0, row.get(0)?)?, row.get(1)?)),
+    )
+    .optional()
+}
+
+// Date the diet-break schedule counts from
+fn first_date(conn: &Connection) -> rusqlite::Result<Option<NaiveDate>> {
+    let first: Option<String> =
+        conn.query_row("SELECT MIN(date) FROM calorie_target", [], |row| row.get(0))?;
+    first.map(|d| parse_date(0, d)).transpose()
+}
+
+/// Whether `date` falls in a diet break, and the last day of that break
+fn diet_break(start: NaiveDate, date: NaiveDate) -> Option<NaiveDate> {
+    let cfg = &config::get().model.controller;
+    if cfg.break_weeks == 0 {
+        return None;
+    }
+    let cycle = 7 * (cfg.diet_weeks + cfg.break_weeks) as i64;
+    let into = (date - start).num_days().rem_euclid(cycle);
+    let break_starts = 7 * cfg.diet_weeks as i64;
+    (into >= break_starts).then(|| date + Days::new((cycle - 1 - into) as u64))
+}
+
+/// Work out the target for `date` from the filter's final state
+pub fn plan(conn: &Connection, date: NaiveDate, kf: &Kalman2D) -> Result<Plan> {
+    let cfg = &config::get().model.controller;
+    let tdee = kf.tdee();
+    let tdee_sd = kf.tdee_var().sqrt();
+    let weight_kg = kf.weight_kg();
+
+    let start = first_date(conn)?.unwrap_or(date);
+    let round = |kcal: f64| (kcal / ROUND_TO).round() * ROUND_TO;
+
+    if diet_break(start, date).is_some() {
+        return Ok(Plan {
+            date,
+            target: round(tdee),
+            tdee,
+            tdee_sd,
+            weight_kg,
+            diet_break: true,
+            wanted: None,
+        });
+    }
+
+    let deficit = cfg.weekly_loss_pct / 100.0 * weight_kg * KCAL_PER_KG / 7.0;
+    let wanted = (tdee - cfg.tdee_caution_sd * tdee_sd - deficit).max(cfg.min_intake);
+
+    let target = match previous(conn, date)? {
+        Some((last_date, last)) => {
+            let days = (date - last_date).num_days() as f64;
+            let step = cfg.max_change_per_week * days / 7.0;
+            wanted.clamp(last - step, last + step)
+        }
+        None => wanted,
+    };
+
+    Ok(Plan {
+        date,
+        target: round(target),
+        tdee,
+        tdee_sd,
+        weight_kg,
+        diet_break: false,
+        wanted: (round(wanted) != round(target)).then(|| round(wanted)),
+    })
+}
+
+pub fn save(conn: &Connection, plan: &Plan) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO calorie_target (date, target, tdee, tdee_sd, weight_kg, diet_break) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        (
+            plan.date.to_string(),
+            plan.target,
+            plan.tdee,
+            plan.tdee_sd,
+            plan.weight_kg,
+            plan.diet_break,
+        ),
+    )?;
+    Ok(())
+}
+
+/// Plan the target for `date` and store it
+pub fn update(date: NaiveDate, kf: &Kalman2D) -> Result<Plan> {
+    let conn = db::open()?;
+    let plan = plan(&conn, date, kf)?;
+    save(&conn, &plan)?;
+    Ok(plan)
+}
+
+pub fn print(plan: &Plan) -> Result<()> {
+    let conn = db::open()?;
+    println!(
+        "TDEE {:.0} ± {:.0} kcal at {:.1} {}",
+        plan.tdee,
+        plan.tdee_sd,
+        display_weight(plan.weight_kg),
+        weight_unit()
+    );
+    println!("{}", describe(&conn, plan)?);
+    if let Some(wanted) = plan.wanted {
+        println!(
+            "  (wants {:.0}; moving at most {:.0} kcal a week)",
+            wanted,
+            config::get().model.controller.max_change_per_week
+        );
+    }
+    Ok(())
+}
+
+fn describe(conn: &Connection, plan: &Plan) -> Result<String> {
+    if plan.diet_break {
+        let start = first_date(conn)?.unwrap_or(plan.date);
+        let until = diet_break(start, plan.date).unwrap_or(plan.date);
+        return Ok(format!(
+            "Diet break: eat at maintenance, {:.0} kcal/day, through {}",
+            plan.target, until
+        ));
+    }
+    let cfg = &config::get().model.controller;
+    Ok(format!(
+        "Calorie target: {:.0} kcal/day for {}%/week ({:.1} {}/week)",
+        plan.target,
+        cfg.weekly_loss_pct,
+        display_weight(cfg.weekly_loss_pct / 100.0 * plan.weight_kg),
+        weight_unit()
+    ))
+}
+
+/// Line for the vault note: the latest stored target, if any
+pub fn vault_line() -> Option<String> {
+    let conn = db::open().ok()?;
+    let plan = conn
+        .query_row(
+            "SELECT date, target, tdee, tdee_sd, weight_kg, diet_break FROM calorie_target ORDER BY date DESC LIMIT 1",
+            [],
+            |row| {
+                Ok(Plan {
+                    date: parse_date(0, row.get(0)?)?,
+                    target: row.get(1)?,
+                    tdee: row.get(2)?,
+                    tdee_sd: row.get(3)?,
+                    weight_kg: row.get(4)?,
+                    diet_break: row.get(5)?,
+                    wanted: None,
+                })
+            },
+        )
+        .optional()
+        .ok()??;
+    let line = describe(&conn, &plan).ok()?;
+    Some(format!("{line} (set {})", plan.date))
+}
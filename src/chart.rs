@@ -0,0 +1,372 @@
+//! Terminal charts
+//!
+//! Line charts are drawn on a braille canvas: every character cell holds a
+//! 2×4 grid of dots, so a 72×12 chart has 144×48 points of resolution.
+//! Secondary series (TDEE under the weight chart) are one-line sparklines.
+//! Colour is only used when stdout is a terminal.
+
+use crate::cli::ChartArgs;
+use crate::config;
+use crate::db;
+use crate::model1::{self, Kalman2D, display_weight, weight_unit};
+use crate::weight;
+use anyhow::{Result, bail};
+use chrono::{Days, NaiveDate};
+use std::io::IsTerminal;
+
+/// Chart height in character rows
+const HEIGHT: usize = 12;
+
+/// Sparkline levels, lowest first
+const SPARKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+const CYAN: &str = "\x1b[36m";
+const RESET: &str = "\x1b[0m";
+
+/// Braille dot bits, indexed [row][column] within a cell
+const DOTS: [[u8; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+struct Canvas {
+    width: usize,
+    height: usize,
+    cells: Vec<u8>,
+    /// Cells last drawn by a highlighted series
+    highlight: Vec<bool>,
+}
+
+impl Canvas {
+    fn new(width: usize, height: usize) -> Self {
+        Canvas {
+            width,
+            height,
+            cells: vec![0; width * height],
+            highlight: vec![false; width * height],
+        }
+    }
+
+    /// Dot columns and rows available
+    fn dots(&self) -> (usize, usize) {
+        (self.width * 2, self.height * 4)
+    }
+
+    fn set(&mut self, x: usize, y: usize, highlight: bool) {
+        let (cols, rows) = self.dots();
+        if x >= cols || y >= rows {
+            return;
+        }
+        let cell = (y / 4) * self.width + x / 2;
+        self.cells[cell] |= DOTS[y % 4][x % 2];
+        self.highlight[cell] = highlight;
+    }
+
+    // Bresenham
+    fn line(&mut self, (x0, y0): (usize, usize), (x1, y1): (usize, usize), highlight: bool) {
+        let (mut x, mut y) = (x0 as i64, y0 as i64);
+        let (x1, y1) = (x1 as i64, y1 as i64);
+        let dx = (x1 - x).abs();
+        let dy = -(y1 - y).abs();
+        let sx = if x < x1 { 1 } else { -1 };
+        let sy = if y < y1 { 1 } else { -1 };
+        let mut err = dx + dy;
+        loop {
+            self.set(x as usize, y as usize, highlight);
+            if x == x1 && y == y1 {
+                break;
+            }
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                x += sx;
+            }
+            if e2 <= dx {
+                err += dx;
+                y += sy;
+            }
+        }
+    }
+
+    fn row(&self, idx: usize, color: bool) -> String {
+        let mut out = String::new();
+        let mut lit = false;
+        for c in 0..self.width {
+            let cell = idx * self.width + c;
+            let hl = color && self.highlight[cell] && self.cells[cell] != 0;
+            if hl != lit {
+                out.push_str(if hl { CYAN } else { RESET });
+                lit = hl;
+            }
+            out.push(char::from_u32(0x2800 + self.cells[cell] as u32).unwrap_or(' '));
+        }
+        if lit {
+            out.push_str(RESET);
+        }
+        out
+    }
+}
+
+/// Maps dates and values onto a canvas
+struct Plot {
+    canvas: Canvas,
+    start: NaiveDate,
+    end: NaiveDate,
+    min: f64,
+    max: f64,
+}
+
+impl Plot {
+    fn new(width: usize, start: NaiveDate, end: NaiveDate, values: &[f64]) -> Self {
+        let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        // a flat series still needs some range to sit in
+        let pad = if max - min < 1e-9 { 1.0 } else { 0.0 };
+        Plot {
+            canvas: Canvas::new(width, HEIGHT),
+            start,
+            end,
+            min: min - pad,
+            max: max + pad,
+        }
+    }
+
+    fn point(&self, date: NaiveDate, value: f64) -> (usize, usize) {
+        let (cols, rows) = self.canvas.dots();
+        let span = (self.end - self.start).num_days().max(1) as f64;
+        let x = (date - self.start).num_days() as f64 / span * (cols - 1) as f64;
+        let y = (self.max - value) / (self.max - self.min) * (rows - 1) as f64;
+        (x.round() as usize, y.round() as usize)
+    }
+
+    fn dots(&mut self, points: &[(NaiveDate, f64)], highlight: bool) {
+        for &(date, value) in points {
+            let (x, y) = self.point(date, value);
+            self.canvas.set(x, y, highlight);
+        }
+    }
+
+    fn line(&mut self, points: &[(NaiveDate, f64)], highlight: bool) {
+        for pair in points.windows(2) {
+            let from = self.point(pair[0].0, pair[0].1);
+            let to = self.point(pair[1].0, pair[1].1);
+            self.canvas.line(from, to, highlight);
+        }
+        if let [only] = points {
+            self.dots(&[*only], highlight);
+        }
+    }
+
+    /// The chart with y labels from `label` on the top, middle and bottom rows
+    fn render(&self, label: impl Fn(f64) -> String) -> String {
+        let color = std::io::stdout().is_terminal();
+        let labels: Vec<String> = (0..self.canvas.height)
+            .map(|row| {
+                let last = self.canvas.height - 1;
+                if row == 0 || row == last || row == last / 2 {
+                    let frac = row as f64 / last as f64;
+                    label(self.max - frac * (self.max - self.min))
+                } else {
+                    String::new()
+                }
+            })
+            .collect();
+        let pad = labels.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+
+        let mut out = String::new();
+        for (row, text) in labels.iter().enumerate() {
+            out.push_str(&format!("{text:>pad$} ┤{}\n", self.canvas.row(row, color)));
+        }
+        let width = self.canvas.width;
+        let start = self.start.to_string();
+        let end = self.end.to_string();
+        let gap = width.saturating_sub(start.len() + end.len());
+        out.push_str(&format!("{:pad$}  {start}{:gap$}{end}\n", "", ""));
+        out
+    }
+}
+
+/// One-line sparkline of `values`, averaged into `width` buckets
+fn sparkline(values: &[f64], width: usize) -> String {
+    if values.is_empty() {
+        return String::new();
+    }
+    let buckets = width.min(values.len());
+    let means: Vec<f64> = (0..buckets)
+        .map(|b| {
+            let from = b * values.len() / buckets;
+            let to = ((b + 1) * values.len() / buckets).max(from + 1);
+            values[from..to].iter().sum::<f64>() / (to - from) as f64
+        })
+        .collect();
+    let min = means.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = means.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    means
+        .iter()
+        .map(|v| {
+            let level = if max > min {
+                ((v - min) / (max - min) * 7.0).round() as usize
+            } else {
+                3
+            };
+            SPARKS[level.min(7)]
+        })
+        .collect()
+}
+
+fn in_range(date: NaiveDate, args: &ChartArgs) -> bool {
+    args.since.is_none_or(|since| date >= since) && args.until.is_none_or(|until| date <= until)
+}
+
+/// `chart weight`: scale readings, the smoothed weight and a TDEE sparkline
+pub fn weight(args: &ChartArgs) -> Result<()> {
+    let days = model1::calendar_days(&weight::model_rows()?)?;
+    let mut kf = Kalman2D::new(config::get().model.initial_weight_kg);
+    for day in &days {
+        kf.step(day);
+    }
+    let smoothed = kf.smoothed();
+
+    let mut raw = Vec::new();
+    let mut trend = Vec::new();
+    let mut tdee = Vec::new();
+    for (day, x) in days.iter().zip(&smoothed) {
+        if !in_range(day.date, args) {
+            continue;
+        }
+        raw.extend(
+            day.weights_kg
+                .iter()
+                .map(|&w| (day.date, display_weight(w))),
+        );
+        trend.push((day.date, display_weight(x[0])));
+        tdee.push(x[1]);
+    }
+    let (Some(first), Some(last)) = (trend.first(), trend.last()) else {
+        bail!("no weigh-ins in that range");
+    };
+
+    let values: Vec<f64> = raw.iter().chain(&trend).map(|p| p.1).collect();
+    let mut plot = Plot::new(args.width as usize, first.0, last.0, &values);
+    plot.dots(&raw, false);
+    plot.line(&trend, true);
+
+    println!(
+        "Weight ({}): dots are scale readings, the line is the smoothed weight",
+        weight_unit()
+    );
+    print!("{}", plot.render(|v| format!("{v:.1}")));
+
+    let lo = tdee.iter().copied().fold(f64::INFINITY, f64::min);
+    let hi = tdee.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    println!(
+        "TDEE {lo:.0}–{hi:.0} kcal  {}",
+        sparkline(&tdee, args.width as usize)
+    );
+    Ok(())
+}
+
+/// `chart spend`: daily totals, days without spend as zero
+pub fn spend(args: &ChartArgs) -> Result<()> {
+    let conn = db::open()?;
+    let mut stmt = conn.prepare(
+        "SELECT date(date_time), SUM(amount) FROM spend GROUP BY date(date_time) ORDER BY date(date_time)",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, f64>(1)?))
+    })?;
+
+    let mut totals = Vec::new();
+    for row in rows {
+        let (date, amount) = row?;
+        let date = NaiveDate::parse_from_str(&date, "%Y-%m-%d")?;
+        if in_range(date, args) {
+            totals.push((date, amount));
+        }
+    }
+    let (Some(&(first, _)), Some(&(last, _))) = (totals.first(), totals.last()) else {
+        bail!("no spend in that range");
+    };
+    let first = args.since.unwrap_or(first);
+    let last = args.until.unwrap_or(last);
+
+    let mut daily = Vec::new();
+    let mut logged = totals.iter().peekable();
+    let mut date = first;
+    while date <= last {
+        let amount = match logged.peek() {
+            Some(&&(d, amount)) if d == date => {
+                logged.next();
+                amount
+            }
+            _ => 0.0,
+        };
+        daily.push((date, amount));
+        date = date + Days::new(1);
+    }
+
+    let mut values: Vec<f64> = daily.iter().map(|d| d.1).collect();
+    values.push(0.0);
+    let mut plot = Plot::new(args.width as usize, first, last, &values);
+    // bars from zero up to each day's total
+    for &(date, amount) in &daily {
+        let (x, y) = plot.point(date, amount);
+        let (_, bottom) = plot.point(date, 0.0);
+        plot.canvas.line((x, bottom), (x, y), true);
+    }
+
+    let total: f64 = daily.iter().map(|d| d.1).sum();
+    println!(
+        "Daily spend: ${:.2} over {} days, ${:.2}/day",
+        total,
+        daily.len(),
+        total / daily.len() as f64
+    );
+    print!("{}", plot.render(|v| format!("${v:.0}")));
+    Ok(())
+}
+
+fn pace(seconds: f64) -> String {
+    let seconds = seconds.round() as u64;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+/// `chart rowing`: 500 m split of every logged row. `time` is logged in
+/// minutes (`log row --time`)
+pub fn rowing(args: &ChartArgs) -> Result<()> {
+    let conn = db::open()?;
+    let mut stmt = conn.prepare(
+        "SELECT date(date_time), distance, time FROM row WHERE distance > 0 ORDER BY date_time",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, f64>(1)?,
+            row.get::<_, f64>(2)?,
+        ))
+    })?;
+
+    let mut splits = Vec::new();
+    for row in rows {
+        let (date, distance, time) = row?;
+        let date = NaiveDate::parse_from_str(&date, "%Y-%m-%d")?;
+        if in_range(date, args) {
+            splits.push((date, time * 60.0 * 500.0 / distance));
+        }
+    }
+    let (Some(&(first, _)), Some(&(last, _))) = (splits.first(), splits.last()) else {
+        bail!("no rows logged in that range");
+    };
+
+    // lower is faster, so flip the values to have improvement go up
+    let flipped: Vec<(NaiveDate, f64)> = splits.iter().map(|&(d, s)| (d, -s)).collect();
+    let values: Vec<f64> = flipped.iter().map(|p| p.1).collect();
+    let mut plot = Plot::new(args.width as usize, first, last, &values);
+    plot.line(&flipped, true);
+
+    let best = splits.iter().map(|s| s.1).fold(f64::INFINITY, f64::min);
+    println!(
+        "Rowing pace per 500 m ({} sessions, best {}), faster is higher",
+        splits.len(),
+        pace(best)
+    );
+    print!("{}", plot.render(|v| pace(-v)));
+    Ok(())
+}
@@ -0,0 +1,640 @@
+use chrono::{NaiveDate, NaiveDateTime, Weekday};
+use clap::{Args as ClapArgs, Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
+
+#[derive(Parser, Debug)]
+#[command(about = "Daily schedule, habit logging and weight model")]
+pub struct Args {
+    /// Config file to use instead of the XDG default
+    #[arg(long, global = true)]
+    pub config: Option<PathBuf>,
+
+    /// Act as if today were this date (YYYY-MM-DD)
+    #[arg(long, global = true, conflicts_with = "at")]
+    pub date: Option<NaiveDate>,
+
+    /// Act as if it were this moment (YYYY-MM-DD HH:MM[:SS])
+    #[arg(long, global = true, value_parser = crate::clock::parse_timestamp)]
+    pub at: Option<NaiveDateTime>,
+
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Record an entry; anything not given as an option is prompted for
+    Log {
+        #[command(subcommand)]
+        entry: LogCommand,
+    },
+
+    /// Print or write schedules, lifts and month stubs
+    Schedule {
+        #[command(subcommand)]
+        command: ScheduleCommand,
+    },
+
+    /// Run the Kalman weight models
+    Model {
+        /// Output format; see the README for the json/csv schemas
+        #[arg(long, value_enum, global = true, default_value_t = Format::Table)]
+        format: Format,
+        #[command(subcommand)]
+        command: ModelCommand,
+    },
+
+    /// Summaries built from the logged data
+    Report {
+        /// Output format; see the README for the json/csv schemas
+        #[arg(long, value_enum, global = true, default_value_t = Format::Table)]
+        format: Format,
+        #[command(subcommand)]
+        command: ReportCommand,
+    },
+
+    /// Plan and track paying off the debt accounts
+    Debt {
+        /// Output format; see the README for the json/csv schemas
+        #[arg(long, value_enum, global = true, default_value_t = Format::Table)]
+        format: Format,
+        #[command(subcommand)]
+        command: DebtCommand,
+    },
+
+    /// Monthly spend budgets
+    Budget {
+        /// Output format; see the README for the json/csv schemas
+        #[arg(long, value_enum, global = true, default_value_t = Format::Table)]
+        format: Format,
+        #[command(subcommand)]
+        command: BudgetCommand,
+    },
+
+    /// Income, spend and balances together
+    Finance {
+        /// Output format; see the README for the json/csv schemas
+        #[arg(long, value_enum, global = true, default_value_t = Format::Table)]
+        format: Format,
+        #[command(subcommand)]
+        command: FinanceCommand,
+    },
+
+    /// Rent, subscriptions and installments that repeat
+    Recurring {
+        /// Output format; see the README for the json/csv schemas
+        #[arg(long, value_enum, global = true, default_value_t = Format::Table)]
+        format: Format,
+        #[command(subcommand)]
+        command: RecurringCommand,
+    },
+
+    /// Plot trends in the terminal
+    Chart {
+        #[command(subcommand)]
+        command: ChartCommand,
+    },
+
+    /// Pick a random study task and log the time spent on it
+    Bored,
+
+    /// Back up the data directory to S3
+    Sync,
+
+    /// Build the Hugo site from the weekly reflections and upload it
+    Publish,
+
+    /// Inspect or upgrade the links.db schema
+    Db {
+        #[command(subcommand)]
+        command: DbCommand,
+    },
+
+    /// List, edit or delete logged entries
+    Entries {
+        #[command(subcommand)]
+        command: EntriesCommand,
+    },
+
+    /// Revert the most recent logged, edited or deleted entry
+    Undo,
+
+    /// Load data from files
+    Import {
+        #[command(subcommand)]
+        command: ImportCommand,
+    },
+
+    /// Write data out to files
+    Export {
+        #[command(subcommand)]
+        command: ExportCommand,
+    },
+
+    /// Inspect or create the config file
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum EntriesCommand {
+    /// Show entries of one kind, oldest first
+    List {
+        /// spend, violation, win, row, time, activity, debt, rule, recurring,
+        /// income, asset or weight
+        kind: String,
+        /// Only entries on or after this date
+        #[arg(long)]
+        since: Option<NaiveDate>,
+        /// Show at most this many of the latest entries
+        #[arg(long, default_value_t = 20)]
+        limit: u32,
+    },
+
+    /// Change columns of an entry; prompts for each column without --set
+    Edit {
+        kind: String,
+        id: i64,
+        /// New column value, e.g. --set amount=12.50
+        #[arg(long, value_parser = crate::entries::parse_assignment)]
+        set: Vec<(String, String)>,
+    },
+
+    /// Remove an entry
+    Delete { kind: String, id: i64 },
+
+    /// Show the latest changes in the audit log
+    Audit {
+        #[arg(long, default_value_t = 20)]
+        limit: u32,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ImportCommand {
+    /// Weigh-ins from a weight_energy.csv style file (header optional)
+    Csv {
+        /// Defaults to weight_energy.csv in the data directory
+        path: Option<PathBuf>,
+        /// Replace weigh-ins on dates that are already logged
+        #[arg(long)]
+        overwrite: bool,
+    },
+
+    /// Purchases from a bank or card statement (OFX, QFX or CSV)
+    Statement {
+        path: PathBuf,
+        /// Show what would be imported without writing anything
+        #[arg(long)]
+        dry_run: bool,
+        /// Leave transactions no rule matches for later instead of asking
+        #[arg(long)]
+        no_review: bool,
+    },
+
+    /// Purchases and debt balances from a YNAB register export
+    Ynab {
+        path: PathBuf,
+        /// Category and account mapping; defaults to ynab.toml in the data directory
+        #[arg(long)]
+        mapping: Option<PathBuf>,
+        /// How dates are written in the export
+        #[arg(long, default_value = "%m/%d/%Y")]
+        date_format: String,
+        /// Show what would be imported without writing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ExportCommand {
+    /// Weigh-ins as CSV
+    Csv {
+        /// File to write; stdout if omitted
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+
+    /// Spend as a CSV for YNAB's file import
+    Ynab {
+        /// File to write; stdout if omitted
+        #[arg(long)]
+        out: Option<PathBuf>,
+        /// Only spend from this date on
+        #[arg(long)]
+        since: Option<NaiveDate>,
+        /// Category mapping; defaults to ynab.toml in the data directory
+        #[arg(long)]
+        mapping: Option<PathBuf>,
+        /// How to write dates
+        #[arg(long, default_value = "%m/%d/%Y")]
+        date_format: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum DbCommand {
+    /// Apply pending schema migrations
+    Migrate,
+
+    /// Show the schema version and pending migrations
+    Status,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommand {
+    /// Print the effective configuration
+    Show,
+
+    /// Write a config file with the default values
+    Init {
+        /// Overwrite an existing file
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum LogCommand {
+    /// Morning weigh-in; also syncs, writes tomorrow's note and runs the model
+    Weight(WeightArgs),
+
+    /// A purchase
+    Spend {
+        #[arg(long)]
+        category: Option<String>,
+
+        #[arg(long)]
+        name: Option<String>,
+
+        #[arg(long)]
+        amount: Option<f64>,
+    },
+
+    /// An erg session
+    Row {
+        /// Distance in m
+        #[arg(long)]
+        distance: Option<u64>,
+
+        /// Time in minutes
+        #[arg(long)]
+        time: Option<u64>,
+
+        #[arg(long)]
+        watts: Option<f64>,
+
+        #[arg(long)]
+        cals: Option<f64>,
+    },
+
+    /// A timed or counted activity from `[[activities]]` in the config
+    Activity {
+        /// Activity name, e.g. ccna or caffeine; prompted for if omitted
+        kind: Option<String>,
+
+        /// Quantity in the activity's unit (minutes, mg, ...)
+        #[arg(long)]
+        amount: Option<f64>,
+
+        /// Free-form tag, repeatable
+        #[arg(long = "tag")]
+        tags: Vec<String>,
+
+        #[arg(long)]
+        note: Option<String>,
+    },
+
+    /// Minutes spent on a category of work
+    Time {
+        #[arg(long)]
+        category: Option<String>,
+
+        #[arg(long)]
+        minutes: Option<u64>,
+    },
+
+    /// A broken rule
+    Violation {
+        #[arg(long)]
+        name: Option<String>,
+    },
+
+    /// Something that went well
+    Win {
+        #[arg(long)]
+        name: Option<String>,
+    },
+
+    /// Current balance of every debt account
+    Debt(DebtArgs),
+
+    /// Money coming in
+    Income {
+        /// Where it came from, e.g. salary
+        #[arg(long)]
+        source: Option<String>,
+
+        #[arg(long)]
+        amount: Option<f64>,
+    },
+
+    /// Current balance of every asset account
+    Asset(AssetArgs),
+
+    /// Completed todos from today's vault note
+    Tasks,
+}
+
+#[derive(ClapArgs, Debug)]
+pub struct WeightArgs {
+    /// Morning weight in lb (e.g. 294.6)
+    #[arg(long)]
+    pub weight: Option<f64>,
+
+    /// Calories eaten yesterday
+    #[arg(long)]
+    pub cals: Option<u32>,
+
+    /// Protein in g
+    #[arg(long)]
+    pub protein: Option<u32>,
+
+    /// Strava relative effort score
+    #[arg(long)]
+    pub strava_re: Option<u32>,
+
+    /// Body fat % read off the scale
+    #[arg(long, conflicts_with = "no_scale")]
+    pub body_fat: Option<f64>,
+
+    /// Replace the weigh-in already logged for the day
+    #[arg(long, conflicts_with = "again")]
+    pub overwrite: bool,
+
+    /// Add another scale reading to a day that already has one
+    #[arg(long)]
+    pub again: bool,
+
+    /// Log the day's calories without a scale reading
+    #[arg(long, conflicts_with_all = ["weight", "again"])]
+    pub no_scale: bool,
+}
+
+#[derive(ClapArgs, Debug)]
+pub struct DebtArgs {
+    /// Balance of one `[[debts]]` account, e.g. --balance discover=1234.56;
+    /// repeatable, accounts not given are prompted for
+    #[arg(long = "balance", value_parser = crate::debt::parse_balance)]
+    pub balances: Vec<(String, f64)>,
+}
+
+#[derive(ClapArgs, Debug)]
+pub struct AssetArgs {
+    /// Balance of one `[[assets]]` account, e.g. --balance savings=2500;
+    /// repeatable, accounts not given are prompted for
+    #[arg(long = "balance", value_parser = crate::debt::parse_balance)]
+    pub balances: Vec<(String, f64)>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ScheduleCommand {
+    /// Print a day's schedule to stdout
+    Show {
+        #[arg(value_enum)]
+        day: Day,
+    },
+
+    /// Write tomorrow's note into the vault
+    Next,
+
+    /// Print the ISO week number
+    WeekNo,
+
+    /// Print tomorrow's lifts
+    Lift,
+
+    /// Print a day-by-day stub for a month
+    Month {
+        /// Month number (1-12)
+        #[arg(long)]
+        month: Option<u32>,
+    },
+
+    /// Print a makeup routine
+    Makeup,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ModelCommand {
+    /// 2-state Kalman model with deltas and calorie recommendation
+    Run,
+
+    /// Per-day estimates only
+    OneState,
+
+    /// Body-composition EKF: fat mass, lean mass and TDEE trends
+    Bodycomp,
+
+    /// Fit the filter noise and initial TDEE to the history by maximum likelihood
+    Tune {
+        /// Tune the 3-state filter (with kcal per Strava RE) instead
+        #[arg(long)]
+        three_state: bool,
+        /// Write the fitted values to the config file
+        #[arg(long)]
+        save: bool,
+    },
+
+    /// Score each model's 1- and 7-day-ahead weight predictions over the history
+    Backtest {
+        /// Days each model runs before its predictions are scored
+        #[arg(long, default_value_t = 14)]
+        warmup: usize,
+    },
+
+    /// Project when the goal weight will be reached, with intervals
+    Forecast {
+        /// Goal weight in the logged unit; defaults to model.goal_weight
+        #[arg(long)]
+        goal: Option<f64>,
+        /// Daily intake to project with; defaults to the last 7 days' average
+        #[arg(long)]
+        intake: Option<f64>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ReportCommand {
+    /// Spend summary and rowing prediction
+    All,
+
+    /// Spend for the last seven days
+    Spend,
+
+    /// 2k prediction and VO2 estimate from the erg log
+    Rowing,
+
+    /// Time spent on study and work today
+    Work,
+
+    /// Self-contained HTML page of charts, for offline viewing or the site
+    Html {
+        /// Directory to write index.html into
+        #[arg(long)]
+        out: PathBuf,
+        /// First date to chart; defaults to 90 days ago
+        #[arg(long)]
+        since: Option<NaiveDate>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum DebtCommand {
+    /// Simulate paying the debt off with a fixed monthly budget
+    Plan {
+        /// Total paid towards debt each month
+        #[arg(long)]
+        budget: f64,
+        /// Payoff order to print the month-by-month schedule for
+        #[arg(long, value_enum, default_value_t = Strategy::Avalanche)]
+        strategy: Strategy,
+        /// Accounts to pay off first for the custom order, e.g. affirm,discover
+        #[arg(long, value_delimiter = ',')]
+        order: Vec<String>,
+    },
+
+    /// Balances over time, paydown rate and projected debt-free date
+    Report,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum BudgetCommand {
+    /// This month's spend against each budget, with a month-end projection
+    Status,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum FinanceCommand {
+    /// Monthly cash flow, savings rate and net worth
+    Statement {
+        /// Number of months to show, ending with this one
+        #[arg(long, default_value_t = 12, value_parser = clap::value_parser!(u32).range(1..))]
+        months: u32,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum RecurringCommand {
+    /// Start tracking an expense that repeats
+    Add {
+        name: String,
+        amount: f64,
+        /// Spend category the payments are logged under
+        #[arg(long)]
+        category: String,
+        #[arg(long, value_enum, default_value_t = Cadence::Monthly)]
+        cadence: Cadence,
+        /// Due every this many cadence units, e.g. --cadence weekly --every 4
+        #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+        every: u32,
+        /// First due date
+        #[arg(long)]
+        next: NaiveDate,
+        /// Last possible due date, for installments
+        #[arg(long)]
+        until: Option<NaiveDate>,
+    },
+
+    /// Each expense with its next due date and yearly cost
+    List,
+
+    /// Add every payment that has come due to spend
+    Materialize,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cadence {
+    Weekly,
+    Monthly,
+    Quarterly,
+    Yearly,
+}
+
+/// Which account gets the money left after the minimum payments
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Strategy {
+    /// Highest APR first
+    Avalanche,
+    /// Smallest balance first
+    Snowball,
+    /// The accounts named by --order first, then highest APR
+    Custom,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ChartCommand {
+    /// Scale readings and smoothed weight, with TDEE underneath
+    Weight(ChartArgs),
+
+    /// Daily spend
+    Spend(ChartArgs),
+
+    /// 500 m split of each row
+    Rowing(ChartArgs),
+}
+
+#[derive(ClapArgs, Debug)]
+pub struct ChartArgs {
+    /// First date to plot (YYYY-MM-DD)
+    #[arg(long)]
+    pub since: Option<NaiveDate>,
+
+    /// Last date to plot (YYYY-MM-DD)
+    #[arg(long)]
+    pub until: Option<NaiveDate>,
+
+    /// Chart width in characters
+    #[arg(long, default_value_t = 72, value_parser = clap::value_parser!(u16).range(8..))]
+    pub width: u16,
+}
+
+/// How the model and report commands print their results
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Format {
+    /// Human-readable text
+    #[default]
+    Table,
+    /// One JSON document with everything the text shows
+    Json,
+    /// The command's main table only, with a header row
+    Csv,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum Day {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+impl From<Day> for Weekday {
+    fn from(day: Day) -> Self {
+        match day {
+            Day::Monday => Weekday::Mon,
+            Day::Tuesday => Weekday::Tue,
+            Day::Wednesday => Weekday::Wed,
+            Day::Thursday => Weekday::Thu,
+            Day::Friday => Weekday::Fri,
+            Day::Saturday => Weekday::Sat,
+            Day::Sunday => Weekday::Sun,
+        }
+    }
+}
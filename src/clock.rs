@@ -0,0 +1,73 @@
+//! Process-wide clock
+//!
+//! Everything that needs "now" or "today" asks this module instead of calling
+//! `Local::now()`, so `--date`/`--at` can pin the whole run to another moment
+//! (back-filling a weigh-in, regenerating an old note).
+
+use chrono::{Local, NaiveDate, NaiveDateTime, NaiveTime};
+use std::sync::OnceLock;
+
+pub trait Clock: Send + Sync {
+    fn now(&self) -> NaiveDateTime;
+}
+
+/// Local wall-clock time
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> NaiveDateTime {
+        Local::now().naive_local()
+    }
+}
+
+/// Always reports the same moment
+pub struct FixedClock(pub NaiveDateTime);
+
+impl Clock for FixedClock {
+    fn now(&self) -> NaiveDateTime {
+        self.0
+    }
+}
+
+static CLOCK: OnceLock<Box<dyn Clock>> = OnceLock::new();
+
+/// Install the clock for this run. Only the first call has an effect.
+pub fn set(clock: Box<dyn Clock>) {
+    CLOCK.set(clock).ok();
+}
+
+/// Pick the clock from the `--date`/`--at` overrides. A bare date keeps the
+/// current time of day so entries still sort in the order they were made.
+pub fn from_overrides(date: Option<NaiveDate>, at: Option<NaiveDateTime>) -> Box<dyn Clock> {
+    match (at, date) {
+        (Some(at), _) => Box::new(FixedClock(at)),
+        (None, Some(date)) => Box::new(FixedClock(date.and_time(Local::now().time()))),
+        (None, None) => Box::new(SystemClock),
+    }
+}
+
+pub fn now() -> NaiveDateTime {
+    CLOCK.get_or_init(|| Box::new(SystemClock)).now()
+}
+
+pub fn today() -> NaiveDate {
+    now().date()
+}
+
+/// `now()` in the format stored in the `date_time` columns
+pub fn timestamp() -> String {
+    now().format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+/// Parse `--at`: `YYYY-MM-DD HH:MM[:SS]` (a `T` separator also works)
+pub fn parse_timestamp(s: &str) -> Result<NaiveDateTime, String> {
+    let s = s.trim().replacen('T', " ", 1);
+    for fmt in ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M"] {
+        if let Ok(dt) = NaiveDateTime::parse_from_str(&s, fmt) {
+            return Ok(dt);
+        }
+    }
+    NaiveDate::parse_from_str(&s, "%Y-%m-%d")
+        .map(|d| d.and_time(NaiveTime::MIN))
+        .map_err(|_| format!("expected YYYY-MM-DD HH:MM[:SS], got {s}"))
+}
@@ -0,0 +1,660 @@
+//! Layered configuration
+//!
+//! Values are resolved in order, later layers winning:
+//!   1. built-in defaults (the values that used to be hard-coded)
+//!   2. TOML file: `--config PATH`, else `$EXIT_STRATEGY_CONFIG`, else
+//!      `$XDG_CONFIG_HOME/exit_strategy/config.toml` (`~/.config/...`)
+//!   3. `EXIT_STRATEGY_*` environment variables, including ones set in `.env`
+
+use anyhow::{Context, Result, bail};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+static CONFIG_PATH: OnceLock<PathBuf> = OnceLock::new();
+
+const ENV_PREFIX: &str = "EXIT_STRATEGY_";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Obsidian folder the daily notes are written to
+    pub vault_dir: String,
+    /// Directory holding links.db, the weight CSV and the text lists
+    pub data_dir: String,
+    pub sync: SyncConfig,
+    pub publish: PublishConfig,
+    pub model: ModelConfig,
+    /// Kinds accepted by `log activity`
+    pub activities: Vec<ActivityConfig>,
+    /// Accounts `log debt` asks for and `debt plan` pays off
+    pub debts: Vec<DebtAccount>,
+    /// Accounts `log asset` asks for; they count towards net worth
+    pub assets: Vec<AssetAccount>,
+    /// Monthly spend limits for `budget status`
+    pub budget: BudgetConfig,
+    /// How `import statement` reads bank exports
+    pub statement: StatementConfig,
+    /// How `finance statement` reads spend
+    pub finance: FinanceConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SyncConfig {
+    /// Bucket the data directory is backed up to
+    pub bucket: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PublishConfig {
+    /// Hugo site directory
+    pub site_dir: String,
+    /// Bucket the built site is uploaded to
+    pub bucket: String,
+    /// CloudFront distribution in front of the bucket
+    pub distribution_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModelConfig {
+    /// Starting guess for maintenance calories
+    pub initial_tdee: f64,
+    /// Starting weight state in kg
+    pub initial_weight_kg: f64,
+    /// Whether logged weights are in lb rather than kg
+    pub weight_in_pounds: bool,
+    /// Weight `model forecast` projects towards, in the logged unit
+    pub goal_weight: f64,
+    /// How `model bodycomp` splits weight change between fat and lean mass
+    pub fat_partition: FatPartition,
+    /// Fat share of each kg gained or lost when `fat_partition = "constant"`
+    pub fat_fraction: f64,
+    /// Noise of the 2-state filter; `model tune --save` writes these
+    pub kalman2d: Kalman2DNoise,
+    /// Noise of the 3-state filter; `model tune --three-state --save`
+    pub kalman3d: Kalman3DNoise,
+    /// Daily calorie target set by `model run`
+    pub controller: ControllerConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ControllerConfig {
+    /// Loss to aim for, as % of body weight per week
+    pub weekly_loss_pct: f64,
+    /// How far the target may move per week, kcal
+    pub max_change_per_week: f64,
+    /// The TDEE the target is built on is this many standard deviations
+    /// below the estimate, so a shaky estimate errs towards a deficit
+    pub tdee_caution_sd: f64,
+    /// The target never goes below this, kcal
+    pub min_intake: f64,
+    /// Weeks of dieting between diet breaks
+    pub diet_weeks: u32,
+    /// Weeks eating at maintenance after each stretch; 0 turns breaks off
+    pub break_weeks: u32,
+}
+
+/// Standard deviations for the 2-state weight filter
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Kalman2DNoise {
+    /// Scale reading noise, kg
+    pub scale_sd: f64,
+    /// Daily weight drift beyond the energy balance, kg
+    pub weight_drift_sd: f64,
+    /// Daily TDEE drift, kcal
+    pub tdee_drift_sd: f64,
+    /// Uncertainty of `initial_tdee`, kcal
+    pub tdee_prior_sd: f64,
+}
+
+/// Standard deviations for the 3-state weight filter
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Kalman3DNoise {
+    /// Scale reading noise, kg
+    pub scale_sd: f64,
+    /// Daily weight drift beyond the energy balance, kg
+    pub weight_drift_sd: f64,
+    /// Daily TDEE drift, kcal
+    pub tdee_drift_sd: f64,
+    /// Daily drift of the kcal per Strava relative effort point
+    pub re_drift_sd: f64,
+    /// Uncertainty of `initial_tdee`, kcal
+    pub tdee_prior_sd: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FatPartition {
+    /// Forbes' curve: the fat share shrinks as fat mass does
+    Forbes,
+    /// Always `fat_fraction`
+    Constant,
+}
+
+impl std::str::FromStr for FatPartition {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::result::Result<Self, ()> {
+        match s {
+            "forbes" => Ok(FatPartition::Forbes),
+            "constant" => Ok(FatPartition::Constant),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActivityConfig {
+    /// Key stored in the `event.kind` column
+    pub name: String,
+    /// Text shown in summaries
+    pub label: String,
+    /// Unit of the logged quantity, e.g. "min" or "mg"
+    #[serde(default = "default_unit")]
+    pub unit: String,
+    /// Relative chance of being picked by `bored`; 0 never picks it
+    #[serde(default)]
+    pub bored_weight: f64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StatementConfig {
+    pub csv: StatementCsv,
+}
+
+/// Column headers of a bank's CSV export
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StatementCsv {
+    pub date: String,
+    /// chrono format of the date column
+    pub date_format: String,
+    pub description: String,
+    /// Signed amount column; leave empty for files with debit and credit columns
+    pub amount: String,
+    /// Whether purchases are negative in `amount`
+    pub purchases_negative: bool,
+    pub debit: String,
+    pub credit: String,
+}
+
+impl Default for StatementCsv {
+    fn default() -> Self {
+        StatementCsv {
+            date: "Date".to_string(),
+            date_format: "%m/%d/%Y".to_string(),
+            description: "Description".to_string(),
+            amount: "Amount".to_string(),
+            purchases_negative: true,
+            debit: String::new(),
+            credit: String::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BudgetConfig {
+    /// First day budgets apply from; rollover counts months from here.
+    /// Unset means the current month, so nothing rolls over
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start: Option<NaiveDate>,
+    pub categories: Vec<CategoryBudget>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CategoryBudget {
+    /// Matches `spend.category` exactly
+    pub category: String,
+    /// Amount allotted each month
+    pub monthly: f64,
+    /// What happens to the month's leftover (or overspend) at month end
+    #[serde(default)]
+    pub rollover: Rollover,
+    /// Most that can be carried into a month
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_rollover: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Rollover {
+    /// Every month starts from `monthly`
+    #[default]
+    None,
+    /// Unspent money carries over, overspending doesn't
+    Unspent,
+    /// Unspent money adds to next month and overspending comes out of it
+    Full,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DebtAccount {
+    /// Key stored in the `debt_balance.account` column
+    pub name: String,
+    /// Text shown in summaries
+    pub label: String,
+    /// Annual interest rate in percent
+    #[serde(default)]
+    pub apr: f64,
+    /// Minimum monthly payment
+    #[serde(default)]
+    pub min_payment: f64,
+    /// Day of the month the payment is due
+    #[serde(default = "default_due_day")]
+    pub due_day: u32,
+}
+
+fn default_due_day() -> u32 {
+    1
+}
+
+impl DebtAccount {
+    fn new(name: &str, label: &str) -> Self {
+        DebtAccount {
+            name: name.to_string(),
+            label: label.to_string(),
+            apr: 0.0,
+            min_payment: 0.0,
+            due_day: default_due_day(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AssetAccount {
+    /// Key stored in the `asset_balance.account` column
+    pub name: String,
+    /// Text shown in summaries
+    pub label: String,
+}
+
+impl AssetAccount {
+    fn new(name: &str, label: &str) -> Self {
+        AssetAccount {
+            name: name.to_string(),
+            label: label.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FinanceConfig {
+    /// Spend categories that are payments towards debt rather than spending
+    pub debt_categories: Vec<String>,
+}
+
+impl Default for FinanceConfig {
+    fn default() -> Self {
+        FinanceConfig {
+            debt_categories: vec!["debt".to_string()],
+        }
+    }
+}
+
+fn default_unit() -> String {
+    "min".to_string()
+}
+
+impl ActivityConfig {
+    fn new(name: &str, label: &str, unit: &str, bored_weight: f64) -> Self {
+        ActivityConfig {
+            name: name.to_string(),
+            label: label.to_string(),
+            unit: unit.to_string(),
+            bored_weight,
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            vault_dir: "/home/emma/Documents/life/Daily".to_string(),
+            data_dir: "data".to_string(),
+            sync: SyncConfig::default(),
+            publish: PublishConfig::default(),
+            model: ModelConfig::default(),
+            activities: vec![
+                ActivityConfig::new("reading_pleasure", "Reading fun book", "min", 0.0),
+                ActivityConfig::new("reading_technical", "Reading the rust book", "min", 0.15),
+                ActivityConfig::new("link", "Reading security articles", "min", 0.15),
+                ActivityConfig::new("ccna", "Studying for the CCNA", "min", 0.42),
+                ActivityConfig::new("pwn_college", "Studying on pwn.college", "min", 0.28),
+                ActivityConfig::new("exit", "Working on todo app", "min", 0.0),
+                ActivityConfig::new("big_game_hunting", "Big game hunting", "min", 0.0),
+                ActivityConfig::new("caffeine", "Caffeine", "mg", 0.0),
+            ],
+            debts: vec![
+                DebtAccount::new("bofa_travel", "BofA Travel"),
+                DebtAccount::new("bofa_reward", "BofA Rewards"),
+                DebtAccount::new("discover", "Discover"),
+                DebtAccount::new("imprint", "Imprint"),
+                DebtAccount::new("paypal", "PayPal"),
+                DebtAccount::new("affirm", "Affirm"),
+                DebtAccount::new("cisco", "Cisco"),
+                DebtAccount::new("medical", "Medical"),
+                DebtAccount::new("schwab", "Schwab"),
+            ],
+            assets: vec![
+                AssetAccount::new("checking", "Checking"),
+                AssetAccount::new("savings", "Savings"),
+            ],
+            budget: BudgetConfig::default(),
+            statement: StatementConfig::default(),
+            finance: FinanceConfig::default(),
+        }
+    }
+}
+
+impl Default for SyncConfig {
+    fn default() -> Self {
+        SyncConfig {
+            bucket: "veryonlineguy".to_string(),
+        }
+    }
+}
+
+impl Default for PublishConfig {
+    fn default() -> Self {
+        PublishConfig {
+            site_dir: "veryonlineguy.com".to_string(),
+            bucket: "veryonlineguy.com".to_string(),
+            distribution_id: "E1FCC9GGT96QM6".to_string(),
+        }
+    }
+}
+
+impl Default for ModelConfig {
+    fn default() -> Self {
+        ModelConfig {
+            initial_tdee: 3_100.0,
+            initial_weight_kg: 127.0,
+            weight_in_pounds: true,
+            goal_weight: 200.0,
+            fat_partition: FatPartition::Forbes,
+            fat_fraction: 0.75,
+            kalman2d: Kalman2DNoise::default(),
+            kalman3d: Kalman3DNoise::default(),
+            controller: ControllerConfig::default(),
+        }
+    }
+}
+
+impl Default for ControllerConfig {
+    fn default() -> Self {
+        ControllerConfig {
+            weekly_loss_pct: 0.75,
+            max_change_per_week: 150.0,
+            tdee_caution_sd: 0.5,
+            min_intake: 1_500.0,
+            diet_weeks: 10,
+            break_weeks: 1,
+        }
+    }
+}
+
+impl Default for Kalman2DNoise {
+    fn default() -> Self {
+        Kalman2DNoise {
+            scale_sd: 0.3,
+            weight_drift_sd: 0.0,
+            tdee_drift_sd: 2.0,
+            tdee_prior_sd: 600.0,
+        }
+    }
+}
+
+impl Default for Kalman3DNoise {
+    fn default() -> Self {
+        Kalman3DNoise {
+            scale_sd: 0.6,
+            weight_drift_sd: 0.0,
+            tdee_drift_sd: 12.0,
+            re_drift_sd: 2.0,
+            tdee_prior_sd: 200.0,
+        }
+    }
+}
+
+/// Default location of the config file
+pub fn default_path() -> PathBuf {
+    if let Ok(path) = env::var(format!("{ENV_PREFIX}CONFIG")) {
+        return PathBuf::from(path);
+    }
+
+    let base = match env::var("XDG_CONFIG_HOME") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var("HOME").unwrap_or_else(|_| ".".to_string())).join(".config"),
+    };
+    base.join("exit_strategy").join("config.toml")
+}
+
+/// Resolve the configuration once at startup. Must be called before `get`.
+pub fn init(path: Option<PathBuf>) -> Result<()> {
+    dotenv::dotenv().ok();
+
+    let explicit = path.is_some();
+    let path = path.unwrap_or_else(default_path);
+
+    let mut config = if path.exists() {
+        let contents =
+            fs::read_to_string(&path).with_context(|| format!("reading {}", path.display()))?;
+        toml::from_str(&contents).with_context(|| format!("parsing {}", path.display()))?
+    } else if explicit {
+        bail!("config file {} does not exist", path.display());
+    } else {
+        Config::default()
+    };
+
+    apply_env(&mut config)?;
+
+    CONFIG_PATH.set(path).ok();
+    CONFIG.set(config).ok();
+    Ok(())
+}
+
+/// The resolved configuration
+pub fn get() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
+
+impl Config {
+    pub fn activity(&self, name: &str) -> Option<&ActivityConfig> {
+        self.activities.iter().find(|a| a.name == name)
+    }
+
+    pub fn debt(&self, name: &str) -> Option<&DebtAccount> {
+        self.debts.iter().find(|d| d.name == name)
+    }
+
+    pub fn asset(&self, name: &str) -> Option<&AssetAccount> {
+        self.assets.iter().find(|a| a.name == name)
+    }
+}
+
+/// Path the configuration was (or would be) read from
+pub fn path() -> PathBuf {
+    CONFIG_PATH.get().cloned().unwrap_or_else(default_path)
+}
+
+fn apply_env(config: &mut Config) -> Result<()> {
+    fn var(name: &str) -> Option<String> {
+        env::var(format!("{ENV_PREFIX}{name}")).ok()
+    }
+
+    fn parse<T: std::str::FromStr>(name: &str, value: String) -> Result<T> {
+        value
+            .parse()
+            .map_err(|_| anyhow::anyhow!("invalid value for {ENV_PREFIX}{name}: {value}"))
+    }
+
+    if let Some(v) = var("VAULT_DIR") {
+        config.vault_dir = v;
+    }
+    if let Some(v) = var("DATA_DIR") {
+        config.data_dir = v;
+    }
+    if let Some(v) = var("SYNC_BUCKET") {
+        config.sync.bucket = v;
+    }
+    if let Some(v) = var("SITE_DIR") {
+        config.publish.site_dir = v;
+    }
+    if let Some(v) = var("SITE_BUCKET") {
+        config.publish.bucket = v;
+    }
+    if let Some(v) = var("DISTRIBUTION_ID") {
+        config.publish.distribution_id = v;
+    }
+    if let Some(v) = var("INITIAL_TDEE") {
+        config.model.initial_tdee = parse("INITIAL_TDEE", v)?;
+    }
+    if let Some(v) = var("INITIAL_WEIGHT_KG") {
+        config.model.initial_weight_kg = parse("INITIAL_WEIGHT_KG", v)?;
+    }
+    if let Some(v) = var("WEIGHT_IN_POUNDS") {
+        config.model.weight_in_pounds = parse("WEIGHT_IN_POUNDS", v)?;
+    }
+    if let Some(v) = var("GOAL_WEIGHT") {
+        config.model.goal_weight = parse("GOAL_WEIGHT", v)?;
+    }
+    if let Some(v) = var("WEEKLY_LOSS_PCT") {
+        config.model.controller.weekly_loss_pct = parse("WEEKLY_LOSS_PCT", v)?;
+    }
+    if let Some(v) = var("FAT_PARTITION") {
+        config.model.fat_partition = parse("FAT_PARTITION", v)?;
+    }
+    if let Some(v) = var("FAT_FRACTION") {
+        config.model.fat_fraction = parse("FAT_FRACTION", v)?;
+    }
+    Ok(())
+}
+
+/// Print the effective configuration as TOML
+pub fn show() -> Result<()> {
+    let path = path();
+    let source = if path.exists() {
+        ""
+    } else {
+        " (not found, using defaults)"
+    };
+    println!("# {}{}", path.display(), source);
+    print!("{}", toml::to_string_pretty(get())?);
+    Ok(())
+}
+
+/// Write the default configuration to the config path
+pub fn write_default(force: bool) -> Result<()> {
+    let path = path();
+    if path.exists() && !force {
+        bail!(
+            "{} already exists, pass --force to overwrite",
+            path.display()
+        );
+    }
+    save(&Config::default(), &path)?;
+    println!("Wrote {}", path.display());
+    Ok(())
+}
+
+/// Change the config file in place, leaving environment overrides out of
+/// it. Only the keys whose value changed are rewritten, so comments and the
+/// order of the rest of the file stay as they were. Starts from the
+/// defaults when there is no file yet.
+pub fn update(change: impl FnOnce(&mut Config)) -> Result<PathBuf> {
+    let path = path();
+    if !path.exists() {
+        let mut config = Config::default();
+        change(&mut config);
+        save(&config, &path)?;
+        return Ok(path);
+    }
+
+    let contents =
+        fs::read_to_string(&path).with_context(|| format!("reading {}", path.display()))?;
+    let before: Config =
+        toml::from_str(&contents).with_context(|| format!("parsing {}", path.display()))?;
+    let mut after = before.clone();
+    change(&mut after);
+
+    let mut doc: toml_edit::DocumentMut = contents
+        .parse()
+        .with_context(|| format!("parsing {}", path.display()))?;
+    let (toml::Value::Table(before), toml::Value::Table(after)) = (
+        toml::Value::try_from(&before)?,
+        toml::Value::try_from(&after)?,
+    ) else {
+        bail!("config doesn't serialize to a table");
+    };
+    write_changes(doc.as_table_mut(), &before, &after)?;
+    fs::write(&path, doc.to_string())?;
+    Ok(path)
+}
+
+/// Set the keys of `table` that differ between `before` and `after`,
+/// descending into sub-tables so untouched siblings keep their formatting
+fn write_changes(
+    table: &mut dyn toml_edit::TableLike,
+    before: &toml::Table,
+    after: &toml::Table,
+) -> Result<()> {
+    for (key, new) in after {
+        let old = before.get(key);
+        if old == Some(new) {
+            continue;
+        }
+        if let (Some(toml::Value::Table(old)), toml::Value::Table(new)) = (old, new) {
+            if table.get(key).is_none() {
+                // only the changed keys get a header, not the whole section
+                let mut sub = toml_edit::Table::new();
+                sub.set_implicit(true);
+                table.insert(key, toml_edit::Item::Table(sub));
+            }
+            if let Some(sub) = table.get_mut(key).and_then(|item| item.as_table_like_mut()) {
+                write_changes(sub, old, new)?;
+                continue;
+            }
+        }
+        let mut value: toml_edit::Value = new
+            .to_string()
+            .parse()
+            .with_context(|| format!("writing config key '{key}'"))?;
+        match table.get_mut(key).and_then(|item| item.as_value_mut()) {
+            // keeps a trailing comment on the line
+            Some(existing) => {
+                *value.decor_mut() = existing.decor().clone();
+                *existing = value;
+            }
+            None => {
+                table.insert(key, toml_edit::value(value));
+            }
+        }
+    }
+    for key in before.keys() {
+        if !after.contains_key(key) {
+            table.remove(key);
+        }
+    }
+    Ok(())
+}
+
+fn save(config: &Config, path: &Path) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, toml::to_string_pretty(config)?)?;
+    Ok(())
+}
@@ -0,0 +1,386 @@
+//! Static HTML dashboard
+//!
+//! `report html` draws the logged data as inline SVG charts on one page.
+//! Nothing is fetched when it is opened (no scripts, stylesheets or fonts),
+//! so it works offline and can be copied into the site's `static` directory
+//! as it is.
+
+use crate::clock;
+use crate::config;
+use crate::db;
+use crate::finance;
+use crate::model1::{self, Kalman2D, display_weight, weight_unit};
+use crate::recurring;
+use crate::weight;
+use anyhow::{Context, Result, bail};
+use chrono::{Days, NaiveDate};
+use rusqlite::{Connection, Params};
+use std::fs;
+use std::path::Path;
+
+/// Days shown when no start date is given
+const DEFAULT_DAYS: u64 = 90;
+
+/// SVG size in user units, and the margins kept for the axis labels
+const WIDTH: f64 = 720.0;
+const HEIGHT: f64 = 220.0;
+const LEFT: f64 = 60.0;
+const RIGHT: f64 = 12.0;
+const TOP: f64 = 10.0;
+const BOTTOM: f64 = 24.0;
+
+/// Series colours, given out in order
+const PALETTE: [&str; 8] = [
+    "#1f77b4", "#ff7f0e", "#2ca02c", "#d62728", "#9467bd", "#8c564b", "#e377c2", "#7f7f7f",
+];
+
+const STYLE: &str = "body{font-family:sans-serif;max-width:760px;margin:2em auto;padding:0 1em;color:#222}\
+svg{width:100%;height:auto}text{font-size:11px;fill:#555}.grid{stroke:#e5e5e5}\
+.key span{margin-right:1.2em;font-size:.9em}.key i{display:inline-block;width:.8em;height:.8em;margin-right:.3em}";
+
+enum Style {
+    Line,
+    Dots,
+    /// Bars of every `Bars` series in a chart are stacked
+    Bars,
+}
+
+struct Series {
+    name: String,
+    style: Style,
+    color: &'static str,
+    points: Vec<(NaiveDate, f64)>,
+}
+
+struct Chart {
+    title: String,
+    /// Formats the y-axis labels
+    label: fn(f64) -> String,
+    series: Vec<Series>,
+}
+
+impl Chart {
+    fn new(title: impl Into<String>, label: fn(f64) -> String) -> Self {
+        Chart {
+            title: title.into(),
+            label,
+            series: Vec::new(),
+        }
+    }
+
+    fn add(mut self, name: impl Into<String>, style: Style, points: Vec<(NaiveDate, f64)>) -> Self {
+        let color = PALETTE[self.series.len() % PALETTE.len()];
+        self.series.push(Series {
+            name: name.into(),
+            style,
+            color,
+            points,
+        });
+        self
+    }
+
+    /// The chart as a `<section>`, plotting only `from..=to`
+    fn html(&self, from: NaiveDate, to: NaiveDate) -> String {
+        let mut out = format!("<section>\n<h2>{}</h2>\n", escape(&self.title));
+        let series: Vec<(&Series, Vec<(NaiveDate, f64)>)> = self
+            .series
+            .iter()
+            .map(|s| {
+                let points = s
+                    .points
+                    .iter()
+                    .copied()
+                    .filter(|(d, v)| (from..=to).contains(d) && v.is_finite())
+                    .collect();
+                (s, points)
+            })
+            .collect();
+        if series.iter().all(|(_, points)| points.is_empty()) {
+            out.push_str("<p>Nothing logged in this range.</p>\n</section>\n");
+            return out;
+        }
+
+        out.push_str("<p class=\"key\">");
+        for (s, _) in &series {
+            out.push_str(&format!(
+                "<span><i style=\"background:{}\"></i>{}</span>",
+                s.color,
+                escape(&s.name)
+            ));
+        }
+        out.push_str("</p>\n");
+        out.push_str(&self.svg(&series, from, to));
+        out.push_str("</section>\n");
+        out
+    }
+
+    fn svg(
+        &self,
+        series: &[(&Series, Vec<(NaiveDate, f64)>)],
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> String {
+        // Stacked bar tops, per day offset
+        let slots = (to - from).num_days() as usize + 1;
+        let mut stack = vec![0.0; slots];
+        let mut bars = Vec::new();
+        for (s, points) in series {
+            if let Style::Bars = s.style {
+                for &(date, value) in points {
+                    let slot = (date - from).num_days() as usize;
+                    bars.push((s.color, slot, stack[slot], stack[slot] + value));
+                    stack[slot] += value;
+                }
+            }
+        }
+
+        let mut values: Vec<f64> = series
+            .iter()
+            .filter(|(s, _)| !matches!(s.style, Style::Bars))
+            .flat_map(|(_, points)| points.iter().map(|p| p.1))
+            .collect();
+        if !bars.is_empty() {
+            values.push(0.0);
+            values.extend(&stack);
+        }
+        let mut min = values.iter().copied().fold(f64::INFINITY, f64::min);
+        let mut max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        if max - min < 1e-9 {
+            min -= 1.0;
+            max += 1.0;
+        }
+
+        let plot_w = WIDTH - LEFT - RIGHT;
+        let plot_h = HEIGHT - TOP - BOTTOM;
+        let slot_w = plot_w / slots as f64;
+        let x = |slot: f64| LEFT + (slot + 0.5) * slot_w;
+        let y = |v: f64| TOP + (max - v) / (max - min) * plot_h;
+
+        let mut out = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {WIDTH} {HEIGHT}\" role=\"img\" aria-label=\"{}\">\n",
+            escape(&self.title)
+        );
+
+        // Horizontal grid with five labelled levels
+        for i in 0..5 {
+            let v = min + (max - min) * i as f64 / 4.0;
+            out.push_str(&format!(
+                "<line class=\"grid\" x1=\"{LEFT}\" x2=\"{}\" y1=\"{y:.1}\" y2=\"{y:.1}\"/><text x=\"{}\" y=\"{:.1}\" text-anchor=\"end\">{}</text>\n",
+                WIDTH - RIGHT,
+                LEFT - 6.0,
+                y(v) + 4.0,
+                escape(&(self.label)(v)),
+                y = y(v)
+            ));
+        }
+        let mid = from + Days::new((slots as u64 - 1) / 2);
+        for (date, anchor) in [(from, "start"), (mid, "middle"), (to, "end")] {
+            let slot = (date - from).num_days() as f64;
+            let at = match anchor {
+                "start" => LEFT,
+                "end" => WIDTH - RIGHT,
+                _ => x(slot),
+            };
+            out.push_str(&format!(
+                "<text x=\"{at:.1}\" y=\"{}\" text-anchor=\"{anchor}\">{date}</text>\n",
+                HEIGHT - 6.0
+            ));
+        }
+
+        let bar_w = (slot_w * 0.8).max(1.0);
+        for (color, slot, low, high) in bars {
+            out.push_str(&format!(
+                "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{bar_w:.1}\" height=\"{:.1}\" fill=\"{color}\"/>\n",
+                x(slot as f64) - bar_w / 2.0,
+                y(high),
+                (y(low) - y(high)).max(0.0)
+            ));
+        }
+
+        for (s, points) in series {
+            let at = |&(date, v): &(NaiveDate, f64)| (x((date - from).num_days() as f64), y(v));
+            match s.style {
+                Style::Bars => {}
+                Style::Line => {
+                    let path: Vec<String> = points
+                        .iter()
+                        .map(at)
+                        .map(|(px, py)| format!("{px:.1},{py:.1}"))
+                        .collect();
+                    out.push_str(&format!(
+                        "<polyline fill=\"none\" stroke=\"{}\" stroke-width=\"2\" points=\"{}\"/>\n",
+                        s.color,
+                        path.join(" ")
+                    ));
+                }
+                Style::Dots => {
+                    for (px, py) in points.iter().map(at) {
+                        out.push_str(&format!(
+                            "<circle cx=\"{px:.1}\" cy=\"{py:.1}\" r=\"2\" fill=\"{}\"/>\n",
+                            s.color
+                        ));
+                    }
+                }
+            }
+        }
+
+        out.push_str("</svg>\n");
+        out
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Rows of (date, value) from a query selecting exactly those two columns
+fn by_day(conn: &Connection, sql: &str, params: impl Params) -> Result<Vec<(NaiveDate, f64)>> {
+    let mut stmt = conn.prepare(sql)?;
+    let rows = stmt.query_map(params, |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, f64>(1)?))
+    })?;
+    let mut points = Vec::new();
+    for row in rows {
+        let (date, value) = row?;
+        points.push((NaiveDate::parse_from_str(&date, "%Y-%m-%d")?, value));
+    }
+    Ok(points)
+}
+
+fn weight_charts() -> Result<[Chart; 2]> {
+    let days = model1::calendar_days(&weight::model_rows()?)?;
+    let mut kf = Kalman2D::new(config::get().model.initial_weight_kg);
+    for day in &days {
+        kf.step(day);
+    }
+    let smoothed = kf.smoothed();
+
+    let mut raw = Vec::new();
+    let mut trend = Vec::new();
+    let mut tdee = Vec::new();
+    let mut intake = Vec::new();
+    for (day, x) in days.iter().zip(&smoothed) {
+        raw.extend(
+            day.weights_kg
+                .iter()
+                .map(|&w| (day.date, display_weight(w))),
+        );
+        trend.push((day.date, display_weight(x[0])));
+        tdee.push((day.date, x[1]));
+        if day.intake_known {
+            intake.push((day.date, day.intake_kcal));
+        }
+    }
+
+    let weight = Chart::new(format!("Weight ({})", weight_unit()), |v| format!("{v:.1}"))
+        .add("scale reading", Style::Dots, raw)
+        .add("smoothed", Style::Line, trend);
+    let energy = Chart::new("TDEE and intake (kcal)", |v| format!("{v:.0}"))
+        .add("TDEE", Style::Line, tdee)
+        .add("intake", Style::Dots, intake);
+    Ok([weight, energy])
+}
+
+fn pace(seconds: f64) -> String {
+    let seconds = seconds.max(0.0).round() as u64;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+fn charts(conn: &Connection) -> Result<Vec<Chart>> {
+    let mut charts: Vec<Chart> = weight_charts()?.into();
+
+    charts.push(Chart::new("Daily spend", |v| format!("${v:.0}")).add(
+        "spend",
+        Style::Bars,
+        by_day(
+            conn,
+            "SELECT date(date_time), SUM(amount) FROM spend GROUP BY date(date_time) ORDER BY date(date_time)",
+            [],
+        )?,
+    ));
+
+    charts.push(Chart::new("Debt balance", |v| format!("${v:.0}")).add(
+        "total across accounts",
+        Style::Line,
+        by_day(
+            conn,
+            "SELECT date(date_time), SUM(balance) FROM debt_balance GROUP BY date_time ORDER BY date_time",
+            [],
+        )?,
+    ));
+
+    charts.push(Chart::new("Net worth", |v| format!("${v:.0}")).add(
+        "assets minus debt",
+        Style::Line,
+        finance::net_worth_history(conn)?,
+    ));
+
+    // `time` is logged in minutes, as `chart rowing` reads it
+    charts.push(Chart::new("Rowing pace per 500 m (lower is faster)", pace).add(
+        "split",
+        Style::Line,
+        by_day(
+            conn,
+            "SELECT date(date_time), time * 60.0 * 500.0 / distance FROM row WHERE distance > 0 ORDER BY date_time",
+            [],
+        )?,
+    ));
+
+    let mut study = Chart::new("Study time (min)", |v| format!("{v:.0}"));
+    for activity in config::get().activities.iter().filter(|a| a.unit == "min") {
+        study = study.add(
+            activity.label.as_str(),
+            Style::Bars,
+            by_day(
+                conn,
+                "SELECT date(date_time), SUM(quantity) FROM event WHERE kind = ?1 GROUP BY date(date_time)",
+                [&activity.name],
+            )?,
+        );
+    }
+    charts.push(study);
+
+    let count = |table: &str| {
+        by_day(
+            conn,
+            &format!("SELECT date(date_time), COUNT(*) FROM {table} GROUP BY date(date_time)"),
+            [],
+        )
+    };
+    charts.push(
+        Chart::new("Habits (per day)", |v| format!("{v:.0}"))
+            .add("tasks done", Style::Bars, count("completed_todos")?)
+            .add("wins", Style::Dots, count("win")?)
+            .add("violations", Style::Dots, count("violations")?),
+    );
+
+    Ok(charts)
+}
+
+/// `report html`: write `index.html` into `out`
+pub fn write(out: &Path, since: Option<NaiveDate>) -> Result<()> {
+    let to = clock::today();
+    let from = since.unwrap_or(to - Days::new(DEFAULT_DAYS));
+    if from > to {
+        bail!("--since {from} is after today ({to})");
+    }
+    let mut conn = db::open()?;
+    recurring::catch_up(&mut conn)?;
+
+    let mut page = format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>Dashboard</title>\n<style>{STYLE}</style>\n</head>\n<body>\n<h1>Dashboard</h1>\n<p>{from} to {to}</p>\n"
+    );
+    for chart in charts(&conn)? {
+        page.push_str(&chart.html(from, to));
+    }
+    page.push_str("</body>\n</html>\n");
+
+    fs::create_dir_all(out).with_context(|| format!("creating {}", out.display()))?;
+    let path = out.join("index.html");
+    fs::write(&path, page).with_context(|| format!("writing {}", path.display()))?;
+    println!("Wrote {}", path.display());
+    Ok(())
+}
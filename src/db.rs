@@ -0,0 +1,409 @@
+//! Schema for `links.db`
+//!
+//! Every table is defined here as a numbered migration. `PRAGMA user_version`
+//! records the last one applied; `open` brings the database up to date before
+//! handing out a connection. Append new migrations to the end of `MIGRATIONS`,
+//! never edit one that has shipped.
+
+use crate::util;
+use rusqlite::{Connection, Result};
+
+struct Migration {
+    name: &'static str,
+    sql: &'static str,
+}
+
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        // Tables as they were created ad hoc by logging.rs. IF NOT EXISTS so
+        // databases that predate user_version adopt the schema untouched.
+        name: "initial tables",
+        sql: "
+        CREATE TABLE IF NOT EXISTS reading (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            date_time TEXT NOT NULL,
+            time INTEGER NOT NULL,
+            type TEXT NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS time (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            date_time TEXT NOT NULL,
+            category TEXT NOT NULL,
+            time INTEGER NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS violations (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            date_time TEXT NOT NULL,
+            name TEXT NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS win (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            date_time TEXT NOT NULL,
+            name TEXT NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS big_game_hunting (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            date_time TEXT NOT NULL,
+            time INTEGER NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS link (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            date_time TEXT NOT NULL,
+            time INTEGER NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS debt (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            date_time TEXT NOT NULL,
+            bofa_travel REAL NOT NULL,
+            bofa_reward REAL NOT NULL,
+            discover REAL NOT NULL,
+            imprint REAL NOT NULL,
+            paypal REAL NOT NULL,
+            affirm REAL NOT NULL,
+            cisco REAL NOT NULL,
+            medical REAL NOT NULL,
+            schwab REAL NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS ccna (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            date_time TEXT NOT NULL,
+            time INTEGER NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS pwn_college (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            date_time TEXT NOT NULL,
+            time INTEGER NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS spend (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            date_time TEXT NOT NULL,
+            amount REAL NOT NULL,
+            name TEXT NOT NULL,
+            category TEXT NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS exit (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            date_time TEXT NOT NULL,
+            time INTEGER NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS caffeine (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            date_time TEXT NOT NULL,
+            caffeine_mg INTEGER NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS row (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            date_time TEXT NOT NULL,
+            distance INTEGER NOT NULL,
+            time INTEGER NOT NULL,
+            watts INTEGER NOT NULL,
+            cals INTEGER NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS completed_todos (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            task_id INTEGER NOT NULL,
+            date_time TEXT NOT NULL,
+            task TEXT NOT NULL
+        );
+    ",
+    },
+    Migration {
+        // One table for every timed or counted activity. The per-activity
+        // tables are copied in and dropped; reading splits on its type.
+        name: "event store",
+        sql: "
+        CREATE TABLE event (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            kind TEXT NOT NULL,
+            date_time TEXT NOT NULL,
+            quantity REAL NOT NULL,
+            unit TEXT NOT NULL,
+            tags TEXT NOT NULL DEFAULT '',
+            note TEXT
+        );
+        CREATE INDEX event_kind_date_time ON event (kind, date_time);
+
+        INSERT INTO event (kind, date_time, quantity, unit)
+            SELECT 'reading_' || type, date_time, time, 'min' FROM reading;
+        INSERT INTO event (kind, date_time, quantity, unit)
+            SELECT 'link', date_time, time, 'min' FROM link;
+        INSERT INTO event (kind, date_time, quantity, unit)
+            SELECT 'ccna', date_time, time, 'min' FROM ccna;
+        INSERT INTO event (kind, date_time, quantity, unit)
+            SELECT 'pwn_college', date_time, time, 'min' FROM pwn_college;
+        INSERT INTO event (kind, date_time, quantity, unit)
+            SELECT 'exit', date_time, time, 'min' FROM exit;
+        INSERT INTO event (kind, date_time, quantity, unit)
+            SELECT 'big_game_hunting', date_time, time, 'min' FROM big_game_hunting;
+        INSERT INTO event (kind, date_time, quantity, unit)
+            SELECT 'caffeine', date_time, caffeine_mg, 'mg' FROM caffeine;
+
+        DROP TABLE reading;
+        DROP TABLE link;
+        DROP TABLE ccna;
+        DROP TABLE pwn_college;
+        DROP TABLE exit;
+        DROP TABLE big_game_hunting;
+        DROP TABLE caffeine;
+    ",
+    },
+    Migration {
+        // Before/after JSON snapshots of every logged, edited or deleted
+        // entry, so `undo` can revert the latest change.
+        name: "audit log",
+        sql: "
+        CREATE TABLE audit (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            date_time TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            entry_id INTEGER NOT NULL,
+            action TEXT NOT NULL,
+            before TEXT,
+            after TEXT,
+            undone INTEGER NOT NULL DEFAULT 0
+        );
+    ",
+    },
+    Migration {
+        // Daily weigh-ins, formerly weight_energy.csv (`import csv` loads
+        // it). Audit rows for the CSV addressed it by row number, which
+        // means nothing here, so they can no longer be undone.
+        name: "weight",
+        sql: "
+        CREATE TABLE weight (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            date TEXT NOT NULL UNIQUE,
+            weight REAL NOT NULL,
+            intake_kcal REAL NOT NULL,
+            strava_re REAL NOT NULL DEFAULT 0,
+            protein REAL
+        );
+        UPDATE audit SET undone = 1 WHERE kind = 'weight';
+    ",
+    },
+    Migration {
+        // Days without a scale reading or without logged calories, and
+        // extra weigh-ins on the same day (`log weight --again`). SQLite
+        // cannot relax NOT NULL/UNIQUE in place, so the table is rebuilt.
+        name: "irregular weigh-ins",
+        sql: "
+        CREATE TABLE weight_new (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            date TEXT NOT NULL,
+            weight REAL,
+            intake_kcal REAL,
+            strava_re REAL NOT NULL DEFAULT 0,
+            protein REAL
+        );
+        INSERT INTO weight_new (id, date, weight, intake_kcal, strava_re, protein)
+            SELECT id, date, weight, intake_kcal, strava_re, protein FROM weight;
+        DROP TABLE weight;
+        ALTER TABLE weight_new RENAME TO weight;
+        CREATE INDEX weight_date ON weight (date);
+    ",
+    },
+    Migration {
+        // Body-fat % from the scale, for `model bodycomp`
+        name: "weight body fat",
+        sql: "
+        ALTER TABLE weight ADD COLUMN body_fat REAL;
+    ",
+    },
+    Migration {
+        // Daily calorie target from the controller in `model run`, with the
+        // estimate it was built on. One row per date; a rerun replaces it.
+        name: "calorie target",
+        sql: "
+        CREATE TABLE calorie_target (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            date TEXT NOT NULL UNIQUE,
+            target REAL NOT NULL,
+            tdee REAL NOT NULL,
+            tdee_sd REAL NOT NULL,
+            weight_kg REAL NOT NULL,
+            diet_break INTEGER NOT NULL DEFAULT 0
+        );
+    ",
+    },
+    Migration {
+        // One row per account and snapshot, so accounts come from the
+        // config instead of the columns. Audit rows of the old table can't
+        // be undone any more.
+        name: "debt balances",
+        sql: "
+        CREATE TABLE debt_balance (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            date_time TEXT NOT NULL,
+            account TEXT NOT NULL,
+            balance REAL NOT NULL
+        );
+        CREATE INDEX debt_balance_account_date_time ON debt_balance (account, date_time);
+
+        INSERT INTO debt_balance (date_time, account, balance)
+            SELECT date_time, 'bofa_travel', bofa_travel FROM debt;
+        INSERT INTO debt_balance (date_time, account, balance)
+            SELECT date_time, 'bofa_reward', bofa_reward FROM debt;
+        INSERT INTO debt_balance (date_time, account, balance)
+            SELECT date_time, 'discover', discover FROM debt;
+        INSERT INTO debt_balance (date_time, account, balance)
+            SELECT date_time, 'imprint', imprint FROM debt;
+        INSERT INTO debt_balance (date_time, account, balance)
+            SELECT date_time, 'paypal', paypal FROM debt;
+        INSERT INTO debt_balance (date_time, account, balance)
+            SELECT date_time, 'affirm', affirm FROM debt;
+        INSERT INTO debt_balance (date_time, account, balance)
+            SELECT date_time, 'cisco', cisco FROM debt;
+        INSERT INTO debt_balance (date_time, account, balance)
+            SELECT date_time, 'medical', medical FROM debt;
+        INSERT INTO debt_balance (date_time, account, balance)
+            SELECT date_time, 'schwab', schwab FROM debt;
+
+        DROP TABLE debt;
+        UPDATE audit SET undone = 1 WHERE kind = 'debt';
+    ",
+    },
+    Migration {
+        // `import statement`: the bank's id of each imported transaction,
+        // so a statement can be imported twice, and the ordered regex rules
+        // that pick a category from the description.
+        name: "statement import",
+        sql: "
+        ALTER TABLE spend ADD COLUMN import_id TEXT;
+        CREATE UNIQUE INDEX spend_import_id ON spend (import_id);
+        CREATE TABLE category_rule (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            date_time TEXT NOT NULL,
+            position INTEGER NOT NULL,
+            pattern TEXT NOT NULL,
+            category TEXT NOT NULL
+        );
+    ",
+    },
+    Migration {
+        // Rent, subscriptions and installments: due every `every` cadence
+        // units from `start`; `paid` counts the occurrences already added
+        // to spend, so the next due date never drifts off the start day.
+        name: "recurring expenses",
+        sql: "
+        CREATE TABLE recurring (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            date_time TEXT NOT NULL,
+            name TEXT NOT NULL,
+            amount REAL NOT NULL,
+            category TEXT NOT NULL,
+            cadence TEXT NOT NULL,
+            every INTEGER NOT NULL DEFAULT 1,
+            start TEXT NOT NULL,
+            until TEXT,
+            paid INTEGER NOT NULL DEFAULT 0
+        );
+    ",
+    },
+    Migration {
+        // Money coming in, and balances of the accounts it's kept in, for
+        // cash flow and net worth. Asset balances are stored like debt ones.
+        name: "income and assets",
+        sql: "
+        CREATE TABLE income (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            date_time TEXT NOT NULL,
+            amount REAL NOT NULL,
+            source TEXT NOT NULL
+        );
+        CREATE TABLE asset_balance (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            date_time TEXT NOT NULL,
+            account TEXT NOT NULL,
+            balance REAL NOT NULL
+        );
+        CREATE INDEX asset_balance_account_date_time ON asset_balance (account, date_time);
+    ",
+    },
+    Migration {
+        // Changes one command makes together share a batch, so `undo`
+        // reverts all of them; older changes are each a batch of their own.
+        name: "audit batches",
+        sql: "
+        ALTER TABLE audit ADD COLUMN batch INTEGER;
+        UPDATE audit SET batch = id;
+        CREATE INDEX audit_batch ON audit (batch);
+    ",
+    },
+];
+
+/// Schema version a fully migrated database is at
+pub fn latest_version() -> i64 {
+    MIGRATIONS.len() as i64
+}
+
+pub fn schema_version(conn: &Connection) -> Result<i64> {
+    conn.query_row("PRAGMA user_version", [], |row| row.get(0))
+}
+
+/// Apply every pending migration, each in its own transaction.
+/// Returns the versions that were applied.
+pub fn migrate(conn: &mut Connection) -> Result<Vec<i64>> {
+    let current = schema_version(conn)?;
+    let mut applied = Vec::new();
+
+    for (idx, migration) in MIGRATIONS.iter().enumerate() {
+        let version = idx as i64 + 1;
+        if version <= current {
+            continue;
+        }
+
+        let tx = conn.transaction()?;
+        tx.execute_batch(migration.sql)?;
+        tx.pragma_update(None, "user_version", version)?;
+        tx.commit()?;
+        applied.push(version);
+    }
+
+    Ok(applied)
+}
+
+/// Open `links.db`, migrated to the latest schema
+pub fn open() -> Result<Connection> {
+    let db_path = util::get_database_path()?;
+    let mut conn = Connection::open(&db_path)?;
+    migrate(&mut conn)?;
+    Ok(conn)
+}
+
+/// `db migrate`
+pub fn run_migrate() -> Result<()> {
+    let db_path = util::get_database_path()?;
+    let mut conn = Connection::open(&db_path)?;
+
+    let applied = migrate(&mut conn)?;
+    if applied.is_empty() {
+        println!("Already at version {}", schema_version(&conn)?);
+    }
+    for version in applied {
+        let name = MIGRATIONS[version as usize - 1].name;
+        println!("Applied {:03} {}", version, name);
+    }
+
+    Ok(())
+}
+
+/// `db status`
+pub fn status() -> Result<()> {
+    let db_path = util::get_database_path()?;
+    let conn = Connection::open(&db_path)?;
+
+    let current = schema_version(&conn)?;
+    println!("{}: version {} of {}", db_path, current, latest_version());
+
+    for (idx, migration) in MIGRATIONS.iter().enumerate() {
+        let version = idx as i64 + 1;
+        let state = if version <= current {
+            "applied"
+        } else {
+            "pending"
+        };
+        println!("  {:03} {: <8} {}", version, state, migration.name);
+    }
+
+    Ok(())
+}
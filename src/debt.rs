@@ -0,0 +1,636 @@
+//! Debt balances and payoff planning
+//!
+//! Accounts are `[[debts]]` entries in the config, with their APR, minimum
+//! payment and due day; `log debt` stores one `debt_balance` row per account.
+//! `debt plan` starts from each account's latest balance and simulates paying
+//! a fixed budget every month, from next month on: interest accrues, every
+//! account gets its minimum, and whatever is left goes to the first account
+//! in the payoff order that still has a balance. Money freed by a paid-off
+//! account stays in the budget, so it rolls to the next one.
+//!
+//! `debt report` looks back instead: balances per snapshot and month, which
+//! accounts went up since the last snapshot, and the debt-free date the
+//! recent paydown trend points to.
+
+use crate::cli::{Format, Strategy};
+use crate::clock;
+use crate::config::{self, DebtAccount};
+use crate::db;
+use crate::output;
+use anyhow::{Result, bail};
+use chrono::{Datelike, Days, Months, NaiveDate};
+use rusqlite::Connection;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+/// Give up on a plan that hasn't cleared the debt after this many months
+const MAX_MONTHS: u32 = 600;
+
+/// Balances below this count as paid off
+const PAID: f64 = 0.005;
+
+/// Parse an `account=balance` pair from `log debt --balance` or `log asset`
+pub fn parse_balance(s: &str) -> Result<(String, f64), String> {
+    let Some((account, balance)) = s.split_once('=') else {
+        return Err(format!("expected account=balance, got {s}"));
+    };
+    let balance = balance
+        .trim()
+        .parse()
+        .map_err(|_| format!("'{}' is not a balance", balance.trim()))?;
+    Ok((account.trim().to_string(), balance))
+}
+
+/// Latest logged balance of every account, with the date of the latest snapshot
+pub fn latest_balances(conn: &Connection) -> Result<(Option<NaiveDate>, HashMap<String, f64>)> {
+    let mut stmt = conn.prepare(
+        "SELECT date(date_time), account, balance FROM debt_balance ORDER BY date_time, id",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, f64>(2)?,
+        ))
+    })?;
+
+    let mut latest = None;
+    let mut balances = HashMap::new();
+    for row in rows {
+        let (date, account, balance) = row?;
+        latest = Some(NaiveDate::parse_from_str(&date, "%Y-%m-%d")?);
+        balances.insert(account, balance);
+    }
+    Ok((latest, balances))
+}
+
+impl Strategy {
+    fn name(self) -> &'static str {
+        match self {
+            Strategy::Avalanche => "avalanche",
+            Strategy::Snowball => "snowball",
+            Strategy::Custom => "custom",
+        }
+    }
+}
+
+/// Label of a configured account, or its name
+fn label(name: &str) -> String {
+    config::get()
+        .debt(name)
+        .map_or(name.to_string(), |d| d.label.clone())
+}
+
+/// Account indices in the order extra money goes to them
+fn payoff_order(
+    strategy: Strategy,
+    accounts: &[(&DebtAccount, f64)],
+    custom: &[String],
+) -> Result<Vec<usize>> {
+    let mut avalanche: Vec<usize> = (0..accounts.len()).collect();
+    avalanche.sort_by(|&a, &b| {
+        let (a, b) = (&accounts[a], &accounts[b]);
+        b.0.apr.total_cmp(&a.0.apr).then(a.1.total_cmp(&b.1))
+    });
+
+    Ok(match strategy {
+        Strategy::Avalanche => avalanche,
+        Strategy::Snowball => {
+            let mut order: Vec<usize> = (0..accounts.len()).collect();
+            order.sort_by(|&a, &b| {
+                let (a, b) = (&accounts[a], &accounts[b]);
+                a.1.total_cmp(&b.1).then(b.0.apr.total_cmp(&a.0.apr))
+            });
+            order
+        }
+        // the named accounts first, then the rest highest APR first
+        Strategy::Custom => {
+            let mut order = Vec::new();
+            for name in custom {
+                match accounts.iter().position(|(a, _)| &a.name == name) {
+                    Some(idx) if !order.contains(&idx) => order.push(idx),
+                    Some(_) => {}
+                    None => bail!("--order names '{name}', which has no balance to pay off"),
+                }
+            }
+            let rest: Vec<usize> = avalanche
+                .into_iter()
+                .filter(|idx| !order.contains(idx))
+                .collect();
+            order.extend(rest);
+            order
+        }
+    })
+}
+
+/// One account's payment in one month
+#[derive(Serialize)]
+pub struct Payment {
+    pub month: String,
+    pub account: String,
+    pub payment: f64,
+    pub interest: f64,
+    pub balance: f64,
+}
+
+/// Result of paying the debts off in one order
+#[derive(Serialize)]
+pub struct Outcome {
+    pub strategy: &'static str,
+    pub order: Vec<String>,
+    pub months: u32,
+    /// Due date of the last payment
+    pub debt_free: NaiveDate,
+    pub total_interest: f64,
+    pub total_paid: f64,
+}
+
+fn cents(amount: f64) -> f64 {
+    (amount * 100.0).round() / 100.0
+}
+
+fn due_date(month: NaiveDate, due_day: u32) -> NaiveDate {
+    (1..=due_day.clamp(1, 31))
+        .rev()
+        .find_map(|day| month.with_day(day))
+        .unwrap_or(month)
+}
+
+fn simulate(
+    strategy: Strategy,
+    accounts: &[(&DebtAccount, f64)],
+    order: &[usize],
+    budget: f64,
+    start: NaiveDate,
+) -> Result<(Outcome, Vec<Payment>)> {
+    let mut balances: Vec<f64> = accounts.iter().map(|a| a.1).collect();
+    let mut schedule = Vec::new();
+    let mut total_interest = 0.0;
+    let mut total_paid = 0.0;
+    let mut debt_free = start;
+    let mut months = 0;
+
+    while balances.iter().any(|&b| b > PAID) {
+        if months == MAX_MONTHS {
+            bail!(
+                "{} still owes ${:.2} after {MAX_MONTHS} months; the budget doesn't outpace the interest",
+                strategy.name(),
+                balances.iter().sum::<f64>()
+            );
+        }
+        months += 1;
+        let month = start
+            .with_day(1)
+            .and_then(|d| d.checked_add_months(Months::new(months)))
+            .unwrap_or(start);
+
+        let owed: Vec<bool> = balances.iter().map(|&b| b > PAID).collect();
+        let mut interest = vec![0.0; accounts.len()];
+        let mut paid = vec![0.0; accounts.len()];
+        for (idx, (account, _)) in accounts.iter().enumerate() {
+            if owed[idx] {
+                interest[idx] = balances[idx] * account.apr / 100.0 / 12.0;
+                balances[idx] += interest[idx];
+                paid[idx] = account.min_payment.min(balances[idx]);
+                balances[idx] -= paid[idx];
+            }
+        }
+        let mut extra = budget - paid.iter().sum::<f64>();
+        for &idx in order {
+            let pay = extra.min(balances[idx]).max(0.0);
+            paid[idx] += pay;
+            balances[idx] -= pay;
+            extra -= pay;
+        }
+
+        for (idx, (account, _)) in accounts.iter().enumerate() {
+            if !owed[idx] {
+                continue;
+            }
+            total_interest += interest[idx];
+            total_paid += paid[idx];
+            if balances[idx] <= PAID {
+                debt_free = debt_free.max(due_date(month, account.due_day));
+            }
+            schedule.push(Payment {
+                month: month.format("%Y-%m").to_string(),
+                account: account.name.clone(),
+                payment: cents(paid[idx]),
+                interest: cents(interest[idx]),
+                balance: cents(balances[idx].max(0.0)),
+            });
+        }
+    }
+
+    let outcome = Outcome {
+        strategy: strategy.name(),
+        order: order
+            .iter()
+            .map(|&idx| accounts[idx].0.name.clone())
+            .collect(),
+        months,
+        debt_free,
+        total_interest: cents(total_interest),
+        total_paid: cents(total_paid),
+    };
+    Ok((outcome, schedule))
+}
+
+#[derive(Serialize)]
+pub struct PlanReport {
+    /// Date of the balances the plan starts from
+    pub as_of: NaiveDate,
+    pub accounts: usize,
+    pub balance: f64,
+    pub budget: f64,
+    pub minimums: f64,
+    pub outcomes: Vec<Outcome>,
+    /// Month-by-month payments of the chosen strategy
+    pub strategy: &'static str,
+    pub schedule: Vec<Payment>,
+}
+
+fn print_plan(report: &PlanReport) {
+    println!(
+        "Debt ${:.2} across {} accounts on {}; paying ${:.2} a month (minimums ${:.2})",
+        report.balance, report.accounts, report.as_of, report.budget, report.minimums
+    );
+    println!();
+    println!("strategy     debt-free   months    interest");
+    for outcome in &report.outcomes {
+        println!(
+            "{:<12} {} {:>7}  {:>10.2}",
+            outcome.strategy, outcome.debt_free, outcome.months, outcome.total_interest
+        );
+    }
+
+    let Some(chosen) = report
+        .outcomes
+        .iter()
+        .find(|o| o.strategy == report.strategy)
+    else {
+        return;
+    };
+    println!();
+    println!(
+        "{}: {}",
+        chosen.strategy,
+        chosen
+            .order
+            .iter()
+            .map(|name| label(name))
+            .collect::<Vec<_>>()
+            .join(" → ")
+    );
+    println!("month      payment   interest     balance  paid off");
+    let mut rows = report.schedule.iter().peekable();
+    while let Some(first) = rows.peek() {
+        let month = first.month.clone();
+        let (mut payment, mut interest, mut balance) = (0.0, 0.0, 0.0);
+        let mut cleared = Vec::new();
+        while let Some(row) = rows.next_if(|r| r.month == month) {
+            payment += row.payment;
+            interest += row.interest;
+            balance += row.balance;
+            if row.balance <= PAID {
+                cleared.push(label(&row.account));
+            }
+        }
+        let line = format!(
+            "{month}  {payment:>9.2}  {interest:>9.2}  {balance:>10.2}  {}",
+            cleared.join(", ")
+        );
+        println!("{}", line.trim_end());
+    }
+}
+
+/// `debt plan`
+pub fn plan(budget: f64, strategy: Strategy, custom: &[String], format: Format) -> Result<()> {
+    if strategy == Strategy::Custom && custom.is_empty() {
+        bail!("the custom strategy needs --order with the accounts to pay first");
+    }
+    let conn = db::open()?;
+    let (as_of, balances) = latest_balances(&conn)?;
+    let Some(as_of) = as_of else {
+        bail!("no debt balances logged yet; run `log debt` first");
+    };
+
+    let accounts: Vec<(&DebtAccount, f64)> = config::get()
+        .debts
+        .iter()
+        .filter_map(|d| balances.get(&d.name).map(|&b| (d, b)))
+        .filter(|(_, b)| *b > PAID)
+        .collect();
+    if accounts.is_empty() {
+        println!("No debt left as of {as_of}");
+        return Ok(());
+    }
+
+    let no_apr: Vec<&str> = accounts
+        .iter()
+        .filter(|(d, _)| d.apr == 0.0)
+        .map(|(d, _)| d.name.as_str())
+        .collect();
+    if !no_apr.is_empty() {
+        eprintln!(
+            "{} at 0% APR; set apr in the [[debts]] entry if that's wrong",
+            no_apr.join(", ")
+        );
+    }
+
+    let minimums: f64 = accounts.iter().map(|(d, b)| d.min_payment.min(*b)).sum();
+    if budget < minimums {
+        bail!("a ${budget:.2} budget doesn't cover the ${minimums:.2} of minimum payments");
+    }
+
+    let start = clock::today().max(as_of);
+    let mut strategies = vec![Strategy::Avalanche, Strategy::Snowball];
+    if !custom.is_empty() {
+        strategies.push(Strategy::Custom);
+    }
+    let mut outcomes = Vec::new();
+    let mut schedule = Vec::new();
+    for s in strategies {
+        let order = payoff_order(s, &accounts, custom)?;
+        let (outcome, payments) = simulate(s, &accounts, &order, budget, start)?;
+        if s == strategy {
+            schedule = payments;
+        }
+        outcomes.push(outcome);
+    }
+
+    let report = PlanReport {
+        as_of,
+        accounts: accounts.len(),
+        balance: accounts.iter().map(|a| a.1).sum(),
+        budget,
+        minimums,
+        outcomes,
+        strategy: strategy.name(),
+        schedule,
+    };
+    match format {
+        Format::Table => print_plan(&report),
+        Format::Json => output::json(&report)?,
+        Format::Csv => output::csv(&report.schedule)?,
+    }
+    Ok(())
+}
+
+/// Days of snapshots the paydown trend is fitted to
+const TREND_DAYS: i64 = 90;
+
+const DAYS_PER_MONTH: f64 = 365.25 / 12.0;
+
+/// Balances of every account as of one `log debt`
+struct Snapshot {
+    date: NaiveDate,
+    /// Accounts missing from the snapshot keep their previous balance
+    balances: BTreeMap<String, f64>,
+}
+
+impl Snapshot {
+    fn total(&self) -> f64 {
+        self.balances.values().sum()
+    }
+}
+
+fn snapshots(conn: &Connection) -> Result<Vec<Snapshot>> {
+    let mut stmt = conn
+        .prepare("SELECT date_time, account, balance FROM debt_balance ORDER BY date_time, id")?;
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, f64>(2)?,
+        ))
+    })?;
+
+    let mut snapshots: Vec<Snapshot> = Vec::new();
+    let mut last_time = String::new();
+    for row in rows {
+        let (date_time, account, balance) = row?;
+        if date_time != last_time || snapshots.is_empty() {
+            let date = NaiveDate::parse_from_str(date_time.get(..10).unwrap_or(""), "%Y-%m-%d")?;
+            let balances = snapshots
+                .last()
+                .map(|s| s.balances.clone())
+                .unwrap_or_default();
+            snapshots.push(Snapshot { date, balances });
+            last_time = date_time;
+        }
+        if let Some(snapshot) = snapshots.last_mut() {
+            snapshot.balances.insert(account, balance);
+        }
+    }
+    Ok(snapshots)
+}
+
+/// One account's balance in one snapshot
+#[derive(Serialize)]
+pub struct BalanceRow {
+    pub date: NaiveDate,
+    pub account: String,
+    pub balance: f64,
+}
+
+/// Total at the last snapshot of a month
+#[derive(Serialize)]
+pub struct MonthTotal {
+    pub month: String,
+    pub total: f64,
+    /// Against the previous month with a snapshot
+    pub change: Option<f64>,
+}
+
+#[derive(Serialize)]
+pub struct AccountStatus {
+    pub account: String,
+    pub label: String,
+    pub balance: f64,
+    /// Balance in the snapshot before the latest
+    pub previous: Option<f64>,
+    pub change: Option<f64>,
+    pub increased: bool,
+}
+
+#[derive(Serialize)]
+pub struct DebtReport {
+    pub as_of: NaiveDate,
+    pub total: f64,
+    pub accounts: Vec<AccountStatus>,
+    pub months: Vec<MonthTotal>,
+    /// Fall in the total per month over the last 90 days of snapshots
+    pub paydown_per_month: Option<f64>,
+    /// When the total reaches zero at that rate
+    pub debt_free: Option<NaiveDate>,
+    pub history: Vec<BalanceRow>,
+}
+
+/// Least-squares slope of the total, per day
+fn trend(snapshots: &[Snapshot]) -> Option<f64> {
+    let last = snapshots.last()?.date;
+    let recent: Vec<(f64, f64)> = snapshots
+        .iter()
+        .filter(|s| (last - s.date).num_days() <= TREND_DAYS)
+        .map(|s| ((s.date - last).num_days() as f64, s.total()))
+        .collect();
+    let n = recent.len() as f64;
+    let mean_x = recent.iter().map(|p| p.0).sum::<f64>() / n;
+    let mean_y = recent.iter().map(|p| p.1).sum::<f64>() / n;
+    let sxx: f64 = recent.iter().map(|p| (p.0 - mean_x).powi(2)).sum();
+    let sxy: f64 = recent.iter().map(|p| (p.0 - mean_x) * (p.1 - mean_y)).sum();
+    (sxx > 0.0).then(|| sxy / sxx)
+}
+
+fn build_report(snapshots: &[Snapshot]) -> Option<DebtReport> {
+    let latest = snapshots.last()?;
+    let previous = snapshots.len().checked_sub(2).map(|idx| &snapshots[idx]);
+
+    let mut accounts: Vec<AccountStatus> = latest
+        .balances
+        .iter()
+        .map(|(account, &balance)| {
+            let before = previous.and_then(|p| p.balances.get(account).copied());
+            let change = before.map(|b| cents(balance - b));
+            AccountStatus {
+                account: account.clone(),
+                label: label(account),
+                balance,
+                previous: before,
+                change,
+                increased: change.is_some_and(|c| c > PAID),
+            }
+        })
+        .filter(|a| a.balance > PAID || a.previous.is_some_and(|b| b > PAID))
+        .collect();
+    // config order, then any accounts no longer configured
+    let debts = &config::get().debts;
+    accounts.sort_by_key(|a| {
+        debts
+            .iter()
+            .position(|d| d.name == a.account)
+            .unwrap_or(debts.len())
+    });
+
+    let mut months: Vec<MonthTotal> = Vec::new();
+    for snapshot in snapshots {
+        let month = snapshot.date.format("%Y-%m").to_string();
+        let total = cents(snapshot.total());
+        match months.last_mut() {
+            Some(last) if last.month == month => last.total = total,
+            _ => months.push(MonthTotal {
+                month,
+                total,
+                change: None,
+            }),
+        }
+    }
+    for idx in 1..months.len() {
+        months[idx].change = Some(cents(months[idx].total - months[idx - 1].total));
+    }
+
+    let total = latest.total();
+    let per_day = trend(snapshots);
+    let debt_free = per_day.filter(|&slope| slope < 0.0).and_then(|slope| {
+        let days = (total / -slope).ceil();
+        (days < 365.0 * 100.0).then(|| latest.date + Days::new(days as u64))
+    });
+
+    let history = snapshots
+        .iter()
+        .flat_map(|s| {
+            s.balances.iter().map(|(account, &balance)| BalanceRow {
+                date: s.date,
+                account: account.clone(),
+                balance,
+            })
+        })
+        .collect();
+
+    Some(DebtReport {
+        as_of: latest.date,
+        total: cents(total),
+        accounts,
+        months,
+        paydown_per_month: per_day.map(|slope| cents(-slope * DAYS_PER_MONTH)),
+        debt_free,
+        history,
+    })
+}
+
+fn print_report(report: &DebtReport) {
+    println!("Debt ${:.2} on {}", report.total, report.as_of);
+    println!();
+    println!("account              balance    previous     change");
+    for a in &report.accounts {
+        let previous = a.previous.map_or(String::new(), |p| format!("{p:.2}"));
+        let change = a.change.map_or(String::new(), |c| format!("{c:+.2}"));
+        let flag = if a.increased {
+            "  up since last snapshot"
+        } else {
+            ""
+        };
+        println!(
+            "{:<18} {:>9.2}  {previous:>10}  {change:>9}{flag}",
+            a.label, a.balance
+        );
+    }
+
+    println!();
+    println!("month        total     change");
+    for m in &report.months {
+        let change = m.change.map_or(String::new(), |c| format!("{c:+.2}"));
+        let line = format!("{}  {:>9.2}  {change:>9}", m.month, m.total);
+        println!("{}", line.trim_end());
+    }
+
+    println!();
+    match (report.paydown_per_month, report.debt_free) {
+        (Some(rate), Some(date)) => println!(
+            "Paying down ${rate:.2} a month over the last {TREND_DAYS} days; debt-free around {date}"
+        ),
+        (Some(rate), None) => println!(
+            "Debt changing by ${:+.2} a month over the last {TREND_DAYS} days; no debt-free date at this rate",
+            -rate
+        ),
+        _ => println!("Log another snapshot to get a paydown rate"),
+    }
+}
+
+/// `debt report`
+pub fn report(format: Format) -> Result<()> {
+    let conn = db::open()?;
+    let Some(report) = build_report(&snapshots(&conn)?) else {
+        bail!("no debt balances logged yet; run `log debt` first");
+    };
+    match format {
+        Format::Table => print_report(&report),
+        Format::Json => output::json(&report)?,
+        Format::Csv => output::csv(&report.history)?,
+    }
+    Ok(())
+}
+
+/// Line for the rules block of the vault note
+pub fn vault_line() -> Option<String> {
+    let conn = db::open().ok()?;
+    let report = build_report(&snapshots(&conn).ok()?)?;
+    let mut line = format!("- Debt ${:.2}", report.total);
+    if let Some(rate) = report.paydown_per_month.filter(|&r| r > 0.0) {
+        line.push_str(&format!(", down ${rate:.0}/month"));
+    }
+    if let Some(date) = report.debt_free {
+        line.push_str(&format!(", debt-free ~{}", date.format("%Y-%m")));
+    }
+    let up: Vec<&str> = report
+        .accounts
+        .iter()
+        .filter(|a| a.increased)
+        .map(|a| a.label.as_str())
+        .collect();
+    if !up.is_empty() {
+        line.push_str(&format!(" ({} up)", up.join(", ")));
+    }
+    Some(line)
+}
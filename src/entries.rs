@@ -0,0 +1,415 @@
+//! List, edit and delete logged entries, and undo the most recent change
+//!
+//! Every change goes through the audit log so `undo` can revert it.
+
+use crate::audit::{self, Action, Change};
+use crate::db;
+use anyhow::{Context, Result, bail};
+use chrono::NaiveDate;
+use rusqlite::Connection;
+use rusqlite::types::Value;
+use serde_json::{Map, Value as Json};
+use std::io::{self, Write};
+
+struct EntryKind {
+    name: &'static str,
+    table: &'static str,
+    /// Column `--since` filters on
+    date_column: &'static str,
+}
+
+const KINDS: &[EntryKind] = &[
+    EntryKind {
+        name: "spend",
+        table: "spend",
+        date_column: "date_time",
+    },
+    EntryKind {
+        name: "violation",
+        table: "violations",
+        date_column: "date_time",
+    },
+    EntryKind {
+        name: "win",
+        table: "win",
+        date_column: "date_time",
+    },
+    EntryKind {
+        name: "row",
+        table: "row",
+        date_column: "date_time",
+    },
+    EntryKind {
+        name: "time",
+        table: "time",
+        date_column: "date_time",
+    },
+    EntryKind {
+        name: "activity",
+        table: "event",
+        date_column: "date_time",
+    },
+    EntryKind {
+        name: "debt",
+        table: "debt_balance",
+        date_column: "date_time",
+    },
+    EntryKind {
+        name: "rule",
+        table: "category_rule",
+        date_column: "date_time",
+    },
+    EntryKind {
+        name: "recurring",
+        table: "recurring",
+        date_column: "date_time",
+    },
+    EntryKind {
+        name: "income",
+        table: "income",
+        date_column: "date_time",
+    },
+    EntryKind {
+        name: "asset",
+        table: "asset_balance",
+        date_column: "date_time",
+    },
+    EntryKind {
+        name: "weight",
+        table: "weight",
+        date_column: "date",
+    },
+];
+
+fn lookup(kind: &str) -> Result<&'static EntryKind> {
+    match KINDS.iter().find(|k| k.name == kind) {
+        Some(kind) => Ok(kind),
+        None => {
+            let known: Vec<&str> = KINDS.iter().map(|k| k.name).collect();
+            bail!(
+                "unknown entry kind '{}', expected one of: {}",
+                kind,
+                known.join(", ")
+            )
+        }
+    }
+}
+
+/// Parse a `column=value` pair from `entries edit --set`
+pub fn parse_assignment(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((column, value)) => Ok((column.trim().to_string(), value.to_string())),
+        None => Err(format!("expected column=value, got {s}")),
+    }
+}
+
+fn print_table(headers: &[String], rows: &[Vec<String>]) {
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.len()).collect();
+    for row in rows {
+        for (idx, cell) in row.iter().enumerate() {
+            widths[idx] = widths[idx].max(cell.len());
+        }
+    }
+
+    let line = |cells: &[String]| {
+        let padded: Vec<String> = cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{cell: <width$}"))
+            .collect();
+        println!("{}", padded.join("  ").trim_end());
+    };
+
+    line(headers);
+    for row in rows {
+        line(row);
+    }
+}
+
+fn display(value: &Json) -> String {
+    match value {
+        Json::Null => String::new(),
+        Json::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Keep the type of the value being replaced: numbers stay numbers
+fn coerce(old: &Json, new: &str) -> Json {
+    match old {
+        Json::Number(_) => {
+            if let Ok(i) = new.trim().parse::<i64>() {
+                Json::from(i)
+            } else if let Ok(f) = new.trim().parse::<f64>() {
+                Json::from(f)
+            } else {
+                Json::from(new)
+            }
+        }
+        Json::Null if new.is_empty() => Json::Null,
+        _ => Json::from(new),
+    }
+}
+
+/// Ask for a new value of every column, keeping the current one on empty input
+fn prompt_changes(current: &Map<String, Json>) -> Vec<(String, String)> {
+    let mut changes = Vec::new();
+    for (column, value) in current {
+        if column == "id" {
+            continue;
+        }
+        print!("{column} [{}]: ", display(value));
+        let mut input = String::new();
+        io::stdout().flush().unwrap();
+        io::stdin().read_line(&mut input).unwrap();
+        let input = input.trim();
+        if !input.is_empty() {
+            changes.push((column.clone(), input.to_string()));
+        }
+    }
+    changes
+}
+
+fn apply_changes(current: &Json, changes: &[(String, String)]) -> Result<Json> {
+    let Json::Object(map) = current else {
+        bail!("entry is not an object");
+    };
+    let mut map = map.clone();
+    for (column, value) in changes {
+        if column == "id" {
+            bail!("the id column cannot be edited");
+        }
+        let Some(old) = map.get(column) else {
+            let known: Vec<&String> = map.keys().filter(|k| *k != "id").collect();
+            bail!("no column '{}', expected one of: {:?}", column, known);
+        };
+        let new = coerce(old, value);
+        map.insert(column.clone(), new);
+    }
+    Ok(Json::Object(map))
+}
+
+// ---------------- SQLite tables ------------------------------
+
+fn table_list(
+    conn: &Connection,
+    entry: &EntryKind,
+    since: Option<NaiveDate>,
+    limit: u32,
+) -> Result<()> {
+    let since = since.map(|d| d.to_string()).unwrap_or_default();
+    let mut stmt = conn.prepare(&format!(
+        "SELECT * FROM {} WHERE date({}) >= ?1 ORDER BY id DESC LIMIT ?2",
+        entry.table, entry.date_column
+    ))?;
+    let headers: Vec<String> = stmt.column_names().iter().map(|s| s.to_string()).collect();
+    let count = headers.len();
+
+    let rows = stmt.query_map((since, limit), |row| {
+        (0..count)
+            .map(|idx| Ok(display(&audit::to_json(row.get_ref(idx)?))))
+            .collect::<rusqlite::Result<Vec<String>>>()
+    })?;
+    let mut rows = rows.collect::<rusqlite::Result<Vec<_>>>()?;
+    rows.reverse();
+
+    print_table(&headers, &rows);
+    Ok(())
+}
+
+fn table_write(conn: &Connection, table: &str, id: i64, row: &Json) -> Result<()> {
+    let Json::Object(map) = row else {
+        bail!("entry is not an object");
+    };
+    let columns: Vec<&String> = map.keys().filter(|k| *k != "id").collect();
+    let sets: Vec<String> = columns
+        .iter()
+        .enumerate()
+        .map(|(idx, column)| format!("{column} = ?{}", idx + 1))
+        .collect();
+    let mut values: Vec<Value> = columns.iter().map(|c| audit::to_sql(&map[*c])).collect();
+    values.push(Value::Integer(id));
+
+    conn.execute(
+        &format!(
+            "UPDATE {table} SET {} WHERE id = ?{}",
+            sets.join(", "),
+            values.len()
+        ),
+        rusqlite::params_from_iter(values),
+    )?;
+    Ok(())
+}
+
+fn table_restore(conn: &Connection, table: &str, row: &Json) -> Result<()> {
+    let Json::Object(map) = row else {
+        bail!("entry is not an object");
+    };
+    let columns: Vec<&String> = map.keys().collect();
+    let placeholders: Vec<String> = (1..=columns.len()).map(|i| format!("?{i}")).collect();
+    let values: Vec<Value> = columns.iter().map(|c| audit::to_sql(&map[*c])).collect();
+
+    conn.execute(
+        &format!(
+            "INSERT INTO {table} ({}) VALUES ({})",
+            columns
+                .iter()
+                .map(|c| c.as_str())
+                .collect::<Vec<_>>()
+                .join(", "),
+            placeholders.join(", ")
+        ),
+        rusqlite::params_from_iter(values),
+    )?;
+    Ok(())
+}
+
+// ---------------- commands -----------------------------------
+
+/// `entries list`
+pub fn list(kind: &str, since: Option<NaiveDate>, limit: u32) -> Result<()> {
+    table_list(&db::open()?, lookup(kind)?, since, limit)
+}
+
+/// `entries edit`; prompts for every column when no changes are given
+pub fn edit(kind: &str, id: i64, changes: Vec<(String, String)>) -> Result<()> {
+    let entry = lookup(kind)?;
+    let mut conn = db::open()?;
+
+    let Some(before) = audit::row_json(&conn, entry.table, id)? else {
+        bail!("no {} entry {}", kind, id);
+    };
+
+    let changes = if changes.is_empty() {
+        match &before {
+            Json::Object(map) => prompt_changes(map),
+            _ => vec![],
+        }
+    } else {
+        changes
+    };
+    if changes.is_empty() {
+        println!("Nothing changed");
+        return Ok(());
+    }
+
+    let after = apply_changes(&before, &changes)?;
+    let tx = conn.transaction()?;
+    table_write(&tx, entry.table, id, &after)?;
+    audit::record(&tx, kind, id, Action::Update, Some(&before), Some(&after))?;
+    tx.commit()?;
+
+    println!("Updated {} {}", kind, id);
+    Ok(())
+}
+
+/// `entries delete`
+pub fn delete(kind: &str, id: i64) -> Result<()> {
+    let entry = lookup(kind)?;
+    let mut conn = db::open()?;
+    let tx = conn.transaction()?;
+
+    let Some(before) = audit::row_json(&tx, entry.table, id)? else {
+        bail!("no {} entry {}", kind, id);
+    };
+    tx.execute(&format!("DELETE FROM {} WHERE id = ?1", entry.table), [id])?;
+    audit::record(&tx, kind, id, Action::Delete, Some(&before), None)?;
+    tx.commit()?;
+
+    println!("Deleted {} {}", kind, id);
+    Ok(())
+}
+
+fn revert(conn: &Connection, change: &Change) -> Result<()> {
+    let table = lookup(&change.kind)?.table;
+    let id = change.entry_id;
+    match change.action {
+        Action::Insert => {
+            conn.execute(&format!("DELETE FROM {table} WHERE id = ?1"), [id])?;
+        }
+        Action::Delete => {
+            table_restore(
+                conn,
+                table,
+                change.before.as_ref().context("missing snapshot")?,
+            )?;
+        }
+        Action::Update => {
+            table_write(
+                conn,
+                table,
+                id,
+                change.before.as_ref().context("missing snapshot")?,
+            )?;
+        }
+    }
+    audit::mark_undone(conn, change.id)?;
+    Ok(())
+}
+
+/// `undo`: revert the most recent batch of changes that has not been undone
+pub fn undo() -> Result<()> {
+    let mut conn = db::open()?;
+    let tx = conn.transaction()?;
+    let changes = audit::last_batch(&tx)?;
+    for change in &changes {
+        revert(&tx, change)?;
+    }
+    tx.commit()?;
+
+    match changes.as_slice() {
+        [] => println!("Nothing to undo"),
+        [change] => println!(
+            "Undid {} of {} {} from {}",
+            change.action.as_str(),
+            change.kind,
+            change.entry_id,
+            change.date_time
+        ),
+        [latest, ..] => {
+            let mut kinds: Vec<&str> = changes.iter().map(|c| c.kind.as_str()).collect();
+            kinds.dedup();
+            println!(
+                "Undid {} changes ({}) from {}",
+                changes.len(),
+                kinds.join(", "),
+                latest.date_time
+            );
+        }
+    }
+    Ok(())
+}
+
+/// `entries audit`
+pub fn history(limit: u32) -> Result<()> {
+    let conn = db::open()?;
+    let headers: Vec<String> = [
+        "change",
+        "batch",
+        "date_time",
+        "action",
+        "kind",
+        "id",
+        "undone",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect();
+    let mut rows: Vec<Vec<String>> = audit::history(&conn, limit)?
+        .into_iter()
+        .map(|c| {
+            vec![
+                c.id.to_string(),
+                c.batch.to_string(),
+                c.date_time,
+                c.action.as_str().to_string(),
+                c.kind,
+                c.entry_id.to_string(),
+                if c.undone { "yes" } else { "" }.to_string(),
+            ]
+        })
+        .collect();
+    rows.reverse();
+    print_table(&headers, &rows);
+    Ok(())
+}
@@ -0,0 +1,123 @@
+//! Typed event store
+//!
+//! Timed or counted activities (study sessions, reading, caffeine, ...) all
+//! land in the `event` table. The kinds that can be logged and how they are
+//! labelled come from `[[activities]]` in the config, so a new habit needs a
+//! config entry rather than a new table.
+
+use crate::audit;
+use crate::clock;
+use crate::config::{self, ActivityConfig};
+use crate::db;
+use crate::prompt_or;
+use anyhow::{Result, bail};
+use chrono::NaiveDate;
+use rand::prelude::IndexedRandom;
+use rusqlite::Connection;
+
+/// Insert an event stamped with the current clock time, returning its id
+pub fn record(
+    conn: &Connection,
+    activity: &ActivityConfig,
+    quantity: f64,
+    tags: &[String],
+    note: Option<&str>,
+) -> rusqlite::Result<i64> {
+    conn.execute(
+        "INSERT INTO event (kind, date_time, quantity, unit, tags, note) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        (
+            &activity.name,
+            clock::timestamp(),
+            quantity,
+            &activity.unit,
+            tags.join(","),
+            note,
+        ),
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// Total quantity per kind on `date`
+pub fn totals_on(conn: &Connection, date: NaiveDate) -> rusqlite::Result<Vec<(String, f64)>> {
+    let mut stmt = conn.prepare(
+        "select kind, sum(quantity) from event where date(date_time) == ?1 group by kind order by kind",
+    )?;
+    let iter = stmt.query_map([date.to_string()], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    iter.collect()
+}
+
+fn lookup(kind: &str) -> Result<&'static ActivityConfig> {
+    match config::get().activity(kind) {
+        Some(activity) => Ok(activity),
+        None => {
+            let known: Vec<&str> = config::get()
+                .activities
+                .iter()
+                .map(|a| a.name.as_str())
+                .collect();
+            bail!(
+                "unknown activity '{}', expected one of: {}",
+                kind,
+                known.join(", ")
+            )
+        }
+    }
+}
+
+fn pick_activity() -> &'static ActivityConfig {
+    let activities = &config::get().activities;
+    for (idx, activity) in activities.iter().enumerate() {
+        println!("{idx}: {} ({})", activity.name, activity.label);
+    }
+    loop {
+        let idx: usize = prompt_or(None, "Enter activity: ");
+        if let Some(activity) = activities.get(idx) {
+            return activity;
+        }
+        println!("Invalid input, try again:");
+    }
+}
+
+/// `log activity`
+pub fn log_activity(
+    kind: Option<String>,
+    amount: Option<f64>,
+    tags: Vec<String>,
+    note: Option<String>,
+) -> Result<()> {
+    let activity = match kind {
+        Some(kind) => lookup(&kind)?,
+        None => pick_activity(),
+    };
+    let amount: f64 = prompt_or(
+        amount,
+        &format!("Enter {} amount in {}: ", activity.label, activity.unit),
+    );
+
+    let conn = db::open()?;
+    let id = record(&conn, activity, amount, &tags, note.as_deref())?;
+    audit::record_insert(&conn, "activity", "event", id)?;
+
+    println!(
+        "Logged {}: {} {} at {}",
+        activity.name,
+        amount,
+        activity.unit,
+        clock::timestamp()
+    );
+
+    Ok(())
+}
+
+/// Activity `bored` should suggest, weighted by `bored_weight`
+pub fn random_bored_activity() -> Option<&'static ActivityConfig> {
+    let candidates: Vec<&ActivityConfig> = config::get()
+        .activities
+        .iter()
+        .filter(|a| a.bored_weight > 0.0)
+        .collect();
+    candidates
+        .choose_weighted(&mut rand::rng(), |a| a.bored_weight)
+        .ok()
+        .copied()
+}
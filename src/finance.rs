@@ -0,0 +1,293 @@
+//! Cash flow and net worth
+//!
+//! `finance statement` puts income, spend and debt together per calendar
+//! month: cash flow is income minus spend minus debt payments (spend in
+//! `finance.debt_categories`), and the savings rate is cash flow as a share
+//! of income. Net worth at each month end is the asset balances minus the
+//! debt balances, each account carrying its latest snapshot forward.
+
+use crate::cli::Format;
+use crate::clock;
+use crate::config;
+use crate::db;
+use crate::output;
+use crate::recurring;
+use anyhow::Result;
+use chrono::{Datelike, Days, Months, NaiveDate};
+use rusqlite::Connection;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+/// Asset and debt totals after one day's snapshots
+struct Totals {
+    date: NaiveDate,
+    /// `None` until the first snapshot of that kind
+    assets: Option<f64>,
+    debt: Option<f64>,
+    /// Latest balance of each account, asset or debt
+    balances: BTreeMap<(bool, String), f64>,
+}
+
+impl Totals {
+    fn net_worth(&self) -> Option<f64> {
+        if self.assets.is_none() && self.debt.is_none() {
+            return None;
+        }
+        Some(self.assets.unwrap_or(0.0) - self.debt.unwrap_or(0.0))
+    }
+}
+
+/// Totals after every day with an asset or debt snapshot, oldest first
+fn totals(conn: &Connection) -> Result<Vec<Totals>> {
+    let mut stmt = conn.prepare(
+        "SELECT date(date_time), 0, account, balance, date_time FROM asset_balance
+            UNION ALL
+            SELECT date(date_time), 1, account, balance, date_time FROM debt_balance
+            ORDER BY 5",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, bool>(1)?,
+            row.get::<_, String>(2)?,
+            row.get::<_, f64>(3)?,
+        ))
+    })?;
+
+    let mut balances: BTreeMap<(bool, String), f64> = BTreeMap::new();
+    let mut totals: Vec<Totals> = Vec::new();
+    for row in rows {
+        let (date, is_debt, account, balance) = row?;
+        let date = NaiveDate::parse_from_str(&date, "%Y-%m-%d")?;
+        balances.insert((is_debt, account), balance);
+
+        let sum = |debt: bool| {
+            let mut kind = balances.iter().filter(|((d, _), _)| *d == debt).peekable();
+            kind.peek().is_some().then(|| kind.map(|(_, b)| b).sum())
+        };
+        let day = Totals {
+            date,
+            assets: sum(false),
+            debt: sum(true),
+            balances: balances.clone(),
+        };
+        match totals.last_mut() {
+            Some(last) if last.date == date => *last = day,
+            _ => totals.push(day),
+        }
+    }
+    Ok(totals)
+}
+
+/// Net worth after every day with a snapshot, for the dashboard
+pub fn net_worth_history(conn: &Connection) -> Result<Vec<(NaiveDate, f64)>> {
+    Ok(totals(conn)?
+        .iter()
+        .filter_map(|t| t.net_worth().map(|n| (t.date, n)))
+        .collect())
+}
+
+/// Sum per "YYYY-MM" month of `sql`'s (month, amount) rows
+fn by_month(conn: &Connection, sql: &str) -> Result<HashMap<String, f64>> {
+    let mut stmt = conn.prepare(sql)?;
+    let rows = stmt.query_map([], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, f64>(1)?))
+    })?;
+    let mut months = HashMap::new();
+    for row in rows {
+        let (month, amount) = row?;
+        *months.entry(month).or_default() += amount;
+    }
+    Ok(months)
+}
+
+#[derive(Serialize)]
+pub struct MonthRow {
+    pub month: String,
+    pub income: f64,
+    /// Spend outside the debt payment categories
+    pub spend: f64,
+    pub debt_payments: f64,
+    pub cash_flow: f64,
+    /// Cash flow over income; empty without income
+    pub savings_rate: Option<f64>,
+    /// At month end (or today for the current month)
+    pub assets: Option<f64>,
+    pub debt: Option<f64>,
+    pub net_worth: Option<f64>,
+    pub net_worth_change: Option<f64>,
+}
+
+#[derive(Serialize)]
+pub struct AccountBalance {
+    pub account: String,
+    pub label: String,
+    /// "asset" or "debt"
+    pub kind: String,
+    pub balance: f64,
+}
+
+#[derive(Serialize)]
+pub struct FinanceReport {
+    pub as_of: NaiveDate,
+    pub months: Vec<MonthRow>,
+    /// Latest balance of every account
+    pub accounts: Vec<AccountBalance>,
+    /// Over the months shown
+    pub savings_rate: Option<f64>,
+    pub net_worth_change_per_month: Option<f64>,
+}
+
+fn build_report(conn: &Connection, today: NaiveDate, months: u32) -> Result<FinanceReport> {
+    let cfg = config::get();
+    let income = by_month(
+        conn,
+        "SELECT strftime('%Y-%m', date_time), SUM(amount) FROM income GROUP BY 1",
+    )?;
+    let mut spend: HashMap<String, f64> = HashMap::new();
+    let mut debt_payments: HashMap<String, f64> = HashMap::new();
+    {
+        let mut stmt = conn.prepare(
+            "SELECT strftime('%Y-%m', date_time), category, SUM(amount) FROM spend GROUP BY 1, 2",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, f64>(2)?,
+            ))
+        })?;
+        for row in rows {
+            let (month, category, amount) = row?;
+            let to = if cfg.finance.debt_categories.contains(&category) {
+                &mut debt_payments
+            } else {
+                &mut spend
+            };
+            *to.entry(month).or_default() += amount;
+        }
+    }
+    let totals = totals(conn)?;
+    let at = |date: NaiveDate| totals.iter().rev().find(|t| t.date <= date);
+
+    let this_month = today.with_day(1).unwrap_or(today);
+    let first = this_month - Months::new(months.max(1) - 1);
+    let mut rows: Vec<MonthRow> = Vec::new();
+    let mut month = first;
+    while month <= this_month {
+        let key = month.format("%Y-%m").to_string();
+        let end = (month + Months::new(1) - Days::new(1)).min(today);
+        let income = income.get(&key).copied().unwrap_or(0.0);
+        let spend = spend.get(&key).copied().unwrap_or(0.0);
+        let debt_payments = debt_payments.get(&key).copied().unwrap_or(0.0);
+        let cash_flow = income - spend - debt_payments;
+        let snapshot = at(end);
+        let net_worth = snapshot.and_then(Totals::net_worth);
+        let previous = at(month - Days::new(1)).and_then(Totals::net_worth);
+        rows.push(MonthRow {
+            month: key,
+            income,
+            spend,
+            debt_payments,
+            cash_flow,
+            savings_rate: (income > 0.0).then(|| cash_flow / income),
+            assets: snapshot.and_then(|t| t.assets),
+            debt: snapshot.and_then(|t| t.debt),
+            net_worth,
+            net_worth_change: net_worth.zip(previous).map(|(now, before)| now - before),
+        });
+        month = month + Months::new(1);
+    }
+
+    let accounts = totals
+        .last()
+        .map(|t| {
+            t.balances
+                .iter()
+                .map(|((is_debt, account), &balance)| {
+                    let label = if *is_debt {
+                        cfg.debt(account).map(|d| d.label.clone())
+                    } else {
+                        cfg.asset(account).map(|a| a.label.clone())
+                    };
+                    AccountBalance {
+                        account: account.clone(),
+                        label: label.unwrap_or_else(|| account.clone()),
+                        kind: if *is_debt { "debt" } else { "asset" }.to_string(),
+                        balance,
+                    }
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let total_income: f64 = rows.iter().map(|r| r.income).sum();
+    let total_flow: f64 = rows.iter().map(|r| r.cash_flow).sum();
+    let changes: Vec<f64> = rows.iter().filter_map(|r| r.net_worth_change).collect();
+    Ok(FinanceReport {
+        as_of: today,
+        months: rows,
+        accounts,
+        savings_rate: (total_income > 0.0).then(|| total_flow / total_income),
+        net_worth_change_per_month: (!changes.is_empty())
+            .then(|| changes.iter().sum::<f64>() / changes.len() as f64),
+    })
+}
+
+fn money(value: Option<f64>) -> String {
+    value.map_or("-".to_string(), |v| format!("{v:.2}"))
+}
+
+fn percent(value: Option<f64>) -> String {
+    value.map_or("-".to_string(), |v| format!("{:.0}%", v * 100.0))
+}
+
+fn print_report(report: &FinanceReport) {
+    println!(
+        "month       income      spend  debt paid  cash flow  saved     assets       debt  net worth     change"
+    );
+    for r in &report.months {
+        println!(
+            "{}  {:>9.2}  {:>9.2}  {:>9.2}  {:>9.2}  {:>5}  {:>9}  {:>9}  {:>9}  {:>9}",
+            r.month,
+            r.income,
+            r.spend,
+            r.debt_payments,
+            r.cash_flow,
+            percent(r.savings_rate),
+            money(r.assets),
+            money(r.debt),
+            money(r.net_worth),
+            r.net_worth_change
+                .map_or("-".to_string(), |v| format!("{v:+.2}")),
+        );
+    }
+
+    println!();
+    println!(
+        "Savings rate over these {} months: {}",
+        report.months.len(),
+        percent(report.savings_rate)
+    );
+    match report.net_worth_change_per_month {
+        Some(change) if change >= 0.0 => println!("Net worth up ${change:.2}/month"),
+        Some(change) => println!("Net worth down ${:.2}/month", -change),
+        None => println!("Log balances with `log asset` and `log debt` to track net worth"),
+    }
+    for a in &report.accounts {
+        println!("  {:<5} {:<15} {:>10.2}", a.kind, a.label, a.balance);
+    }
+}
+
+/// `finance statement`
+pub fn statement(format: Format, months: u32) -> Result<()> {
+    let mut conn = db::open()?;
+    recurring::catch_up(&mut conn)?;
+    let report = build_report(&conn, clock::today(), months)?;
+    match format {
+        Format::Table => print_report(&report),
+        Format::Json => output::json(&report)?,
+        Format::Csv => output::csv(&report.months)?,
+    }
+    Ok(())
+}
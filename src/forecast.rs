@@ -0,0 +1,279 @@
+//! Goal-date projection from the 2-state weight model
+//!
+//! The filtered state is carried forward under a fixed daily intake with no
+//! further weigh-ins, so the weight variance grows with the uncertainty in
+//! TDEE. The chance of being at or below the goal on a given day is read off
+//! the projected normal; the first day that chance passes q is taken as the
+//! q-quantile of the goal date.
+
+use crate::cli::Format;
+use crate::config;
+use crate::model1::{self, KCAL_PER_KG, Kalman2D, display_weight, weight_unit};
+use crate::output;
+use crate::util;
+use crate::weight;
+use anyhow::{Result, bail};
+use chrono::{Days, NaiveDate};
+use serde::Serialize;
+
+/// How far ahead to look for the goal
+const HORIZON_DAYS: usize = 730;
+
+/// Central intervals reported for the goal date
+const INTERVALS: [f64; 3] = [0.50, 0.80, 0.95];
+
+// Abramowitz & Stegun 7.1.26, |error| < 1.5e-7
+fn erf(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.327_591_1 * x.abs());
+    let poly = t
+        * (0.254_829_592
+            + t * (-0.284_496_736
+                + t * (1.421_413_741 + t * (-1.453_152_027 + t * 1.061_405_429))));
+    let y = 1.0 - poly * (-x * x).exp();
+    if x >= 0.0 { y } else { -y }
+}
+
+fn normal_cdf(x: f64) -> f64 {
+    0.5 * (1.0 + erf(x / std::f64::consts::SQRT_2))
+}
+
+/// P(weight ≤ goal) for each projected day, made non-decreasing so it reads
+/// as "reached by then"
+fn chances(projection: &[(f64, f64)], goal_kg: f64) -> Vec<f64> {
+    let mut best: f64 = 0.0;
+    projection
+        .iter()
+        .map(|&(mean, var)| {
+            best = best.max(normal_cdf((goal_kg - mean) / var.sqrt()));
+            best
+        })
+        .collect()
+}
+
+/// First projected date by which the goal is reached with probability `q`
+fn quantile_date(start: NaiveDate, chances: &[f64], q: f64) -> Option<NaiveDate> {
+    let idx = chances.iter().position(|&p| p >= q)?;
+    Some(start + Days::new(idx as u64 + 1))
+}
+
+fn show_date(date: Option<NaiveDate>) -> String {
+    match date {
+        Some(date) => date.to_string(),
+        None => format!("beyond {} days", HORIZON_DAYS),
+    }
+}
+
+/// Central interval of the goal date; None is past the horizon
+#[derive(Serialize)]
+pub struct Interval {
+    pub level: f64,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+/// Projected weight every four weeks, in the logged unit
+#[derive(Serialize)]
+pub struct ProjectionRow {
+    pub date: NaiveDate,
+    pub mean: f64,
+    pub low_80: f64,
+    pub high_80: f64,
+}
+
+/// Whether the calorie target in target.json makes its date
+#[derive(Serialize)]
+pub struct TargetCheck {
+    pub target: u32,
+    pub date: NaiveDate,
+    /// Expected weight on the date; absent once the date has passed
+    pub expected: Option<f64>,
+    pub chance: Option<f64>,
+    pub on_pace: Option<bool>,
+    /// Daily intake that reaches the goal exactly on the date
+    pub needed_intake: Option<f64>,
+}
+
+#[derive(Serialize)]
+pub struct ForecastReport {
+    pub as_of: NaiveDate,
+    pub unit: &'static str,
+    pub weight: f64,
+    pub tdee: f64,
+    pub tdee_sd: f64,
+    pub goal: f64,
+    pub intake: f64,
+    pub already_reached: bool,
+    pub median: Option<NaiveDate>,
+    pub intervals: Vec<Interval>,
+    pub projection: Vec<ProjectionRow>,
+    pub target: Option<TargetCheck>,
+}
+
+fn print_report(report: &ForecastReport) {
+    let unit = report.unit;
+    println!(
+        "As of {}: {:.1} {unit}, TDEE {:.0} ± {:.0} kcal",
+        report.as_of, report.weight, report.tdee, report.tdee_sd
+    );
+
+    if report.already_reached {
+        println!("Already at or below the goal of {} {unit}", report.goal);
+        return;
+    }
+
+    println!(
+        "Goal {} {unit} eating {:.0} kcal/day:",
+        report.goal, report.intake
+    );
+    println!("  median date: {}", show_date(report.median));
+    for interval in &report.intervals {
+        println!(
+            "  {:>2.0}% interval: {} to {}",
+            interval.level * 100.0,
+            show_date(interval.from),
+            show_date(interval.to)
+        );
+    }
+
+    println!("  date        mean   80% range");
+    for row in &report.projection {
+        println!(
+            "  {}  {:.1}  {:.1}–{:.1}",
+            row.date, row.mean, row.low_80, row.high_80
+        );
+    }
+
+    if let Some(check) = &report.target {
+        let (Some(expected), Some(chance), Some(on_pace), Some(needed)) = (
+            check.expected,
+            check.chance,
+            check.on_pace,
+            check.needed_intake,
+        ) else {
+            println!("Target date {} has passed", check.date);
+            return;
+        };
+        println!(
+            "Target {} kcal/day by {}: expect {:.1} {}, {:.0}% chance of reaching {} — {}",
+            check.target,
+            check.date,
+            expected,
+            unit,
+            chance * 100.0,
+            report.goal,
+            if on_pace { "on pace" } else { "behind" }
+        );
+        println!(
+            "  reaching it on that date needs about {:.0} kcal/day",
+            needed
+        );
+    }
+}
+
+/// `model forecast`
+pub fn run(goal: Option<f64>, intake: Option<f64>, format: Format) -> Result<()> {
+    let days = model1::calendar_days(&weight::model_rows()?)?;
+    let Some(last) = days.last() else {
+        bail!("no weigh-ins logged yet");
+    };
+
+    let mut kf = Kalman2D::new(config::get().model.initial_weight_kg);
+    for day in &days {
+        kf.step(day);
+    }
+
+    let goal = goal.unwrap_or(config::get().model.goal_weight);
+    let goal_kg = model1::to_kg(goal);
+    let Some(intake) = intake.or_else(|| model1::recent_intake(&days)) else {
+        bail!("no intake logged in the last 7 days; pass --intake");
+    };
+
+    let mut report = ForecastReport {
+        as_of: last.date,
+        unit: weight_unit(),
+        weight: display_weight(kf.weight_kg()),
+        tdee: kf.tdee(),
+        tdee_sd: kf.tdee_var().sqrt(),
+        goal,
+        intake,
+        already_reached: kf.weight_kg() <= goal_kg,
+        median: None,
+        intervals: Vec::new(),
+        projection: Vec::new(),
+        target: None,
+    };
+
+    if !report.already_reached {
+        let projection = kf.project(intake, HORIZON_DAYS);
+        let chances = chances(&projection, goal_kg);
+
+        report.median = quantile_date(last.date, &chances, 0.5);
+        report.intervals = INTERVALS
+            .iter()
+            .map(|&level| {
+                let tail = (1.0 - level) / 2.0;
+                Interval {
+                    level,
+                    from: quantile_date(last.date, &chances, tail),
+                    to: quantile_date(last.date, &chances, 1.0 - tail),
+                }
+            })
+            .collect();
+
+        // every four weeks until the goal is all but certain
+        for (idx, &(mean, var)) in projection.iter().enumerate().skip(27).step_by(28) {
+            let spread = 1.2816 * var.sqrt();
+            report.projection.push(ProjectionRow {
+                date: last.date + Days::new(idx as u64 + 1),
+                mean: display_weight(mean),
+                low_80: display_weight(mean - spread),
+                high_80: display_weight(mean + spread),
+            });
+            if chances[idx] > 0.975 {
+                break;
+            }
+        }
+
+        if util::data_path("target.json").exists() {
+            report.target = Some(check_target(&kf, last.date, goal_kg)?);
+        }
+    }
+
+    match format {
+        Format::Table => print_report(&report),
+        Format::Json => output::json(&report)?,
+        Format::Csv => output::csv(&report.projection)?,
+    }
+    Ok(())
+}
+
+// Is the calorie target in target.json on pace for its date?
+fn check_target(kf: &Kalman2D, start: NaiveDate, goal_kg: f64) -> Result<TargetCheck> {
+    let target = model1::load_target()?;
+    let Some(date) = target.date() else {
+        bail!("target.json has an invalid date");
+    };
+    let mut check = TargetCheck {
+        target: target.target,
+        date,
+        expected: None,
+        chance: None,
+        on_pace: None,
+        needed_intake: None,
+    };
+    let days_left = (date - start).num_days();
+    if days_left <= 0 {
+        return Ok(check);
+    }
+
+    let projection = kf.project(target.target as f64, days_left as usize);
+    let (mean, var) = projection[projection.len() - 1];
+    let chance = normal_cdf((goal_kg - mean) / var.sqrt());
+
+    check.expected = Some(display_weight(mean));
+    check.chance = Some(chance);
+    check.on_pace = Some(chance >= 0.5);
+    check.needed_intake =
+        Some(kf.tdee() + (goal_kg - kf.weight_kg()) * KCAL_PER_KG / days_left as f64);
+    Ok(check)
+}
@@ -5,42 +5,77 @@
 // Controls      u = { intake_kcal, activity_kcal }
 //
 // Weight update:   ΔW = (intake_kcal − (TDEE_kcal + activity_kcal)) / 7700
+// Fat update:      ΔF = p(F) · ΔW, where p is the fat fraction of the change:
+//                  Forbes' curve p(F) = F / (F + 10.4) or a constant
 // TDEE evolves as a random walk with tiny process noise, letting the
 // filter "learn" your maintenance calories as you diet or gain.
 //
-// Crate deps (Cargo.toml)
-// [dependencies]
-// nalgebra = "0.34"
-// csv       = "1.3"
-// serde     = { version = "1", features = ["derive"] }
-
-use nalgebra::{Matrix2, Matrix3, Matrix3x2, Matrix2x3, Vector2, Vector3};
-use serde::Deserialize;
-use std::{error::Error, path::Path};
-
-/// CSV row structure.
-#[derive(Debug, Deserialize)]
-pub struct Row {
-    pub date: String,
-    pub weight: f64,        // kg *or* lb – we'll convert
-    pub intake_kcal: f64,   // raw calories eaten
-    pub activity: f64,      // exercise calories (can be 0)
-    pub body_fat: f64,      // scale BF% measurement
+// Fed from the `weight` table by `model bodycomp`; BF% readings come from
+// `log weight --body-fat`. The tests check the filter against synthetic
+// data with a known truth.
+
+use crate::cli::Format;
+use crate::config::{self, FatPartition};
+use crate::model1::{self, Day, KCAL_PER_KG, display_weight, weight_unit};
+use crate::output;
+use crate::weight;
+use anyhow::{Result, bail};
+use chrono::NaiveDate;
+use nalgebra::{Matrix2, Matrix2x3, Matrix3, RowVector3, Vector2, Vector3};
+use serde::Serialize;
+
+/// Forbes' constant (kg): lean/fat partition of weight change is 10.4 / F
+const FORBES_C: f64 = 10.4;
+
+// 1σ of the intake guess used for a day whose calories weren't logged
+const MISSING_INTAKE_SD: f64 = 800.0;
+
+/// Fraction of a weight change that is fat
+#[derive(Debug, Clone, Copy)]
+pub enum Partition {
+    /// Forbes' curve: the leaner you are, the more of a loss is lean mass
+    Forbes,
+    /// Fixed fraction in [0, 1]
+    Constant(f64),
+}
+
+impl Partition {
+    pub fn from_config() -> Self {
+        let model = &config::get().model;
+        match model.fat_partition {
+            FatPartition::Forbes => Partition::Forbes,
+            FatPartition::Constant => Partition::Constant(model.fat_fraction.clamp(0.0, 1.0)),
+        }
+    }
+
+    /// p(F) and dp/dF at fat mass `fat_kg`
+    fn fraction(&self, fat_kg: f64) -> (f64, f64) {
+        match *self {
+            Partition::Forbes => {
+                let fat_kg = fat_kg.max(0.0);
+                let d = fat_kg + FORBES_C;
+                (fat_kg / d, FORBES_C / (d * d))
+            }
+            Partition::Constant(p) => (p, 0.0),
+        }
+    }
 }
 
 /// Extended Kalman filter struct
+#[derive(Clone)]
 pub struct KalmanBF {
-    x: Vector3<f64>,   // state estimate [bf%, w_kg, tdee]
-    p: Matrix3<f64>,   // covariance
-    q: Matrix3<f64>,   // process noise
-    r: Matrix2<f64>,   // measurement noise
+    x: Vector3<f64>, // state estimate [bf%, w_kg, tdee]
+    p: Matrix3<f64>, // covariance
+    q: Matrix3<f64>, // process noise
+    r: Matrix2<f64>, // measurement noise
+    partition: Partition,
 }
 
 impl KalmanBF {
-    pub fn new(initial_bf: f64, initial_w_kg: f64, tdee_guess: f64) -> Self {
+    pub fn new(initial_bf: f64, initial_w_kg: f64, tdee_guess: f64, partition: Partition) -> Self {
         let x = Vector3::new(initial_bf, initial_w_kg, tdee_guess);
 
-        // Wide priors: 4 % BF var, 1 kg var, 400 kcal var
+        // Wide priors: 4 % BF var, 1 kg var, 400 kcal var
         let mut p = Matrix3::zeros();
         p[(0, 0)] = 4.0;
         p[(1, 1)] = 1.0;
@@ -48,47 +83,72 @@ impl KalmanBF {
 
         // Process noise: BF changes very little; weight per formula; TDEE slow drift
         let mut q = Matrix3::zeros();
-        q[(0, 0)] = 0.0004;    // (~0.02 %/day σ)
-        q[(1, 1)] = 0.0025;    // (±50 g)
-        q[(2, 2)] = 25.0;      // (±5 kcal/day drift)
+        q[(0, 0)] = 0.0004; // (~0.02 %/day σ)
+        q[(1, 1)] = 0.0025; // (±50 g)
+        q[(2, 2)] = 25.0; // (±5 kcal/day drift)
 
-        // Measurement noise: scale BF% σ=2 → var=4 ; weight σ=0.2 kg → var≈0.04
+        // Measurement noise: scale BF% σ=2 → var=4 ; weight σ=0.2 kg → var≈0.04
         let r = Matrix2::new(4.0, 0.0, 0.0, 0.04);
 
-        Self { x, p, q, r }
+        Self {
+            x,
+            p,
+            q,
+            r,
+            partition,
+        }
     }
 
-    /// Predict step using control inputs (intake, activity).
-    pub fn predict(&mut self, intake: f64, activity: f64) {
-        let bf = self.x[0];
-        let w = self.x[1];
-        let tdee = self.x[2];
+    /// State transition f(x, u) and its Jacobian ∂f/∂x.
+    fn transition(&self, intake: f64, activity: f64) -> (Vector3<f64>, Matrix3<f64>) {
+        let c = KCAL_PER_KG;
+        let (bf, w, tdee) = (self.x[0], self.x[1], self.x[2]);
 
-        let energy_balance = intake - (tdee + activity); // +ve = surplus
-        let delta_w = energy_balance / 7700.0;           // kg change
-        let w_pred = w + delta_w;
+        let fat = bf / 100.0 * w;
+        let delta_w = (intake - (tdee + activity)) / c; // kg change, +ve = surplus
+        let (frac, dfrac) = self.partition.fraction(fat);
 
-        // Assume all mass change is fat (can refine later)
-        let fat_mass = bf / 100.0 * w;
-        let fat_mass_pred = (fat_mass + delta_w).max(0.0);
-        let bf_pred = 100.0 * fat_mass_pred / w_pred.max(1e-3);
+        let w_pred = (w + delta_w).max(1e-3);
+        let fat_raw = fat + frac * delta_w;
+        let fat_pred = fat_raw.max(0.0);
+        let bf_pred = 100.0 * fat_pred / w_pred;
 
-        // State prediction
-        let x_pred = Vector3::new(bf_pred, w_pred, tdee);
+        // ∂F'/∂[bf, w, tdee]; zero once fat mass is clamped at 0
+        let dfat_dfat = 1.0 + dfrac * delta_w;
+        let dfat = if fat_raw > 0.0 {
+            RowVector3::new(dfat_dfat * w / 100.0, dfat_dfat * bf / 100.0, -frac / c)
+        } else {
+            RowVector3::zeros()
+        };
+        // ∂w'/∂[bf, w, tdee]
+        let dw = RowVector3::new(0.0, 1.0, -1.0 / c);
+        // bf' = 100 F'/w'  ⇒  ∂bf' = 100 (∂F' · w' − F' · ∂w') / w'²
+        let dbf = (dfat * w_pred - dw * fat_pred) * (100.0 / (w_pred * w_pred));
 
-        // Jacobian F = ∂f/∂x  (linearised around current state)
-        // We'll approximate:
         let mut f = Matrix3::identity();
-        f[(1, 2)] =  -1.0 / 7700.0;          // ∂weight/∂tdee
-        // bf% sensitivities tiny – ignore for simplicity
+        f.set_row(0, &dbf);
+        f.set_row(1, &dw);
 
-        // Covariance prediction: P = FPFᵀ + Q
-        self.p = f * &self.p * f.transpose() + &self.q;
+        (Vector3::new(bf_pred, w_pred, tdee), f)
+    }
+
+    /// Predict step using control inputs (intake, activity). `intake_sd` is
+    /// the uncertainty of the intake itself, 0 when it was logged.
+    pub fn predict(&mut self, intake: f64, activity: f64, intake_sd: f64) {
+        let (x_pred, f) = self.transition(intake, activity);
+
+        // Intake enters exactly opposite to TDEE, so ∂f/∂u = −∂f/∂tdee
+        let g = -f.column(2);
+        let g = Vector3::new(g[0], g[1], 0.0);
+
+        // Covariance prediction: P = FPFᵀ + Q + G σᵤ² Gᵀ
+        self.p = f * self.p * f.transpose() + self.q + g * g.transpose() * intake_sd.powi(2);
         self.x = x_pred;
     }
 
-    /// Measurement update with BF% and weight readings.
-    pub fn update(&mut self, bf_meas: f64, w_meas: f64) {
+    /// Measurement update with BF% and weight readings. Returns false and
+    /// leaves the state alone if the innovation covariance is singular.
+    pub fn update(&mut self, bf_meas: f64, w_meas: f64) -> bool {
         let z = Vector2::new(bf_meas, w_meas);
 
         // H maps state → measurement: picks BF and weight.
@@ -97,14 +157,82 @@ impl KalmanBF {
         h[(1, 1)] = 1.0; // weight
 
         // Innovation
-        let y = z - &h * &self.x;
-        let s = &h * &self.p * h.transpose() + &self.r;
-        let k = &self.p * h.transpose() * s.try_inverse().expect("S invertible");
+        let y = z - h * self.x;
+        let s = h * self.p * h.transpose() + self.r;
+        let Some(s_inv) = s.try_inverse().filter(|m| m.iter().all(|v| v.is_finite())) else {
+            return false;
+        };
+        let k = self.p * h.transpose() * s_inv;
+
+        // Joseph form keeps P symmetric/PSD
+        self.x += k * y;
+        let i_kh = Matrix3::identity() - k * h;
+        self.p = i_kh * self.p * i_kh.transpose() + k * self.r * k.transpose();
+        true
+    }
+
+    /// Measurement update on a day the scale gave a weight but no BF%.
+    pub fn update_weight(&mut self, w_meas: f64) -> bool {
+        let h = RowVector3::new(0.0, 1.0, 0.0);
+        let r = self.r[(1, 1)];
+        let y = w_meas - (h * self.x)[0];
+        let s = (h * self.p * h.transpose())[0] + r;
+        if !(s.is_finite() && s > 0.0) {
+            return false;
+        }
+        let k = self.p * h.transpose() / s;
+
+        self.x += k * y;
+        let i_kh = Matrix3::identity() - k * h;
+        self.p = i_kh * self.p * i_kh.transpose() + k * r * k.transpose();
+        true
+    }
 
-        // Update state & covariance
-        self.x += &k * y;
-        let i = Matrix3::identity();
-        self.p = (i - &k * h) * &self.p;
+    /// Predict into `day`, then apply its readings. Returns how many
+    /// readings had to be skipped.
+    pub fn step(&mut self, day: &Day) -> usize {
+        // exercise is part of TDEE here, as in the 2-state model
+        let intake_sd = if day.intake_known {
+            0.0
+        } else {
+            MISSING_INTAKE_SD
+        };
+        self.predict(day.intake_kcal, 0.0, intake_sd);
+        self.observe(day)
+    }
+
+    /// Apply the day's readings: the first weight together with the BF%
+    /// reading if there is one, any others on their own
+    pub fn observe(&mut self, day: &Day) -> usize {
+        let mut skipped = 0;
+        let mut weights = day.weights_kg.iter();
+        if let Some(&bf) = day.body_fat.first()
+            && let Some(&w) = weights.next()
+            && !self.update(bf, w)
+        {
+            skipped += 1;
+        }
+        for &w in weights {
+            if !self.update_weight(w) {
+                skipped += 1;
+            }
+        }
+        skipped
+    }
+
+    /// Predicted scale weight (mean, variance) after the `ahead` days, using
+    /// their intake but none of their readings
+    pub fn predict_weight(&self, ahead: &[Day]) -> (f64, f64) {
+        let mut kf = self.clone();
+        for day in ahead {
+            let intake_sd = if day.intake_known {
+                0.0
+            } else {
+                MISSING_INTAKE_SD
+            };
+            kf.predict(day.intake_kcal, 0.0, intake_sd);
+        }
+        (kf.x[1], kf.p[(1, 1)] + kf.r[(1, 1)])
     }
 
     pub fn state(&self) -> (f64, f64, f64) {
@@ -112,38 +240,316 @@ impl KalmanBF {
     }
 }
 
-/// Run EKF on CSV; emit: date,bf_est,wt_est_kg,tdee_est_kcal
-pub fn run_from_csv<P: AsRef<Path>>(path: P, tdee_guess: f64) -> Result<(), Box<dyn Error>> {
-    let mut rdr = csv::Reader::from_path(&path)?;
-    let mut rows = rdr.deserialize::<Row>();
+/// Daily estimate, weights in the logged unit
+#[derive(Serialize)]
+pub struct Estimate {
+    pub date: NaiveDate,
+    pub weight: f64,
+    pub body_fat_pct: f64,
+    pub fat: f64,
+    pub lean: f64,
+    pub tdee: f64,
+}
 
-    let first = rows.next().ok_or("CSV empty")??;
-    let mut kf = KalmanBF::new(first.body_fat, to_kg(first.weight), tdee_guess);
-    kf.update(first.body_fat, to_kg(first.weight));
+impl Estimate {
+    fn new(date: NaiveDate, (bf, w_kg, tdee): (f64, f64, f64)) -> Self {
+        let fat_kg = bf / 100.0 * w_kg;
+        Estimate {
+            date,
+            weight: display_weight(w_kg),
+            body_fat_pct: bf,
+            fat: display_weight(fat_kg),
+            lean: display_weight(w_kg - fat_kg),
+            tdee,
+        }
+    }
+}
 
-    println!("date,bf_est,wt_est_kg,tdee_est_kcal");
-    let (bf, wt, tdee) = kf.state();
-    println!("{},{:.2},{:.2},{:.0}", first.date, bf, wt, tdee);
+/// Change over the last `days` days
+#[derive(Serialize)]
+pub struct Trend {
+    pub days: usize,
+    pub weight: f64,
+    pub fat: f64,
+    pub lean: f64,
+    pub tdee: f64,
+    /// Lean share of the change (0.3 = 30%) when both weight and lean fell
+    pub lean_share_of_loss: Option<f64>,
+}
 
-    for row in rows {
-        let r = row?;
-        kf.predict(r.intake_kcal, r.activity);
-        kf.update(r.body_fat, to_kg(r.weight));
-        let (bf, wt, tdee) = kf.state();
-        println!("{},{:.2},{:.2},{:.0}", r.date, bf, wt, tdee);
+#[derive(Serialize)]
+pub struct BodyCompReport {
+    pub unit: &'static str,
+    pub days: Vec<Estimate>,
+    pub trends: Vec<Trend>,
+    /// Readings dropped because the innovation covariance was singular
+    pub skipped: usize,
+}
+
+/// Filter started on the first day with both a weight and a BF% reading,
+/// with that day's readings applied. None before any BF% is logged.
+pub fn start(days: &[Day]) -> Option<(usize, KalmanBF)> {
+    let start = days
+        .iter()
+        .position(|d| !d.body_fat.is_empty() && !d.weights_kg.is_empty())?;
+    let first = &days[start];
+    let mut kf = KalmanBF::new(
+        first.body_fat[0],
+        first.weights_kg[0],
+        config::get().model.initial_tdee,
+        Partition::from_config(),
+    );
+    kf.observe(first);
+    Some((start, kf))
+}
+
+fn print_report(report: &BodyCompReport) {
+    let unit = report.unit;
+    println!("date        weight   BF%    fat     lean    TDEE");
+    for e in &report.days {
+        println!(
+            "{}  {:>6.1}  {:>5.1}  {:>6.1}  {:>6.1}  {:>5.0}",
+            e.date, e.weight, e.body_fat_pct, e.fat, e.lean, e.tdee
+        );
+    }
+    if report.skipped > 0 {
+        println!(
+            "Skipped {} readings the filter could not use (singular innovation)",
+            report.skipped
+        );
+    }
+
+    for trend in &report.trends {
+        println!(
+            "{}-day: weight {:+.1} {unit}, fat {:+.1} {unit}, lean {:+.1} {unit}, TDEE {:+.0} kcal",
+            trend.days, trend.weight, trend.fat, trend.lean, trend.tdee
+        );
+        if let Some(share) = trend.lean_share_of_loss {
+            println!("  lean mass is {:.0}% of the loss", 100.0 * share);
+        }
     }
-    Ok(())
 }
 
-fn to_kg(w: f64) -> f64 {
-    if w > 200.0 { w * 0.453_592 } else { w }
+/// `model bodycomp`: run the EKF over the stored weigh-ins and report fat
+/// mass, lean mass and TDEE trends
+pub fn run(format: Format) -> Result<()> {
+    let days = model1::calendar_days(&weight::model_rows()?)?;
+
+    let Some((start, mut kf)) = start(&days) else {
+        bail!("no body-fat readings logged yet; add them with `log weight --body-fat`");
+    };
+
+    let mut estimates = Vec::new();
+    let mut skipped = 0;
+    for (idx, day) in days.iter().enumerate().skip(start) {
+        if idx > start {
+            skipped += kf.step(day);
+        }
+        estimates.push(Estimate::new(day.date, kf.state()));
+    }
+
+    let last = &estimates[estimates.len() - 1];
+    let trends = [14, 28]
+        .into_iter()
+        .filter_map(|span| {
+            let then = &estimates[estimates.len().checked_sub(span + 1)?];
+            let weight = last.weight - then.weight;
+            let lean = last.lean - then.lean;
+            Some(Trend {
+                days: span,
+                weight,
+                fat: last.fat - then.fat,
+                lean,
+                tdee: last.tdee - then.tdee,
+                lean_share_of_loss: (weight < 0.0 && lean < 0.0).then(|| lean / weight),
+            })
+        })
+        .collect();
+
+    let report = BodyCompReport {
+        unit: weight_unit(),
+        days: estimates,
+        trends,
+        skipped,
+    };
+    match format {
+        Format::Table => print_report(&report),
+        Format::Json => output::json(&report)?,
+        Format::Csv => output::csv(&report.days)?,
+    }
+
+    Ok(())
 }
 
-// -------------------------------------------------------------
-// Example `main.rs`
-// -------------------------------------------------------------
-// fn main() -> Result<(), Box<dyn std::error::Error>> {
-//     kalman_bf::run_from_csv("bodycomp.csv", 3100.0)?;
-//     Ok(())
-// }
-// -------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    const DAYS: usize = 180;
+
+    /// Standard deviations of [bf%, w_kg]
+    fn std_devs(kf: &KalmanBF) -> (f64, f64) {
+        (kf.p[(0, 0)].sqrt(), kf.p[(1, 1)].sqrt())
+    }
+
+    // Standard normal draw (Box–Muller)
+    fn gauss(rng: &mut StdRng) -> f64 {
+        let u1: f64 = rng.random::<f64>().max(f64::MIN_POSITIVE);
+        let u2: f64 = rng.random();
+        (-2.0 * u1.ln()).sqrt() * (std::f64::consts::TAU * u2).cos()
+    }
+
+    /// Errors over the second half of a simulated diet, once the filter has
+    /// had time to converge
+    struct Scores {
+        /// RMSE of fat and lean mass in kg
+        fat_rmse: f64,
+        lean_rmse: f64,
+        tdee_rmse: f64,
+        /// Share of days the 95% band of BF% and weight covered the truth
+        coverage: [f64; 2],
+        /// Last day's (estimate, truth) of fat and lean mass
+        fat: (f64, f64),
+        lean: (f64, f64),
+        skipped: usize,
+    }
+
+    /// Generate a diet from the model itself with a known truth and run the
+    /// filter on noisy readings. Some days skip the scale, the BF% reading
+    /// or the calorie log.
+    fn simulate(seed: u64) -> Scores {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let partition = Partition::Forbes;
+        let tdee_guess = 3_000.0;
+
+        // truth starts inside the filter's prior
+        let mut bf = 35.0 + 2.0 * gauss(&mut rng);
+        let mut w: f64 = 120.0 + gauss(&mut rng);
+        let mut tdee = tdee_guess + 20.0 * gauss(&mut rng);
+
+        let mut kf = KalmanBF::new(bf + 2.0 * gauss(&mut rng), w, tdee_guess, partition);
+
+        let (mut fat_sq, mut lean_sq, mut tdee_sq) = (0.0, 0.0, 0.0);
+        let mut covered = [0usize; 2];
+        let mut scored = 0usize;
+        let mut skipped = 0usize;
+        let (mut fat, mut lean) = ((0.0, 0.0), (0.0, 0.0));
+
+        for day in 1..DAYS {
+            let intake = 2_200.0 + 150.0 * gauss(&mut rng);
+
+            // ground truth moves by the model's own dynamics plus process noise
+            let fat_kg = bf / 100.0 * w;
+            let delta_w = (intake - tdee) / KCAL_PER_KG;
+            let (frac, _) = partition.fraction(fat_kg);
+            let fat_kg = fat_kg + frac * delta_w;
+            w += delta_w + 0.05 * gauss(&mut rng);
+            bf = 100.0 * fat_kg / w + 0.02 * gauss(&mut rng);
+            tdee += 5.0 * gauss(&mut rng);
+
+            if day % 11 != 0 {
+                kf.predict(intake, 0.0, 0.0);
+            } else {
+                kf.predict(2_200.0, 0.0, MISSING_INTAKE_SD);
+            }
+
+            let w_meas = w + 0.2 * gauss(&mut rng);
+            let bf_meas = bf + 2.0 * gauss(&mut rng);
+            let ok = match day % 7 {
+                3 => true, // no weigh-in
+                5 => kf.update_weight(w_meas),
+                _ => kf.update(bf_meas, w_meas),
+            };
+            if !ok {
+                skipped += 1;
+            }
+
+            if day >= DAYS / 2 {
+                let (e_bf, e_w, e_tdee) = kf.state();
+                let (s_bf, s_w) = std_devs(&kf);
+                let e_fat = e_bf / 100.0 * e_w;
+                let true_fat = bf / 100.0 * w;
+                fat = (e_fat, true_fat);
+                lean = (e_w - e_fat, w - true_fat);
+                fat_sq += (fat.0 - fat.1).powi(2);
+                lean_sq += (lean.0 - lean.1).powi(2);
+                tdee_sq += (e_tdee - tdee).powi(2);
+                if (e_bf - bf).abs() <= 1.96 * s_bf {
+                    covered[0] += 1;
+                }
+                if (e_w - w).abs() <= 1.96 * s_w {
+                    covered[1] += 1;
+                }
+                scored += 1;
+            }
+        }
+
+        let n = scored as f64;
+        Scores {
+            fat_rmse: (fat_sq / n).sqrt(),
+            lean_rmse: (lean_sq / n).sqrt(),
+            tdee_rmse: (tdee_sq / n).sqrt(),
+            coverage: [covered[0] as f64 / n, covered[1] as f64 / n],
+            fat,
+            lean,
+            skipped,
+        }
+    }
+
+    // The filter gets within a few tenths of a kg; the margin keeps the
+    // test from failing on a retune, not on a broken filter
+    const MASS_TOLERANCE_KG: f64 = 1.0;
+
+    #[test]
+    fn fat_and_lean_mass_track_the_truth() {
+        for seed in [7, 11, 23] {
+            let s = simulate(seed);
+            assert!(
+                s.fat_rmse <= MASS_TOLERANCE_KG,
+                "seed {seed}: fat RMSE {:.2} kg",
+                s.fat_rmse
+            );
+            assert!(
+                s.lean_rmse <= MASS_TOLERANCE_KG,
+                "seed {seed}: lean RMSE {:.2} kg",
+                s.lean_rmse
+            );
+            assert!(
+                (s.fat.0 - s.fat.1).abs() <= MASS_TOLERANCE_KG,
+                "seed {seed}: final fat {:.1} kg vs {:.1} kg",
+                s.fat.0,
+                s.fat.1
+            );
+            assert!(
+                (s.lean.0 - s.lean.1).abs() <= MASS_TOLERANCE_KG,
+                "seed {seed}: final lean {:.1} kg vs {:.1} kg",
+                s.lean.0,
+                s.lean.1
+            );
+        }
+    }
+
+    #[test]
+    fn tdee_converges() {
+        let s = simulate(7);
+        assert!(s.tdee_rmse <= 150.0, "TDEE RMSE {:.0} kcal", s.tdee_rmse);
+    }
+
+    #[test]
+    fn bands_cover_the_truth() {
+        let s = simulate(7);
+        assert!(s.coverage[0] >= 0.80, "BF% coverage {:.2}", s.coverage[0]);
+        assert!(
+            s.coverage[1] >= 0.80,
+            "weight coverage {:.2}",
+            s.coverage[1]
+        );
+    }
+
+    #[test]
+    fn missing_readings_are_not_skipped_updates() {
+        assert_eq!(simulate(7).skipped, 0);
+    }
+}
@@ -1,4 +1,6 @@
-use chrono::{DateTime, Datelike, Days, Local, NaiveDate, Weekday};
+use crate::clock;
+use crate::schedule::utils;
+use chrono::{Datelike, Days, NaiveDate, Weekday};
 
 #[allow(dead_code)]
 #[derive(Clone)]
@@ -17,16 +19,6 @@ struct Workout<'a> {
     cardio: Option<String>,
 }
 
-struct Week<'a> {
-    monday: Option<Workout<'a>>,
-    tuesday: Option<Workout<'a>>,
-    wednesday: Option<Workout<'a>>,
-    thursday: Option<Workout<'a>>,
-    friday: Option<Workout<'a>>,
-    saturday: Option<Workout<'a>>,
-    sunday: Option<Workout<'a>>,
-}
-
 fn format_workout<'a>(workout: Workout<'a>) -> String {
     let mut result = String::new();
     {
@@ -38,8 +30,8 @@ fn format_workout<'a>(workout: Workout<'a>) -> String {
             let workout_str = "- [ ] ".to_owned() + &workout.warmup.clone().unwrap() + "\n";
             result.push_str(&workout_str);
         }
-        if workout.strength.is_some() {
-            for lift in workout.strength.as_ref().unwrap() {
+        if let Some(strength) = workout.strength.as_ref() {
+            for lift in strength {
                 for set in lift.sets.clone() {
                     let set_str = "- [ ] ".to_owned()
                         + &lift.name.clone()
@@ -51,7 +43,7 @@ fn format_workout<'a>(workout: Workout<'a>) -> String {
                     result.push_str(&set_str);
                 }
             }
-            result.push_str("\n");
+            result.push('\n');
         }
     }
 
@@ -64,7 +56,13 @@ fn format_workout<'a>(workout: Workout<'a>) -> String {
 }
 
 pub fn get_lifts() -> String {
-    let week_no = crate::schedule::utils::current_week_number();
+    lifts_for(clock::today())
+}
+
+/// Tomorrow's workout as seen from `today`, following the 5-week
+/// test/build/deload rotation
+pub fn lifts_for(today: NaiveDate) -> String {
+    let week_no = utils::week_number(today);
     let _monday = if week_no % 5 == 5 {
         Workout {
             warmup: Some("- [ ] 5 min erg @ 22spm w df 1".to_string()),
@@ -263,12 +261,6 @@ pub fn get_lifts() -> String {
         cardio: None,
     };
 
-    let saturday = Workout {
-        warmup: None,
-        strength: None,
-        cardio: Some("- [ ] 5 min df 1\n- [ ] 5000m @ 22spm".to_string()),
-    };
-
     let grindset = Workout {
         warmup: None,
         strength: None,
@@ -287,15 +279,9 @@ pub fn get_lifts() -> String {
         warmup: None,
     };
 
-    let local: DateTime<Local> = Local::now();
-    let tomorrow: NaiveDate =
-        NaiveDate::from_ymd_opt(local.year(), local.month(), local.day()).unwrap();
-    let tomorrow = tomorrow + Days::new(1);
-    let day = tomorrow.weekday();
-
-    let week_no = crate::schedule::utils::current_week_number();
+    let day = (today + Days::new(1)).weekday();
 
-    let monday = if week_no % 5 == 0 {
+    let monday = if week_no.is_multiple_of(5) {
         Workout {
             warmup: Some("- [ ] 5 min erg @ 22spm w df 1".to_string()),
             cardio: Some("- [ ] 2000k @28spm".to_string()),
@@ -327,3 +313,61 @@ pub fn get_lifts() -> String {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GRINDSET: &str = "# Cardio\n- [ ] 5 min df 1\n- [ ] 20m @ 22spm";
+    const DELOAD: &str = "# Cardio\n20m @ 18spm Df 1";
+    const TEST_DAY: &str =
+        "# Heben\n## LIFT\n- [ ] - [ ] 5 min erg @ 22spm w df 1\n# Cardio\n- [ ] 2000k @28spm";
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn build_weeks_repeat_the_same_plan() {
+        // ISO weeks 1, 2 and 3 of 2026, each from Monday
+        for monday in ["2025-12-29", "2026-01-05", "2026-01-12"] {
+            let monday = date(monday);
+            // Tuesday through Saturday
+            let plan = [GRINDSET, "", GRINDSET, GRINDSET, ""];
+            for (offset, expected) in (0..).zip(plan) {
+                let today = monday + Days::new(offset);
+                assert_eq!(lifts_for(today), expected, "day after {today}");
+            }
+        }
+    }
+
+    #[test]
+    fn fourth_week_deloads() {
+        // ISO week 4 of 2026
+        assert_eq!(lifts_for(date("2026-01-19")), "");
+        assert_eq!(lifts_for(date("2026-01-20")), DELOAD);
+        assert_eq!(lifts_for(date("2026-01-21")), "");
+        assert_eq!(lifts_for(date("2026-01-22")), DELOAD);
+    }
+
+    #[test]
+    fn monday_follows_sundays_week() {
+        // Sunday of week 1: an ordinary Monday
+        assert_eq!(lifts_for(date("2026-01-04")), GRINDSET);
+        // Sunday of week 4: a deload Monday
+        assert_eq!(lifts_for(date("2026-01-25")), DELOAD);
+        // Sunday of week 5: the test Monday
+        assert_eq!(lifts_for(date("2026-02-01")), TEST_DAY);
+    }
+
+    #[test]
+    fn rotation_wraps_around() {
+        // Week 49 deloads and week 50 starts the next cycle with a test
+        assert_eq!(lifts_for(date("2026-12-01")), DELOAD);
+        assert_eq!(lifts_for(date("2026-12-13")), TEST_DAY);
+        // 2026 has 53 ISO weeks; week 53 and week 1 of 2027 are both build weeks
+        assert_eq!(lifts_for(date("2027-01-03")), GRINDSET);
+        assert_eq!(lifts_for(date("2027-01-05")), "");
+        assert_eq!(lifts_for(date("2027-01-06")), GRINDSET);
+    }
+}
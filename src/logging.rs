@@ -1,7 +1,9 @@
-use crate::VAULT_DIR;
-use crate::read_input;
+use crate::audit;
+use crate::clock;
+use crate::config;
+use crate::db;
+use crate::prompt_or;
 use crate::util;
-use chrono::{DateTime, Datelike, Local, NaiveDate};
 use regex::Regex;
 use rusqlite::{Connection, Result, params};
 use std::fs;
@@ -13,487 +15,251 @@ struct Task {
     name: String,
 }
 
-pub fn log_reading(time: i32, rtype: String) -> Result<()> {
-    let db_path = util::get_database_path()?;
-    let conn = Connection::open(&db_path)?;
+// Print the distinct past values from `query` and let the user pick one by
+// index or type a new one
+fn pick_or_enter(conn: &Connection, query: &str, prompt: &str) -> Result<String> {
+    let mut stmt = conn.prepare(query)?;
 
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS reading (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            date_time TEXT NOT NULL,
-            time INTEGER NOT NULL,
-            type TEXT NOT NULL
-        )",
-        [],
-    )?;
-
-    let now: DateTime<Local> = Local::now();
-    let date_time_str = now.format("%Y-%m-%d %H:%M:%S").to_string();
-
-    conn.execute(
-        "INSERT INTO reading (date_time, time,type) VALUES (?1, ?2, ?3)",
-        [&date_time_str, &time.to_string(), &rtype],
-    )?;
-
-    println!("Logged reading: {} min at {}", time, date_time_str);
-
-    Ok(())
-}
-
-pub fn log_time() -> Result<()> {
-    let db_path = util::get_database_path()?;
-    let conn = Connection::open(&db_path)?;
-
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS time (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            date_time TEXT NOT NULL,
-            category TEXT NOT NULL,
-            time INTEGER NOT NULL
-        )",
-        [],
-    )?;
-
-    let mut stmt = conn.prepare("select category from time group by category;")?;
-
-    let past_violations = stmt.query_map([], |row| Ok(row.get::<_, String>(0)?))?;
+    let past = stmt.query_map([], |row| row.get::<_, String>(0))?;
 
     let mut options = Vec::new();
 
-    for (idx, violation) in past_violations.enumerate() {
-        let name = violation?;
+    for (idx, name) in past.enumerate() {
+        let name = name?;
         options.push(name.clone());
         println!("{idx}: {name}");
     }
 
-    print!("Enter Category: ");
+    print!("{prompt}");
     let mut input = String::new();
     io::stdout().flush().unwrap();
     io::stdin().read_line(&mut input).unwrap();
 
     let choice = match input.trim().parse::<usize>() {
-        Ok(val) => options[val].clone(),
-        Err(_) => input.trim().to_string(),
+        Ok(idx) if idx < options.len() => options[idx].clone(),
+        _ => input.trim().to_string(),
     };
 
-    print!("Enter Time (minutes): ");
-    let mut input = String::new();
-    io::stdout().flush().unwrap();
-    io::stdin().read_line(&mut input).unwrap();
+    Ok(choice)
+}
 
-    let time = match input.trim().parse::<u64>() {
-        Ok(val) => val,
-        Err(_) => panic!("Couldn't parse amount"),
+pub fn log_time(category: Option<String>, minutes: Option<u64>) -> Result<()> {
+    let conn = db::open()?;
+
+    let choice = match category {
+        Some(category) => category,
+        None => pick_or_enter(
+            &conn,
+            "select category from time group by category;",
+            "Enter Category: ",
+        )?,
     };
 
+    let time: u64 = prompt_or(minutes, "Enter Time (minutes): ");
+
     conn.execute(
-        "INSERT INTO time (date_time, category, time) VALUES (datetime('now', 'localtime'), ?1, ?2)",
-        (&choice, &time),
+        "INSERT INTO time (date_time, category, time) VALUES (?1, ?2, ?3)",
+        (clock::timestamp(), &choice, &time),
     )?;
+    audit::record_insert(&conn, "time", "time", conn.last_insert_rowid())?;
 
-    println!("Logged violation: {}", choice);
+    println!("Logged time: {} {} min", choice, time);
 
     Ok(())
 }
 
-pub fn log_violations() -> Result<()> {
-    let db_path = util::get_database_path()?;
-    let conn = Connection::open(&db_path)?;
-
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS violations (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            date_time TEXT NOT NULL,
-            name TEXT NOT NULL
-        )",
-        [],
-    )?;
-
-    let mut stmt = conn.prepare("select name from violations group by  name;")?;
-
-    let past_violations = stmt.query_map([], |row| Ok(row.get::<_, String>(0)?))?;
+pub fn log_violations(name: Option<String>) -> Result<()> {
+    let conn = db::open()?;
 
-    let mut options = Vec::new();
-
-    for (idx, violation) in past_violations.enumerate() {
-        let name = violation?;
-        options.push(name.clone());
-        println!("{idx}: {name}");
-    }
-
-    print!("Enter violation: ");
-    let mut input = String::new();
-    io::stdout().flush().unwrap();
-    io::stdin().read_line(&mut input).unwrap();
-
-    let choice = match input.trim().parse::<usize>() {
-        Ok(val) => options[val].clone(),
-        Err(_) => input.trim().to_string(),
+    let choice = match name {
+        Some(name) => name,
+        None => pick_or_enter(
+            &conn,
+            "select name from violations group by  name;",
+            "Enter violation: ",
+        )?,
     };
     conn.execute(
-        "INSERT INTO violations (date_time, name) VALUES (datetime('now', 'localtime'), ?1)",
-        [&choice],
+        "INSERT INTO violations (date_time, name) VALUES (?1, ?2)",
+        [&clock::timestamp(), &choice],
     )?;
+    audit::record_insert(&conn, "violation", "violations", conn.last_insert_rowid())?;
 
     println!("Logged violation: {}", choice);
 
     Ok(())
 }
 
-pub fn log_win() -> Result<()> {
-    let db_path = util::get_database_path()?;
-    let conn = Connection::open(&db_path)?;
-
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS win (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            date_time TEXT NOT NULL,
-            name TEXT NOT NULL
-        )",
-        [],
-    )?;
-
-    let mut stmt = conn.prepare("select name from win group by  name;")?;
+pub fn log_win(name: Option<String>) -> Result<()> {
+    let conn = db::open()?;
 
-    let past_win = stmt.query_map([], |row| Ok(row.get::<_, String>(0)?))?;
-
-    let mut options = Vec::new();
-
-    for (idx, violation) in past_win.enumerate() {
-        let name = violation?;
-        options.push(name.clone());
-        println!("{idx}: {name}");
-    }
-
-    print!("Enter violation: ");
-    let mut input = String::new();
-    io::stdout().flush().unwrap();
-    io::stdin().read_line(&mut input).unwrap();
-
-    let choice = match input.trim().parse::<usize>() {
-        Ok(val) => options[val].clone(),
-        Err(_) => input.trim().to_string(),
+    let choice = match name {
+        Some(name) => name,
+        None => pick_or_enter(&conn, "select name from win group by  name;", "Enter win: ")?,
     };
     conn.execute(
-        "INSERT INTO win (date_time, name) VALUES (datetime('now', 'localtime'), ?1)",
-        [&choice],
+        "INSERT INTO win (date_time, name) VALUES (?1, ?2)",
+        [&clock::timestamp(), &choice],
     )?;
+    audit::record_insert(&conn, "win", "win", conn.last_insert_rowid())?;
 
     println!("Logged win: {}", choice);
 
     Ok(())
 }
 
-pub fn _log_big_game_hunting(time: i32) -> Result<()> {
-    let db_path = util::get_database_path()?;
-    let conn = Connection::open(&db_path)?;
-
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS big_game_hunting (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            date_time TEXT NOT NULL,
-            time INTEGER NOT NULL
-        )",
-        [],
-    )?;
-
-    let now: DateTime<Local> = Local::now();
-    let date_time_str = now.format("%Y-%m-%d %H:%M:%S").to_string();
-
-    conn.execute(
-        "INSERT INTO big_game_hunting (date_time, time) VALUES (?1, ?2)",
-        [&date_time_str, &time.to_string()],
-    )?;
-
-    println!("Logged Big Gme Hunting: {} min at {}", time, date_time_str);
-
-    Ok(())
-}
-
-pub fn log_link(time: i32) -> Result<()> {
-    let db_path = util::get_database_path()?;
-    let conn = Connection::open(&db_path)?;
-
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS link (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            date_time TEXT NOT NULL,
-            time INTEGER NOT NULL
-        )",
-        [],
-    )?;
-
-    let now: DateTime<Local> = Local::now();
-    let date_time_str = now.format("%Y-%m-%d %H:%M:%S").to_string();
-
-    conn.execute(
-        "INSERT INTO link (date_time, time) VALUES (?1, ?2)",
-        [&date_time_str, &time.to_string()],
-    )?;
-
-    println!("Logged link: {} min at {}", time, date_time_str);
-
-    Ok(())
+/// One balance snapshot: a row for every `[[debts]]` account, prompting for
+/// the ones not given
+pub fn log_debt(given: Vec<(String, f64)>) -> anyhow::Result<()> {
+    let accounts: Vec<(&str, &str)> = config::get()
+        .debts
+        .iter()
+        .map(|d| (d.name.as_str(), d.label.as_str()))
+        .collect();
+    if accounts.is_empty() {
+        anyhow::bail!("no debt accounts; add [[debts]] entries to the config");
+    }
+    log_balances("debt", "debt_balance", &accounts, given)
 }
 
-pub fn log_debt() -> Result<()> {
-    let db_path = util::get_database_path()?;
-    let conn = Connection::open(&db_path)?;
-
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS debt (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            date_time TEXT NOT NULL,
-            bofa_travel REAL NOT NULL,
-            bofa_reward REAL NOT NULL,
-            discover REAL NOT NULL,
-            imprint REAL NOT NULL,
-            paypal REAL NOT NULL,
-            affirm REAL NOT NULL,
-            cisco REAL NOT NULL,
-            medical REAL NOT NULL,
-            schwab REAL NOT NULL
-            )",
-        [],
-    )?;
-
-    print!("Enter bofa_travel: ");
-    let bofa_travel: f64 = read_input();
-
-    print!("Enter bofa_reward: ");
-    let bofa_reward: f64 = read_input();
-
-    print!("Enter discover: ");
-    let discover: f64 = read_input();
-
-    print!("Enter imprint: ");
-    let imprint: f64 = read_input();
-    print!("Enter paypal: ");
-    let paypal: f64 = read_input();
-    print!("Enter affirm: ");
-    let affirm: f64 = read_input();
-    print!("Enter cisco: ");
-    let cisco: f64 = read_input();
-
-    print!("Enter medical: ");
-    let medical: f64 = read_input();
-
-    print!("Enter schwab: ");
-    let schwab: f64 = read_input();
-
-    conn.execute(
-        "INSERT INTO debt (date_time, bofa_travel, bofa_reward, discover, imprint, paypal, affirm, cisco, medical, schwab) VALUES (datetime('now', 'localtime'), ?1, ?2, ?3,?4,?5,?6,?7,?8,?9)",
-        (bofa_travel, bofa_reward, discover, imprint, paypal, affirm, cisco, medical, schwab),
-    )?;
-
-    Ok(())
+pub fn log_asset(given: Vec<(String, f64)>) -> anyhow::Result<()> {
+    let accounts: Vec<(&str, &str)> = config::get()
+        .assets
+        .iter()
+        .map(|a| (a.name.as_str(), a.label.as_str()))
+        .collect();
+    if accounts.is_empty() {
+        anyhow::bail!("no asset accounts; add [[assets]] entries to the config");
+    }
+    log_balances("asset", "asset_balance", &accounts, given)
 }
 
-pub fn log_ccna(time: i32) -> Result<()> {
-    let db_path = util::get_database_path()?;
-    let conn = Connection::open(&db_path)?;
-
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS ccna (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            date_time TEXT NOT NULL,
-            time INTEGER NOT NULL
-        )",
-        [],
-    )?;
-
-    let now: DateTime<Local> = Local::now();
-    let date_time_str = now.format("%Y-%m-%d %H:%M:%S").to_string();
-
-    conn.execute(
-        "INSERT INTO ccna (date_time, time) VALUES (?1, ?2)",
-        [&date_time_str, &time.to_string()],
-    )?;
+// One row per (name, label) account in `table`, all with the same time;
+// balances not in `given` are prompted for. Nothing is saved unless every
+// balance is, so an interrupted prompt can't leave a partial snapshot.
+fn log_balances(
+    kind: &str,
+    table: &str,
+    accounts: &[(&str, &str)],
+    given: Vec<(String, f64)>,
+) -> anyhow::Result<()> {
+    if let Some((name, _)) = given
+        .iter()
+        .find(|(name, _)| !accounts.iter().any(|(known, _)| known == name))
+    {
+        let known: Vec<&str> = accounts.iter().map(|&(name, _)| name).collect();
+        anyhow::bail!(
+            "unknown {} account '{}', expected one of: {}",
+            kind,
+            name,
+            known.join(", ")
+        );
+    }
 
-    println!("Logged ccna: {} min at {}", time, date_time_str);
+    let mut conn = db::open()?;
+    let tx = conn.transaction()?;
+    let date_time = clock::timestamp();
+    let mut batch = audit::Batch::new();
+    for &(account, label) in accounts {
+        let balance = given
+            .iter()
+            .rev()
+            .find(|(name, _)| name == account)
+            .map(|&(_, balance)| balance);
+        let balance: f64 = prompt_or(balance, &format!("Enter {}: ", label));
+
+        tx.execute(
+            &format!("INSERT INTO {table} (date_time, account, balance) VALUES (?1, ?2, ?3)"),
+            (&date_time, account, balance),
+        )?;
+        batch.record_insert(&tx, kind, table, tx.last_insert_rowid())?;
+    }
+    tx.commit()?;
 
     Ok(())
 }
 
-pub fn log_pwn_college(time: i32) -> Result<()> {
-    let db_path = util::get_database_path()?;
-    let conn = Connection::open(&db_path)?;
+pub fn log_income(source: Option<String>, amount: Option<f64>) -> Result<()> {
+    let conn = db::open()?;
 
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS pwn_college (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            date_time TEXT NOT NULL,
-            time INTEGER NOT NULL
-        )",
-        [],
-    )?;
-
-    let now: DateTime<Local> = Local::now();
-    let date_time_str = now.format("%Y-%m-%d %H:%M:%S").to_string();
+    let source = match source {
+        Some(source) => source,
+        None => pick_or_enter(
+            &conn,
+            "select source from income group by source;",
+            "Enter Source: ",
+        )?,
+    };
 
+    let amount: f64 = prompt_or(amount, "Enter Amount: ");
     conn.execute(
-        "INSERT INTO pwn_college (date_time, time) VALUES (?1, ?2)",
-        [&date_time_str, &time.to_string()],
+        "INSERT INTO income (date_time, amount, source) VALUES (?1, ?2, ?3)",
+        (clock::timestamp(), &amount, &source),
     )?;
+    audit::record_insert(&conn, "income", "income", conn.last_insert_rowid())?;
 
-    println!("Logged pwn.college: {} min at {}", time, date_time_str);
+    println!("Logged income: {} {}", source, amount);
 
     Ok(())
 }
 
-pub fn log_spend() -> Result<()> {
-    let db_path = util::get_database_path()?;
-    let conn = Connection::open(&db_path)?;
-
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS spend (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            date_time TEXT NOT NULL,
-            amount REAL NOT NULL,
-            name TEXT NOT NULL,
-            category TEXT NOT NULL
-        )",
-        [],
-    )?;
-
-    let mut stmt = conn.prepare("select category from spend group by category;")?;
-
-    let past_categories = stmt.query_map([], |row| Ok(row.get::<_, String>(0)?))?;
-
-    let mut options = Vec::new();
-
-    for (idx, violation) in past_categories.enumerate() {
-        let name = violation?;
-        options.push(name.clone());
-        println!("{idx}: {name}");
-    }
-
-    print!("Enter Category: ");
-    let mut input = String::new();
-    io::stdout().flush().unwrap();
-    io::stdin().read_line(&mut input).unwrap();
-
-    let category = match input.trim().parse::<usize>() {
-        Ok(val) => options[val].clone(),
-        Err(_) => input.trim().to_string(),
+pub fn log_spend(
+    category: Option<String>,
+    name: Option<String>,
+    amount: Option<f64>,
+) -> Result<()> {
+    let conn = db::open()?;
+
+    let category = match category {
+        Some(category) => category,
+        None => pick_or_enter(
+            &conn,
+            "select category from spend group by category;",
+            "Enter Category: ",
+        )?,
     };
 
-    print!("Enter Name: ");
-    let mut input = String::new();
-    io::stdout().flush().unwrap();
-    io::stdin().read_line(&mut input).unwrap();
-
-    let name = input.trim();
-
-    print!("Enter Amount: ");
-    let mut input = String::new();
-    io::stdout().flush().unwrap();
-    io::stdin().read_line(&mut input).unwrap();
-
-    let amount = match input.trim().parse::<f64>() {
-        Ok(val) => val,
-        Err(_) => panic!("Couldn't parse amount"),
+    let name = match name {
+        Some(name) => name,
+        None => {
+            print!("Enter Name: ");
+            let mut input = String::new();
+            io::stdout().flush().unwrap();
+            io::stdin().read_line(&mut input).unwrap();
+            input.trim().to_string()
+        }
     };
-    conn.execute(
-        "INSERT INTO spend (date_time, amount, name, category) VALUES (datetime('now', 'localtime'), ?1, ?2, ?3)",
-        (&amount, &name, &category),
-    )?;
-
-    println!("Logged spend: {} {}: {}", name, amount, category);
-
-    Ok(())
-}
-
-pub fn log_exit(time: i32) -> Result<()> {
-    let db_path = util::get_database_path()?;
-    let conn = Connection::open(&db_path)?;
 
+    let amount: f64 = prompt_or(amount, "Enter Amount: ");
     conn.execute(
-        "CREATE TABLE IF NOT EXISTS exit (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            date_time TEXT NOT NULL,
-            time INTEGER NOT NULL
-        )",
-        [],
+        "INSERT INTO spend (date_time, amount, name, category) VALUES (?1, ?2, ?3, ?4)",
+        (clock::timestamp(), &amount, &name, &category),
     )?;
+    audit::record_insert(&conn, "spend", "spend", conn.last_insert_rowid())?;
 
-    let now: DateTime<Local> = Local::now();
-    let date_time_str = now.format("%Y-%m-%d %H:%M:%S").to_string();
-
-    conn.execute(
-        "INSERT INTO exit (date_time, time) VALUES (?1, ?2)",
-        [&date_time_str, &time.to_string()],
-    )?;
-
-    println!("Logged exit: {} min at {}", time, date_time_str);
-
-    Ok(())
-}
-
-pub fn log_caffeine(caffeine_mg: i32) -> Result<()> {
-    let db_path = util::get_database_path()?;
-    let conn = Connection::open(&db_path)?;
-
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS caffeine (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            date_time TEXT NOT NULL,
-            caffeine_mg INTEGER NOT NULL
-        )",
-        [],
-    )?;
-
-    let now: DateTime<Local> = Local::now();
-    let date_time_str = now.format("%Y-%m-%d %H:%M:%S").to_string();
-
-    conn.execute(
-        "INSERT INTO caffeine (date_time, caffeine_mg) VALUES (?1, ?2)",
-        [&date_time_str, &caffeine_mg.to_string()],
-    )?;
-
-    println!(
-        "Logged caffeine intake: {}mg at {}",
-        caffeine_mg, date_time_str
-    );
+    println!("Logged spend: {} {}: {}", name, amount, category);
 
     Ok(())
 }
 
-pub fn log_row() -> Result<()> {
-    let db_path = util::get_database_path()?;
-    let conn = Connection::open(&db_path)?;
-
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS row (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            date_time TEXT NOT NULL,
-            distance INTEGER NOT NULL,
-            time INTEGER NOT NULL,
-            watts INTEGER NOT NULL,
-            cals INTEGER NOT NULL
-        )",
-        [],
-    )?;
-
-    let now: DateTime<Local> = Local::now();
-    let date_time_str = now.format("%Y-%m-%d %H:%M:%S").to_string();
-
-    print!("Enter distance (m): ");
-    let distance: u64 = read_input();
+pub fn log_row(
+    distance: Option<u64>,
+    time: Option<u64>,
+    watts: Option<f64>,
+    cals: Option<f64>,
+) -> Result<()> {
+    let conn = db::open()?;
 
-    print!("Enter time (m): ");
-    let time: u64 = read_input();
-
-    print!("Enter watts: ");
-    let watts: f64 = read_input();
-
-    print!("Enter cals: ");
-    let cals: f64 = read_input();
+    let distance: u64 = prompt_or(distance, "Enter distance (m): ");
+    let time: u64 = prompt_or(time, "Enter time (m): ");
+    let watts: f64 = prompt_or(watts, "Enter watts: ");
+    let cals: f64 = prompt_or(cals, "Enter cals: ");
 
     conn.execute(
-        "INSERT INTO row (date_time, distance, time, watts, cals) VALUES (datetime('now', 'localtime'), ?1, ?2, ?3, ?4 )",
-        (distance, time, watts, cals),
+        "INSERT INTO row (date_time, distance, time, watts, cals) VALUES (?1, ?2, ?3, ?4, ?5)",
+        (clock::timestamp(), distance, time, watts, cals),
     )?;
+    audit::record_insert(&conn, "row", "row", conn.last_insert_rowid())?;
 
     println!("Logged {}m row", distance);
 
@@ -501,12 +267,14 @@ pub fn log_row() -> Result<()> {
 }
 
 fn tasks_to_insert(conn: &Connection, tasks: Vec<Task>) -> Vec<Task> {
-    let mut stmt = conn.prepare(
-        "select task_id from completed_todos where date(date_time) ==  date(datetime('now', 'localtime'))",
-    ).unwrap();
+    let mut stmt = conn
+        .prepare("select task_id from completed_todos where date(date_time) == ?1")
+        .unwrap();
 
     let id_iter = stmt
-        .query_map([], |row| Ok(row.get::<_, u8>(0).unwrap()))
+        .query_map([clock::today().to_string()], |row| {
+            Ok(row.get::<_, u8>(0).unwrap())
+        })
         .unwrap();
 
     let inserted_id: Vec<u8> = id_iter.into_iter().map(|x| x.unwrap()).collect();
@@ -524,30 +292,16 @@ fn tasks_to_insert(conn: &Connection, tasks: Vec<Task>) -> Vec<Task> {
 
 fn insert_task(conn: &Connection, task: &Task) {
     conn.execute(
-        "INSERT INTO completed_todos (task_id, date_time, task) VALUES (?1,datetime('now','localtime'), ?2)",
-        params![task.id, task.name],
-    ).unwrap();
-}
-pub fn log_tasks() {
-    let db_path = util::get_database_path().unwrap();
-    let conn = Connection::open(&db_path).unwrap();
-
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS completed_todos (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            task_id INTEGER NOT NULL,
-            date_time TEXT NOT NULL,
-            task TEXT NOT NULL
-        );",
-        [],
+        "INSERT INTO completed_todos (task_id, date_time, task) VALUES (?1, ?2, ?3)",
+        params![task.id, clock::timestamp(), task.name],
     )
     .unwrap();
+}
+pub fn log_tasks() {
+    let conn = db::open().unwrap();
 
-    let local: DateTime<Local> = Local::now();
-    let today: NaiveDate =
-        NaiveDate::from_ymd_opt(local.year(), local.month(), local.day()).unwrap();
-    let date_str = today.format("%Y-%m-%d").to_string();
-    let filename = format!("{}/{}.md", VAULT_DIR, date_str);
+    let date_str = clock::today().format("%Y-%m-%d").to_string();
+    let filename = util::vault_path(&format!("{}.md", date_str));
 
     let re = Regex::new(r"([0-9]+). \[x\] ([0-9a-zA-z ]+)").unwrap();
 
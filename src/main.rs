@@ -1,142 +1,58 @@
 mod affirmations;
+mod audit;
+mod backtest;
+mod budget;
+mod calorie_target;
+mod chart;
+mod cli;
+mod clock;
+mod config;
+mod dashboard;
+mod db;
+mod debt;
+mod entries;
+mod events;
+mod finance;
+mod forecast;
+mod kalman_bf;
 mod lift;
 mod logging;
 mod menu;
 mod model1;
+mod output;
 mod publish;
+mod recurring;
+mod rts;
 mod s3_sync;
 mod schedule;
+mod statement;
 mod stats;
+mod tune;
 mod util;
+mod weight;
+mod ynab;
 
-use chrono::Duration;
-use chrono::{Datelike, Local, NaiveDate};
+use chrono::{Datelike, NaiveDate};
 use clap::Parser;
-use csv::WriterBuilder;
-use std::fs::OpenOptions;
 use std::io;
 use std::io::Write;
 
-use crate::util::VAULT_DIR;
-
-#[derive(Parser, Debug)]
-#[command()]
-struct Args {
-    #[arg(long)]
-    week_no: bool,
-
-    /// Print Monday's schedule to stdout
-    #[arg(long)]
-    monday: bool,
-
-    #[arg(long)]
-    report: bool,
-
-    #[arg(long)]
-    log_time: bool,
-    /// Print Tuesday's schedule to stdout
-    #[arg(long)]
-    tuesday: bool,
-
-    #[arg(long)]
-    publish: bool,
-
-    /// Print Wednesday's schedule to stdout
-    #[arg(long)]
-    wednesday: bool,
-
-    /// Print Thursday's schedule to stdout
-    #[arg(long)]
-    thursday: bool,
-
-    /// Print Friday's schedule to stdout
-    #[arg(long)]
-    friday: bool,
-
-    /// Print Saturday's schedule to stdout
-    #[arg(long)]
-    saturday: bool,
-
-    /// Print Sunday's schedule to stdout
-    #[arg(long)]
-    sunday: bool,
-
-    #[arg(long)]
-    weight: bool,
-
-    #[arg(long)]
-    predict: bool,
-
-    #[arg(long)]
-    lift: bool,
-
-    #[arg(long)]
-    model1: bool,
-
-    #[arg(long)]
-    model2: bool,
-
-    #[arg(long)]
-    month: bool,
-
-    #[arg(long)]
-    financial: bool,
-
-    #[arg(long)]
-    bored: bool,
-
-    #[arg(long)]
-    debt: bool,
-
-    #[arg(long)]
-    workout: bool,
-
-    #[arg(long)]
-    violation: bool,
-
-    #[arg(long)]
-    win: bool,
-
-    #[arg(long)]
-    log: bool,
-
-    #[arg(long)]
-    sync: bool,
-
-    #[arg(long)]
-    makeup: bool,
-
-    #[arg(long)]
-    read: bool,
-
-    #[arg(long)]
-    query_undone: bool,
-
-    #[arg(long)]
-    task_summary: bool,
-
-    #[arg(long)]
-    next: bool,
-
-    #[arg(long)]
-    exit: bool,
-
-    #[arg(long)]
-    work_sum: bool,
-
-    #[arg(long)]
-    spend: bool,
-
-    #[arg(long)]
-    row: bool,
-}
+use crate::cli::{
+    Args, BudgetCommand, ChartCommand, Command, ConfigCommand, DbCommand, DebtCommand,
+    EntriesCommand, ExportCommand, FinanceCommand, Format, ImportCommand, LogCommand, ModelCommand,
+    RecurringCommand, ReportCommand, ScheduleCommand,
+};
 
 // Simple number reader
 fn read_input<T: std::str::FromStr>() -> T {
     loop {
         let mut input = String::new();
         io::stdout().flush().unwrap();
-        io::stdin().read_line(&mut input).unwrap();
+        // Out of input: stop instead of asking forever
+        if io::stdin().read_line(&mut input).unwrap() == 0 {
+            eprintln!("No input");
+            std::process::exit(1);
+        }
 
         match input.trim().parse::<T>() {
             Ok(val) => return val,
@@ -147,49 +63,19 @@ fn read_input<T: std::str::FromStr>() -> T {
     }
 }
 
-fn log_weight() {
-    let weight: f64;
-    let cals_realized: u32;
-
-    print!("Enter today's am weight (e.g. 294.6): ");
-    weight = read_input();
-
-    print!("Enter cals_realized (e.g. 1979): ");
-    cals_realized = read_input();
-
-    print!("Enter protein in g ");
-    let protein: u32 = read_input();
-
-    print!("Enter strava RE score ");
-    let strava_re: u32 = read_input();
-
-    let today: String = Local::now().format("%Y-%m-%d").to_string();
-
-    let record = vec![
-        today,
-        format!("{weight}"),
-        format!("{}", cals_realized as f64),
-        format!("{}", strava_re),
-        format!("{}", protein),
-    ];
-    println!();
-    println!("Update calendar");
-
-    let file_path = "data/weight_energy.csv";
-    let file = OpenOptions::new()
-        .append(true)
-        .create(true)
-        .open(file_path)
-        .expect("Unable to open weight.csv");
-
-    let mut wtr = WriterBuilder::new().has_headers(false).from_writer(file);
-
-    wtr.write_record(&record).expect("Write failed");
-    wtr.flush().expect("Flush failed");
+// Use the value passed on the command line, only prompt when it was omitted
+fn prompt_or<T: std::str::FromStr>(value: Option<T>, prompt: &str) -> T {
+    match value {
+        Some(val) => val,
+        None => {
+            print!("{prompt}");
+            read_input()
+        }
+    }
 }
 
-fn month_gen() {
-    let today = Local::now();
+fn month_gen(pick: Option<u32>) {
+    let today = clock::today();
     let months = [
         "January",
         "February",
@@ -205,208 +91,361 @@ fn month_gen() {
         "December",
     ];
 
-    for (idx, month) in months.into_iter().enumerate() {
-        println!("{month}: {}", idx + 1);
-    }
-    print!("Month to gen: ");
-    let pick: u32 = read_input();
+    let pick = match pick {
+        Some(pick) => pick,
+        None => {
+            for (idx, month) in months.into_iter().enumerate() {
+                println!("{month}: {}", idx + 1);
+            }
+            print!("Month to gen: ");
+            read_input()
+        }
+    };
 
-    //let (year, month) = (today.year(), today.month());
     let (year, month) = (today.year(), pick);
     let mut day = 1;
 
-    loop {
-        if let Some(date) = NaiveDate::from_ymd_opt(year, month, day) {
-            let weekday_letter = date
-                .weekday()
-                .to_string()
-                .to_lowercase()
-                .chars()
-                .nth(0)
-                .unwrap();
-            println!("{}{} ", day, weekday_letter);
-            day += 1;
-        } else {
-            break;
-        }
+    while let Some(date) = NaiveDate::from_ymd_opt(year, month, day) {
+        let weekday_letter = date
+            .weekday()
+            .to_string()
+            .to_lowercase()
+            .chars()
+            .next()
+            .unwrap();
+        println!("{}{} ", day, weekday_letter);
+        day += 1;
     }
 }
 
-fn main() {
-    let args = Args::parse();
-    if args.weight {
-        log_weight();
-
-        // Sync data directory to S3
-        println!("Syncing data to S3...");
-        if let Err(e) = s3_sync::sync_data_to_s3() {
-            eprintln!("Error syncing data to S3: {}", e);
-        }
-
-        schedule::write_tomorrow_to_vault();
-        model1::run("data/weight_energy.csv").unwrap();
+fn sync() {
+    println!("Syncing data to S3...");
+    if let Err(e) = s3_sync::sync_data_to_s3() {
+        eprintln!("Error syncing data to S3: {}", e);
     }
+}
 
-    if args.publish {
-        publish::publish();
-    }
+fn bored() {
+    print!("Start stopwatch Y/n: ");
+    let mut input: String = String::new();
+    io::stdout().flush().unwrap();
+    io::stdin().read_line(&mut input).unwrap();
 
-    if args.sync {
-        println!("Syncing data to S3...");
-        if let Err(e) = s3_sync::sync_data_to_s3() {
-            eprintln!("Error syncing data to S3: {}", e);
+    match events::random_bored_activity() {
+        Some(activity) => {
+            if let Err(e) = events::log_activity(Some(activity.name.clone()), None, vec![], None) {
+                eprintln!("Error logging {}: {}", activity.name, e);
+            }
         }
+        None => println!("No activities have a bored_weight in the config"),
     }
 
-    if args.predict {
-        model1::run("data/weight_energy.csv").unwrap();
-    }
-
-    if args.model1 {
-        model1::run("data/weight_energy.csv").unwrap();
-    }
-
-    if args.model2 {
-        model1::run_one_state("data/weight_energy.csv").unwrap();
-    }
-
-    fn read_task(name: String) -> i32 {
-        print!("Enter {} amount in mins: ", name);
-        read_input()
-    }
-
-    if args.read {
-        logging::log_reading(
-            read_task("Reading Pleasure".to_string()),
-            String::from("pleasure"),
-        )
-        .unwrap();
-    }
-
-    if args.report {
-        stats::report().unwrap();
-    }
-
-    if args.bored {
-        print!("Start stopwatch Y/n: ");
-        let mut input: String = String::new();
-        io::stdout().flush().unwrap();
-        io::stdin().read_line(&mut input).unwrap();
+    print!("Updated Anki Y/n: ");
+    io::stdout().flush().unwrap();
+    io::stdin().read_line(&mut input).unwrap();
+}
 
-        // Link + Read pleasure
-        if rand::random_bool(0.3) {
-            if rand::random_bool(0.5) {
-                logging::log_reading(
-                    read_task("Reading technical".to_string()),
-                    String::from("technical"),
-                )
-                .unwrap();
-            } else {
-                logging::log_link(read_task("Link".to_string())).unwrap();
-            }
-        } else {
-            if rand::random_bool(0.6) {
-                logging::log_ccna(read_task("CCNA".to_string())).unwrap();
-            } else {
-                logging::log_pwn_college(read_task("Pwn.college".to_string())).unwrap();
+fn run_log(entry: LogCommand) {
+    match entry {
+        LogCommand::Weight(args) => {
+            if let Err(e) = weight::log_weight(args) {
+                eprintln!("Error logging weight: {:#}", e);
+                std::process::exit(1);
+            }
+            sync();
+            schedule::write_tomorrow_to_vault();
+            if let Err(e) = model1::run(Format::Table) {
+                eprintln!("Weight model failed: {:#}", e);
+                std::process::exit(1);
             }
         }
-
-        print!("Updated Anki Y/n: ");
-        io::stdout().flush().unwrap();
-        io::stdin().read_line(&mut input).unwrap();
-    }
-
-    if args.month {
-        month_gen();
-    }
-
-    if args.next {
-        schedule::utils::write_tomorrow_to_vault();
-    }
-
-    if args.week_no {
-        println!("{}", crate::schedule::utils::current_week_number());
-    }
-
-    if args.lift {
-        println!("{}", lift::get_lifts());
-    }
-
-    if args.violation {
-        if let Err(e) = logging::log_violations() {
-            eprintln!("Error logging violation: {}", e);
+        LogCommand::Spend {
+            category,
+            name,
+            amount,
+        } => {
+            if let Err(e) = logging::log_spend(category, name, amount) {
+                eprintln!("Error logging spend: {}", e);
+                std::process::exit(1);
+            }
         }
-    }
-
-    if args.spend {
-        if let Err(e) = logging::log_spend() {
-            eprintln!("Error logging violatio: {}", e);
+        LogCommand::Row {
+            distance,
+            time,
+            watts,
+            cals,
+        } => {
+            if let Err(e) = logging::log_row(distance, time, watts, cals) {
+                eprintln!("Error logging row: {}", e);
+                std::process::exit(1);
+            }
         }
-    }
-
-    if args.win {
-        if let Err(e) = logging::log_win() {
-            eprintln!("Error logging win: {}", e);
+        LogCommand::Activity {
+            kind,
+            amount,
+            tags,
+            note,
+        } => {
+            if let Err(e) = events::log_activity(kind, amount, tags, note) {
+                eprintln!("Error logging activity: {}", e);
+                std::process::exit(1);
+            }
         }
-    }
-
-    if args.exit {
-        print!("Enter exit amount in mins: ");
-        let time: i32 = read_input();
-        if let Err(e) = logging::log_exit(time) {
-            eprintln!("Error logging exit intake: {}", e);
+        LogCommand::Time { category, minutes } => {
+            if let Err(e) = logging::log_time(category, minutes) {
+                eprintln!("Error logging time: {}", e);
+                std::process::exit(1);
+            }
         }
+        LogCommand::Violation { name } => {
+            if let Err(e) = logging::log_violations(name) {
+                eprintln!("Error logging violation: {}", e);
+                std::process::exit(1);
+            }
+        }
+        LogCommand::Win { name } => {
+            if let Err(e) = logging::log_win(name) {
+                eprintln!("Error logging win: {}", e);
+                std::process::exit(1);
+            }
+        }
+        LogCommand::Debt(debt) => {
+            if let Err(e) = logging::log_debt(debt.balances) {
+                eprintln!("Error logging debt: {:#}", e);
+                std::process::exit(1);
+            }
+        }
+        LogCommand::Income { source, amount } => {
+            if let Err(e) = logging::log_income(source, amount) {
+                eprintln!("Error logging income: {}", e);
+                std::process::exit(1);
+            }
+        }
+        LogCommand::Asset(asset) => {
+            if let Err(e) = logging::log_asset(asset.balances) {
+                eprintln!("Error logging asset: {:#}", e);
+                std::process::exit(1);
+            }
+        }
+        LogCommand::Tasks => logging::log_tasks(),
     }
+}
 
-    if args.log_time {
-        logging::log_time().unwrap();
-    }
-
-    if args.makeup {
-        schedule::utils::makeup();
-    }
-
-    if args.monday {
-        schedule::print_monday_schedule();
-    }
-
-    if args.debt {
-        logging::log_debt().unwrap();
-    }
-    if args.tuesday {
-        schedule::print_tuesday_schedule();
-    }
-
-    if args.wednesday {
-        schedule::print_wednesday_schedule();
-    }
-
-    if args.log {
-        logging::log_tasks();
-    }
-
-    if args.row {
-        logging::log_row().unwrap();
-    }
-
-    if args.work_sum {
-        stats::work_summary().unwrap();
+fn run_schedule(command: ScheduleCommand) {
+    match command {
+        ScheduleCommand::Show { day } => schedule::print_schedule(day.into()),
+        ScheduleCommand::Next => schedule::write_tomorrow_to_vault(),
+        ScheduleCommand::WeekNo => println!("{}", schedule::utils::current_week_number()),
+        ScheduleCommand::Lift => println!("{}", lift::get_lifts()),
+        ScheduleCommand::Month { month } => month_gen(month),
+        ScheduleCommand::Makeup => schedule::utils::makeup(),
     }
+}
 
-    if args.thursday {
-        schedule::print_thursday_schedule();
-    }
+fn main() {
+    let args = Args::parse();
 
-    if args.friday {
-        schedule::print_friday_schedule();
-    }
+    clock::set(clock::from_overrides(args.date, args.at));
 
-    if args.saturday {
-        schedule::print_saturday_schedule();
+    if let Err(e) = config::init(args.config) {
+        eprintln!("Error loading config: {:#}", e);
+        std::process::exit(1);
     }
 
-    if args.sunday {
-        schedule::print_sunday_schedule();
+    match args.command {
+        Command::Log { entry } => run_log(entry),
+        Command::Schedule { command } => run_schedule(command),
+        Command::Model { format, command } => match command {
+            ModelCommand::Run => {
+                if let Err(e) = model1::run(format) {
+                    eprintln!("Weight model failed: {:#}", e);
+                    std::process::exit(1);
+                }
+            }
+            ModelCommand::OneState => {
+                if let Err(e) = model1::run_one_state(format) {
+                    eprintln!("One-state model failed: {:#}", e);
+                    std::process::exit(1);
+                }
+            }
+            ModelCommand::Bodycomp => {
+                if let Err(e) = kalman_bf::run(format) {
+                    eprintln!("Body composition model failed: {:#}", e);
+                    std::process::exit(1);
+                }
+            }
+            ModelCommand::Tune { three_state, save } => {
+                if let Err(e) = tune::run(three_state, save, format) {
+                    eprintln!("Tuning failed: {:#}", e);
+                    std::process::exit(1);
+                }
+            }
+            ModelCommand::Backtest { warmup } => {
+                if let Err(e) = backtest::run(warmup, format) {
+                    eprintln!("Backtest failed: {:#}", e);
+                    std::process::exit(1);
+                }
+            }
+            ModelCommand::Forecast { goal, intake } => {
+                if let Err(e) = forecast::run(goal, intake, format) {
+                    eprintln!("Forecast failed: {:#}", e);
+                    std::process::exit(1);
+                }
+            }
+        },
+        Command::Report { format, command } => {
+            let res = match command {
+                ReportCommand::All => stats::report(format),
+                ReportCommand::Spend => stats::spend_summary(format),
+                ReportCommand::Rowing => stats::fit_rowing(format),
+                ReportCommand::Work => stats::work_summary(format),
+                ReportCommand::Html { out, since } => dashboard::write(&out, since),
+            };
+            if let Err(e) = res {
+                eprintln!("Report failed: {:#}", e);
+                std::process::exit(1);
+            }
+        }
+        Command::Debt { format, command } => {
+            let res = match command {
+                DebtCommand::Plan {
+                    budget,
+                    strategy,
+                    order,
+                } => debt::plan(budget, strategy, &order, format),
+                DebtCommand::Report => debt::report(format),
+            };
+            if let Err(e) = res {
+                eprintln!("Debt command failed: {:#}", e);
+                std::process::exit(1);
+            }
+        }
+        Command::Budget { format, command } => {
+            let res = match command {
+                BudgetCommand::Status => budget::status(format),
+            };
+            if let Err(e) = res {
+                eprintln!("Budget command failed: {:#}", e);
+                std::process::exit(1);
+            }
+        }
+        Command::Finance { format, command } => {
+            let result = match command {
+                FinanceCommand::Statement { months } => finance::statement(format, months),
+            };
+            if let Err(e) = result {
+                eprintln!("Finance command failed: {:#}", e);
+                std::process::exit(1);
+            }
+        }
+        Command::Recurring { format, command } => {
+            let result = match command {
+                RecurringCommand::Add {
+                    name,
+                    amount,
+                    category,
+                    cadence,
+                    every,
+                    next,
+                    until,
+                } => recurring::add(&name, amount, &category, cadence, every, next, until),
+                RecurringCommand::List => recurring::list(format),
+                RecurringCommand::Materialize => recurring::materialize(),
+            };
+            if let Err(e) = result {
+                eprintln!("Recurring command failed: {:#}", e);
+                std::process::exit(1);
+            }
+        }
+        Command::Chart { command } => {
+            let res = match command {
+                ChartCommand::Weight(args) => chart::weight(&args),
+                ChartCommand::Spend(args) => chart::spend(&args),
+                ChartCommand::Rowing(args) => chart::rowing(&args),
+            };
+            if let Err(e) = res {
+                eprintln!("Chart failed: {:#}", e);
+                std::process::exit(1);
+            }
+        }
+        Command::Bored => bored(),
+        Command::Sync => sync(),
+        Command::Publish => publish::publish(),
+        Command::Db { command } => {
+            let res = match command {
+                DbCommand::Migrate => db::run_migrate(),
+                DbCommand::Status => db::status(),
+            };
+            if let Err(e) = res {
+                eprintln!("Database error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Command::Entries { command } => {
+            let res = match command {
+                EntriesCommand::List { kind, since, limit } => entries::list(&kind, since, limit),
+                EntriesCommand::Edit { kind, id, set } => entries::edit(&kind, id, set),
+                EntriesCommand::Delete { kind, id } => entries::delete(&kind, id),
+                EntriesCommand::Audit { limit } => entries::history(limit),
+            };
+            if let Err(e) = res {
+                eprintln!("{:#}", e);
+                std::process::exit(1);
+            }
+        }
+        Command::Import { command } => {
+            let res = match command {
+                ImportCommand::Csv { path, overwrite } => weight::import_csv(
+                    &path.unwrap_or_else(|| util::data_path("weight_energy.csv")),
+                    overwrite,
+                ),
+                ImportCommand::Statement {
+                    path,
+                    dry_run,
+                    no_review,
+                } => statement::import(&path, dry_run, no_review),
+                ImportCommand::Ynab {
+                    path,
+                    mapping,
+                    date_format,
+                    dry_run,
+                } => ynab::import(&path, mapping.as_deref(), &date_format, dry_run),
+            };
+            if let Err(e) = res {
+                eprintln!("Import failed: {:#}", e);
+                std::process::exit(1);
+            }
+        }
+        Command::Export { command } => {
+            let res = match command {
+                ExportCommand::Csv { out } => weight::export_csv(out.as_deref()),
+                ExportCommand::Ynab {
+                    out,
+                    since,
+                    mapping,
+                    date_format,
+                } => ynab::export(out.as_deref(), since, mapping.as_deref(), &date_format),
+            };
+            if let Err(e) = res {
+                eprintln!("Export failed: {:#}", e);
+                std::process::exit(1);
+            }
+        }
+        Command::Undo => {
+            if let Err(e) = entries::undo() {
+                eprintln!("{:#}", e);
+                std::process::exit(1);
+            }
+        }
+        Command::Config { command } => {
+            let res = match command {
+                ConfigCommand::Show => config::show(),
+                ConfigCommand::Init { force } => config::write_default(force),
+            };
+            if let Err(e) = res {
+                eprintln!("{:#}", e);
+                std::process::exit(1);
+            }
+        }
     }
 }
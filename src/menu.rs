@@ -16,7 +16,7 @@ pub fn get_menu() -> String {
     let water = "Water: 12 cups\n";
     let ip = "IP: 60 min\n";
 
-    menu.push_str(&water);
-    menu.push_str(&ip);
-    String::from(menu)
+    menu.push_str(water);
+    menu.push_str(ip);
+    menu
 }
@@ -3,61 +3,220 @@
 //! Enhancements in this version
 //!   • Keeps 7‑day *and* 14‑day Kalman‑smoothed weight deltas
 //!   • Computes 14‑day **percent‑loss‑per‑week**
-//!   • Emits a simple calorie‑tuning recommendation:
-//!        < 0.5 % / wk   ⇒ suggest –100 kcal (or +1 k steps)
-//!        0.5–1.0 % / wk ⇒ keep calories
-//!        > 1.0 % / wk   ⇒ suggest +100 kcal
+//!   • Sets the daily calorie target (see `calorie_target`) from the
+//!     TDEE estimate instead of a fixed ±200 kcal step
 //!
 //! The final stdout block looks like:
 //!     7‑day Kalman change : –1.8 lb (loss)
 //!     14‑day %‑loss / wk : 0.8 %
-//!     Recommendation     : keep calories steady
+//!     Calorie target     : 2450 kcal/day for 0.75%/week
 //! --------------------------------------------------------------
 
+use crate::calorie_target;
+use crate::cli::Format;
+use crate::clock;
+use crate::config::{self, Kalman2DNoise, Kalman3DNoise};
+use crate::output;
+use crate::rts::{self, Step};
+use crate::util;
+use crate::weight;
 use anyhow::Result;
-use chrono::{Datelike, Local, NaiveDate};
-use csv::Reader;
+use chrono::{Datelike, NaiveDate};
 use nalgebra::{Matrix3, RowVector3, Vector3};
 use serde::{Deserialize, Serialize};
 use std::fs::File;
-use std::io::{Read, Write};
+use std::io::Read;
 
 use nalgebra::{Matrix2, RowVector2, Vector2};
 
 // ---------------- constants ----------------------------------
-const KCAL_PER_KG: f64 = 7_700.0; // ≈ 1 kg fat ≈ 7 700 kcal
-const INITIAL_TDEE: f64 = 3_100.0; // baseline guess
+pub const KCAL_PER_KG: f64 = 7_700.0; // ≈ 1 kg fat ≈ 7 700 kcal
 const INITIAL_K_PER_RE: f64 = 70.0; // kcal per RE starting point
 
 // ---------------- input row ----------------------------------
 
-#[allow(dead_code)]
-#[derive(Debug, Deserialize)]
+#[derive(Debug)]
 pub struct Row {
     pub date: String,
-    pub weight: f64,          // kg *or* lb – see flag below
-    pub intake_kcal: f64,     // +ve = intake below 3 100 kcal
-    pub activity: f64,        // kcal of exercise (optional, can be 0)
-    pub protien: Option<f64>, //
+    /// kg *or* lb – see flag below; None = skipped
+    pub weight: Option<f64>,
+    /// None = calories not logged
+    pub intake_kcal: Option<f64>,
+    /// kcal of exercise (optional, can be 0)
+    pub activity: f64,
+    /// Scale BF%, when it was read
+    pub body_fat: Option<f64>,
 }
 
+// ---------------- calendar day -------------------------------
+
+// 1σ of the intake guess used for a day whose calories weren't logged
+const MISSING_INTAKE_SD: f64 = 800.0;
+
+/// Everything the filters see for one calendar day
+#[derive(Debug, Clone)]
+pub struct Day {
+    pub date: NaiveDate,
+    pub intake_kcal: f64, // logged, or the recent average when unknown
+    pub intake_known: bool,
+    pub activity: f64,
+    pub weights_kg: Vec<f64>, // every scale reading that day, possibly none
+    pub body_fat: Vec<f64>,   // BF% readings that day
+}
+
+/// Average of the logged intakes over the last 7 days
+pub fn recent_intake(days: &[Day]) -> Option<f64> {
+    let cals: Vec<f64> = days
+        .iter()
+        .rev()
+        .take(7)
+        .filter(|d| d.intake_known)
+        .map(|d| d.intake_kcal)
+        .collect();
+    if cals.is_empty() {
+        None
+    } else {
+        Some(cals.iter().sum::<f64>() / cals.len() as f64)
+    }
+}
+
+/// Spread the rows over consecutive calendar days from the first to the
+/// last date, so gaps become days with no weigh-in and unknown intake.
+pub fn calendar_days(rows: &[Row]) -> Result<Vec<Day>> {
+    let mut days: Vec<Day> = Vec::new();
+
+    for row in rows {
+        let date = NaiveDate::parse_from_str(&row.date, "%Y-%m-%d")?;
+
+        // open days up to and including this date
+        while days.last().is_none_or(|d| d.date < date) {
+            let next = days.last().map_or(date, |d| d.date + chrono::Days::new(1));
+            let known: Vec<f64> = days
+                .iter()
+                .rev()
+                .take(7)
+                .filter(|d| d.intake_known)
+                .map(|d| d.intake_kcal)
+                .collect();
+            // a long gap keeps carrying the last guess forward
+            let guess = if known.is_empty() {
+                days.last()
+                    .map_or(config::get().model.initial_tdee, |d| d.intake_kcal)
+            } else {
+                known.iter().sum::<f64>() / known.len() as f64
+            };
+            days.push(Day {
+                date: next,
+                intake_kcal: guess,
+                intake_known: false,
+                activity: 0.0,
+                weights_kg: Vec::new(),
+                body_fat: Vec::new(),
+            });
+        }
+
+        let day = days.last_mut().expect("opened above");
+        if let Some(w) = row.weight {
+            day.weights_kg.push(to_kg(w));
+        }
+        if let Some(bf) = row.body_fat {
+            day.body_fat.push(bf);
+        }
+        if let Some(intake) = row.intake_kcal
+            && !day.intake_known
+        {
+            day.intake_kcal = intake;
+            day.intake_known = true;
+        }
+        if day.activity == 0.0 {
+            day.activity = row.activity;
+        }
+    }
+
+    Ok(days)
+}
+
+/// `data/target.json`: daily calorie target and the date it should get us
+/// to the goal weight by
 #[derive(Deserialize, Serialize)]
-struct Target {
-    target: u32,
-    year: i32,
-    month: u32,
-    day: u32,
+pub struct Target {
+    pub target: u32,
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
 }
 
-const PCT_LOSS_LOW: f64 = 0.005; // 0.5 % / wk
-const PCT_LOSS_HIGH: f64 = 0.010; // 1.0 % / wk
-const CAL_ADJUST_STEP: u32 = 200;
-const LB_PER_KG: f64 = 2.205;
+impl Target {
+    pub fn date(&self) -> Option<NaiveDate> {
+        NaiveDate::from_ymd_opt(self.year, self.month, self.day)
+    }
+}
+
+/// The target from `target.json`, or 2700 kcal for today when there is none
+pub fn load_target() -> Result<Target> {
+    let today = clock::today();
+    let target_file = File::open(util::data_path("target.json")).ok();
+    let mut target = Target {
+        target: 2700,
+        year: today.year(),
+        month: today.month(),
+        day: today.day(),
+    };
+    if let Some(mut file) = target_file {
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+        target = serde_json::from_str(&contents)?;
+    }
+    Ok(target)
+}
 
-// convert incoming weight to kg if the CSV is in pounds
-const WEIGHT_IN_POUNDS: bool = true; // flip to false if CSV uses kg
-fn to_kg(w: f64) -> f64 {
-    if WEIGHT_IN_POUNDS { w / 2.205 } else { w }
+pub const LB_PER_KG: f64 = 2.205;
+
+// convert incoming weight to kg if the CSV is in pounds (`model.weight_in_pounds`)
+pub fn to_kg(w: f64) -> f64 {
+    if config::get().model.weight_in_pounds {
+        w / 2.205
+    } else {
+        w
+    }
+}
+
+/// Weight in the unit it is logged in
+pub fn display_weight(kg: f64) -> f64 {
+    if config::get().model.weight_in_pounds {
+        kg * LB_PER_KG
+    } else {
+        kg
+    }
+}
+
+pub fn weight_unit() -> &'static str {
+    if config::get().model.weight_in_pounds {
+        "lb"
+    } else {
+        "kg"
+    }
+}
+
+// ---------------- innovation likelihood ----------------------
+
+/// Gaussian log-likelihood of the weigh-in residuals. The first one is
+/// left out: it mostly measures how far `initial_weight_kg` was from the
+/// scale, which has nothing to do with the noise being fitted.
+#[derive(Clone, Debug, Default)]
+struct Innovations {
+    count: usize,
+    log_likelihood: f64,
+}
+
+impl Innovations {
+    fn add(&mut self, residual: f64, variance: f64) {
+        if self.count > 0 {
+            self.log_likelihood -= 0.5
+                * ((2.0 * std::f64::consts::PI * variance).ln() + residual * residual / variance);
+        }
+        self.count += 1;
+    }
 }
 
 // ---------------- Kalman 2‑D filter --------------------------
@@ -67,66 +226,112 @@ pub struct Kalman3D {
     x: Vector3<f64>, // [ w_kg , tdee , k_per_re ]
     p: Matrix3<f64>, // covariance
     q: Matrix3<f64>, // process noise matrix
+    r: f64,          // scale variance (kg^2)
+    /// per-step record for the RTS pass
+    history: Vec<Step<3>>,
+    /// innovation log-likelihood, see `Innovations`
+    innovations: Innovations,
 }
 
 impl Kalman3D {
     pub fn new(initial_w_kg: f64) -> Self {
-        let q_weight_var: f64 = 0.0_f64.powi(2); // process noise on weight
-        let q_tdee_var: f64 = 12.0_f64.powi(2); // TDEE drift ±40 kcal
-        let q_re_var: f64 = 2.0_f64.powi(2); // k/RE drift ±5 kcal
+        let model = &config::get().model;
+        Self::with_noise(initial_w_kg, model.initial_tdee, &model.kalman3d)
+    }
+
+    /// Filter with explicit noise rather than the configured one (`model tune`)
+    pub fn with_noise(initial_w_kg: f64, initial_tdee: f64, noise: &Kalman3DNoise) -> Self {
+        let q_weight_var: f64 = noise.weight_drift_sd.powi(2); // process noise on weight
+        let q_tdee_var: f64 = noise.tdee_drift_sd.powi(2); // TDEE drift
+        let q_re_var: f64 = noise.re_drift_sd.powi(2); // k/RE drift
 
         let p0 = Matrix3::from_diagonal(&Vector3::new(
             2.0_f64.powi(2),
-            200.0_f64.powi(2),
+            noise.tdee_prior_sd.powi(2),
             30.0_f64.powi(2),
         ));
         let q = Matrix3::from_diagonal(&Vector3::new(q_weight_var, q_tdee_var, q_re_var));
         Kalman3D {
-            x: Vector3::new(initial_w_kg, INITIAL_TDEE, INITIAL_K_PER_RE),
+            x: Vector3::new(initial_w_kg, initial_tdee, INITIAL_K_PER_RE),
             p: p0,
             q,
+            r: noise.scale_sd.powi(2),
+            history: Vec::new(),
+            innovations: Innovations::default(),
         }
     }
 
-    /// One‑day predict / update
-    pub fn step(&mut self, row: &Row) {
-        let r_scale_var: f64 = 0.6_f64.powi(2); // measurement variance on scale (kg^2)
+    /// One‑day predict, then an update per weigh-in (none on skipped days)
+    pub fn step(&mut self, day: &Day) {
+        let r_scale_var = self.r; // measurement variance on scale (kg^2)
         let c = KCAL_PER_KG;
-        let a = row.activity;
+        let a = day.activity;
 
         // ---------- PREDICT ----------
         // F_t and control b_t
         let f = Matrix3::new(1.0, -1.0 / c, a / c, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0);
-        let b = Vector3::new(row.intake_kcal / c, 0.0, 0.0);
+        let b = Vector3::new(day.intake_kcal / c, 0.0, 0.0);
 
         // state & covariance prediction
         self.x = f * self.x + b;
         self.p = f * self.p * f.transpose() + self.q;
+        if !day.intake_known {
+            self.p[(0, 0)] += (MISSING_INTAKE_SD / c).powi(2);
+        }
+        let (x_pred, p_pred) = (self.x, self.p);
 
         // ---------- UPDATE ----------
-        let z = to_kg(row.weight); // measurement
-        let h = RowVector3::new(1.0, 0.0, 0.0); // H
-        let y = z - (h * self.x)[0]; // residual
-        let s = (h * self.p * h.transpose())[0] + r_scale_var; // scalar
-        let k = (self.p * h.transpose()) / s; // 3x1 Kalman gain
-
-        self.x = self.x + k * y;
+        for &z in &day.weights_kg {
+            let h = RowVector3::new(1.0, 0.0, 0.0); // H
+            let y = z - (h * self.x)[0]; // residual
+            let s = (h * self.p * h.transpose())[0] + r_scale_var; // scalar
+            let k = (self.p * h.transpose()) / s; // 3x1 Kalman gain
+            self.innovations.add(y, s);
+
+            self.x += k * y;
+
+            // Joseph form keeps P symmetric/PSD
+            let i = Matrix3::identity();
+            self.p =
+                (i - k * h) * self.p * (i - k * h).transpose() + k * r_scale_var * k.transpose();
+        }
 
-        // Joseph form keeps P symmetric/PSD
-        let i = Matrix3::identity();
-        self.p = (i.clone() - &k * &h) * self.p * (i - &k * &h).transpose()
-            + k * r_scale_var * k.transpose();
+        self.history.push(Step {
+            f,
+            x_pred,
+            p_pred,
+            x_post: self.x,
+            p_post: self.p,
+        });
     }
 
-    // Accessors
-    pub fn weight_kg(&self) -> f64 {
-        self.x[0]
+    /// Predicted scale weight (mean, variance) after the `ahead` days, using
+    /// their intake and activity but none of their weigh-ins
+    pub fn predict_weight(&self, ahead: &[Day]) -> (f64, f64) {
+        let c = KCAL_PER_KG;
+        let (mut x, mut p) = (self.x, self.p);
+        for day in ahead {
+            let a = day.activity;
+            let f = Matrix3::new(1.0, -1.0 / c, a / c, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0);
+            x = f * x + Vector3::new(day.intake_kcal / c, 0.0, 0.0);
+            p = f * p * f.transpose() + self.q;
+            if !day.intake_known {
+                p[(0, 0)] += (MISSING_INTAKE_SD / c).powi(2);
+            }
+        }
+        (x[0], p[(0, 0)] + self.r)
     }
-    pub fn tdee(&self) -> f64 {
-        self.x[1]
+
+    pub fn log_likelihood(&self) -> f64 {
+        self.innovations.log_likelihood
     }
-    pub fn kcal_per_re(&self) -> f64 {
-        self.x[2]
+
+    /// RTS-smoothed states `[w_kg, tdee, k_per_re]`, one per step
+    pub fn smoothed(&self) -> Vec<Vector3<f64>> {
+        rts::smooth(&self.history)
+            .into_iter()
+            .map(|(x, _)| x)
+            .collect()
     }
 }
 
@@ -135,55 +340,136 @@ pub struct Kalman2D {
     x: Vector2<f64>, // [ w_kg , tdee ]
     p: Matrix2<f64>, // covariance
     q: Matrix2<f64>, // process noise
+    r: f64,          // scale variance (kg^2)
+    /// per-step record for the RTS pass
+    history: Vec<Step<2>>,
+    /// innovation log-likelihood, see `Innovations`
+    innovations: Innovations,
 }
 
 impl Kalman2D {
     pub fn new(initial_w_kg: f64) -> Self {
-        // Tune these:
-        let q_weight_var: f64 = 0.0_f64.powi(2); // weight has no intrinsic drift in the process model
-        let q_tdee_var: f64 = 2.0_f64.powi(2); // allow TDEE to drift day-to-day (e.g., ~±12 kcal)
+        let model = &config::get().model;
+        Self::with_noise(initial_w_kg, model.initial_tdee, &model.kalman2d)
+    }
+
+    /// Filter with explicit noise rather than the configured one (`model tune`)
+    pub fn with_noise(initial_w_kg: f64, initial_tdee: f64, noise: &Kalman2DNoise) -> Self {
+        // `model tune` fits these:
+        let q_weight_var: f64 = noise.weight_drift_sd.powi(2); // weight drift outside the energy balance
+        let q_tdee_var: f64 = noise.tdee_drift_sd.powi(2); // allow TDEE to drift day-to-day
 
-        // Initial uncertainty (also tune):
-        let p0 = Matrix2::from_diagonal(&Vector2::new(0.5_f64.powi(2), 600.0_f64.powi(2)));
+        // Initial uncertainty; the weight one is not tuned
+        let p0 =
+            Matrix2::from_diagonal(&Vector2::new(0.5_f64.powi(2), noise.tdee_prior_sd.powi(2)));
         let q = Matrix2::from_diagonal(&Vector2::new(q_weight_var, q_tdee_var));
 
         Kalman2D {
-            x: Vector2::new(initial_w_kg, INITIAL_TDEE),
+            x: Vector2::new(initial_w_kg, initial_tdee),
             p: p0,
             q,
+            r: noise.scale_sd.powi(2),
+            history: Vec::new(),
+            innovations: Innovations::default(),
         }
     }
 
-    /// One-day predict / update
-    pub fn step(&mut self, row: &Row) {
-        let r_scale_var: f64 = 0.3_f64.powi(2); // variance of scale noise (kg^2) — tune to your scale/variance
+    /// One-day predict, then an update per weigh-in (none on skipped days)
+    pub fn step(&mut self, day: &Day) {
+        let r_scale_var = self.r; // variance of scale noise (kg^2), see `model tune`
         let c = KCAL_PER_KG; // e.g., 7700.0
 
         // ---------- PREDICT ----------
         // State transition F and control B (uses only total intake_kcal)
         let f = Matrix2::new(1.0, -1.0 / c, 0.0, 1.0);
-        let b = Vector2::new(row.intake_kcal / c, 0.0);
+        let b = Vector2::new(day.intake_kcal / c, 0.0);
 
-        // Predict state and covariance
+        // Predict state and covariance; an unknown intake is a guess, so
+        // the weight it implies is much less certain
         self.x = f * self.x + b;
         self.p = f * self.p * f.transpose() + self.q;
+        if !day.intake_known {
+            self.p[(0, 0)] += (MISSING_INTAKE_SD / c).powi(2);
+        }
+        let (x_pred, p_pred) = (self.x, self.p);
 
         // ---------- UPDATE ----------
-        let z = to_kg(row.weight); // measured morning weight in kg
-        let h = RowVector2::new(1.0, 0.0); // we observe only weight
-        let y = z - (h * self.x)[0]; // residual
-        let s = (h * self.p * h.transpose())[0] + r_scale_var;
-        let k = (self.p * h.transpose()) / s; // 2x1 Kalman gain
+        // each morning weight in kg; several readings simply update in turn
+        for &z in &day.weights_kg {
+            let h = RowVector2::new(1.0, 0.0); // we observe only weight
+            let y = z - (h * self.x)[0]; // residual
+            let s = (h * self.p * h.transpose())[0] + r_scale_var;
+            let k = (self.p * h.transpose()) / s; // 2x1 Kalman gain
+            self.innovations.add(y, s);
+
+            self.x += k * y;
+
+            // Joseph form for numerical stability (keeps P symmetric/PSD)
+            let i = Matrix2::identity();
+            self.p =
+                (i - k * h) * self.p * (i - k * h).transpose() + k * r_scale_var * k.transpose();
+        }
+
+        self.history.push(Step {
+            f,
+            x_pred,
+            p_pred,
+            x_post: self.x,
+            p_post: self.p,
+        });
+    }
+
+    /// Mean and variance of the weight (kg) on each of the next `days`
+    /// days, eating `intake_kcal` a day and never stepping on the scale
+    pub fn project(&self, intake_kcal: f64, days: usize) -> Vec<(f64, f64)> {
+        let c = KCAL_PER_KG;
+        let f = Matrix2::new(1.0, -1.0 / c, 0.0, 1.0);
+        let b = Vector2::new(intake_kcal / c, 0.0);
+
+        let (mut x, mut p) = (self.x, self.p);
+        (0..days)
+            .map(|_| {
+                x = f * x + b;
+                p = f * p * f.transpose() + self.q;
+                (x[0], p[(0, 0)])
+            })
+            .collect()
+    }
+
+    /// Predicted scale weight (mean, variance) after the `ahead` days, using
+    /// their intake but none of their weigh-ins
+    pub fn predict_weight(&self, ahead: &[Day]) -> (f64, f64) {
+        let c = KCAL_PER_KG;
+        let f = Matrix2::new(1.0, -1.0 / c, 0.0, 1.0);
+        let (mut x, mut p) = (self.x, self.p);
+        for day in ahead {
+            x = f * x + Vector2::new(day.intake_kcal / c, 0.0);
+            p = f * p * f.transpose() + self.q;
+            if !day.intake_known {
+                p[(0, 0)] += (MISSING_INTAKE_SD / c).powi(2);
+            }
+        }
+        (x[0], p[(0, 0)] + self.r)
+    }
 
-        self.x = self.x + k * y;
+    /// Variance of the TDEE estimate
+    pub fn tdee_var(&self) -> f64 {
+        self.p[(1, 1)]
+    }
+
+    pub fn log_likelihood(&self) -> f64 {
+        self.innovations.log_likelihood
+    }
 
-        // Joseph form for numerical stability (keeps P symmetric/PSD)
-        let i = Matrix2::identity();
-        self.p = (i.clone() - &k * &h) * self.p * (i - &k * &h).transpose()
-            + k * r_scale_var * k.transpose();
+    /// RTS-smoothed states `[w_kg, tdee]`, one per step
+    pub fn smoothed(&self) -> Vec<Vector2<f64>> {
+        rts::smooth(&self.history)
+            .into_iter()
+            .map(|(x, _)| x)
+            .collect()
     }
 
-        // Accessors
+    // Accessors
     pub fn weight_kg(&self) -> f64 {
         self.x[0]
     }
@@ -194,18 +480,27 @@ impl Kalman2D {
     pub fn tdee(&self) -> f64 {
         self.x[1]
     }
+}
 
+/// 7- and 14-day change of a weight series
+#[derive(Serialize)]
+pub struct Deltas {
+    pub delta_7d: f64,
+    pub delta_14d: f64,
+    /// Share of body weight lost per week over the 14 days (0.01 = 1%)
+    pub loss_per_week: f64,
 }
 
-fn compute_kalman_deltas(weights: &Vec<f64>) -> (f64, f64, f64) {
+fn compute_kalman_deltas(weights: &[f64]) -> Deltas {
     let len = weights.len();
-    let delta7 = if len >= 7 {
-        weights[len - 1] - weights[len - 7]
+    // One weight per day, so a 7-day change spans 8 entries
+    let delta7 = if len >= 8 {
+        weights[len - 1] - weights[len - 8]
     } else {
         0.0
     };
     let delta14 = if len >= 15 {
-        weights[len - 1] - weights[len - 14]
+        weights[len - 1] - weights[len - 15]
     } else {
         0.0
     };
@@ -214,121 +509,226 @@ fn compute_kalman_deltas(weights: &Vec<f64>) -> (f64, f64, f64) {
     } else {
         0.0
     };
-    (delta7, delta14, pct_loss_per_week)
+    Deltas {
+        delta_7d: delta7,
+        delta_14d: delta14,
+        loss_per_week: pct_loss_per_week,
+    }
 }
 
-// ---------------- main runner --------------------------------
-pub fn run<P: AsRef<std::path::Path>>(csv_path: P) -> Result<()> {
-    // ---------- ingest CSV ----------
+// Print the 7-/14-day trend of a weight series
+fn print_deltas(label: &str, deltas: &Deltas) {
+    let unit = weight_unit();
+    let (delta7, delta14) = (deltas.delta_7d, deltas.delta_14d);
+    let dir = if delta7 < 0.0 { "loss" } else { "gain" };
 
-    let today = Local::now();
+    let dir14 = if delta14 < 0.0 { "loss" } else { "gain" };
 
-    let target_file = File::open("data/target.json").ok();
-    let mut target = Target {
-        target: 2700,
-        year: today.year(),
-        month: today.month(),
-        day: today.day(),
-    };
-    if let Some(mut file) = target_file {
-        let mut contents = String::new();
-        file.read_to_string(&mut contents)?;
-        target = serde_json::from_str(&contents)?;
-        println!(
-            "Loaded Target date: {:04}-{:02}-{:02}, target: {}",
-            target.year, target.month, target.day, target.target
-        );
-    }
-    // ---------- estimate R (scale variance) ----------
+    println!(
+        "{label} 7-day Kalman {} {:.2}{unit}, 14-day {dir14} {:.2}{unit}, %{dir14}/week: {:.3}%",
+        dir,
+        delta7.abs(),
+        delta14.abs(),
+        deltas.loss_per_week.abs() * 100.0
+    );
+}
 
-    let file = File::open(csv_path)?;
-    let mut rdr = Reader::from_reader(file);
-    let mut kf = Kalman2D::new(127.0);
+/// One calendar day of `model run`, weights in the logged unit
+#[derive(Serialize)]
+pub struct DayEstimate {
+    pub date: NaiveDate,
+    pub filtered_weight: f64,
+    pub filtered_tdee: f64,
+    pub smoothed_weight: f64,
+    pub smoothed_tdee: f64,
+    /// RTS-smoothed 3-state filter, which also counts activity
+    pub three_state_weight: f64,
+    pub three_state_tdee: f64,
+    pub weighed_in: bool,
+    pub intake_known: bool,
+}
 
-    let mut kalman_weights: Vec<f64> = Vec::new();
-    let mut cals: Vec<f64> = Vec::new();
+/// Everything `model run` reports
+#[derive(Serialize)]
+pub struct RunReport {
+    pub unit: &'static str,
+    pub days: Vec<DayEstimate>,
+    pub filtered: Deltas,
+    pub smoothed: Deltas,
+    /// Deltas of the smoothed 3-state weights
+    pub three_state: Deltas,
+    /// Today's calorie target; absent with no weigh-ins
+    pub recommendation: Option<calorie_target::Plan>,
+}
 
-    for result in rdr.deserialize() {
-        let row: Row = result?;
-        kf.step(&row);
+fn print_run(report: &RunReport) -> Result<()> {
+    let unit = report.unit;
+    for day in &report.days {
+        let note = match (day.weighed_in, day.intake_known) {
+            (false, false) => "  (no weigh-in, intake unknown)",
+            (false, true) => "  (no weigh-in)",
+            (true, false) => "  (intake unknown)",
+            (true, true) => "",
+        };
         println!(
-            "final est: {:.2} lb  TDEE {:.0} kcal",
-            kf.weight_kg() * LB_PER_KG,
-            kf.tdee(),        );
-        cals.push(row.intake_kcal);
-        kalman_weights.push(kf.weight_kg() * LB_PER_KG);
+            "{}  filtered {:.2} {unit} TDEE {:.0}  smoothed {:.2} {unit} TDEE {:.0}  3-state {:.2} {unit} TDEE {:.0}{}",
+            day.date,
+            day.filtered_weight,
+            day.filtered_tdee,
+            day.smoothed_weight,
+            day.smoothed_tdee,
+            day.three_state_weight,
+            day.three_state_tdee,
+            note
+        );
+    }
+    if let Some(last) = report.days.last() {
+        println!(
+            "final est: {:.2} {unit}  TDEE {:.0} kcal",
+            last.filtered_weight, last.filtered_tdee
+        );
     }
 
-    let (delta7, delta14, pct_loss_per_week) = compute_kalman_deltas(&kalman_weights);
+    print_deltas("Filtered", &report.filtered);
+    print_deltas("Smoothed", &report.smoothed);
+    print_deltas("3-state smoothed", &report.three_state);
 
-    let dir = if delta7 < 0.0 { "loss" } else { "gain" };
+    if let Some(plan) = &report.recommendation {
+        calorie_target::print(plan)?;
+    }
+    Ok(())
+}
 
-    let dir14 = if delta14 < 0.0 { "loss" } else { "gain" };
+// ---------------- main runner --------------------------------
+pub fn run(format: Format) -> Result<()> {
+    // ---------- ingest weigh-ins ----------
 
-    println!(
-        "7-day Kalman {} {:.2}lb, 14-day {dir14} {:.2}lb, %{dir14}/week: {:.3}%",
-        dir,
-        delta7.abs(),
-        delta14.abs(),
-        pct_loss_per_week.abs()
-    );
+    let target = load_target()?;
+    if format == Format::Table && util::data_path("target.json").exists() {
+        println!(
+            "Loaded Target date: {:04}-{:02}-{:02}, target: {}",
+            target.year, target.month, target.day, target.target
+        );
+    }
+    // R (scale variance) and Q come from config; `model tune` fits them
 
-    // 14‑day %‑loss per week & guidance
-    // guidance
+    let days = calendar_days(&weight::model_rows()?)?;
+    let mut kf = Kalman2D::new(config::get().model.initial_weight_kg);
+    let mut kf3 = Kalman3D::new(config::get().model.initial_weight_kg);
 
-    let sum: f64 = cals.into_iter().rev().take(7).sum();
-    let avg = sum / 7.0;
+    let mut filtered: Vec<(f64, f64)> = Vec::new(); // (weight, tdee)
 
-    println!(
-        "Guidelines: -{} < {:.3 } < -{} ",
-        PCT_LOSS_LOW, -pct_loss_per_week, PCT_LOSS_HIGH
-    );
+    for day in &days {
+        kf.step(day);
+        kf3.step(day);
+        filtered.push((display_weight(kf.weight_kg()), kf.tdee()));
+    }
 
-    let suggestion = if pct_loss_per_week < PCT_LOSS_LOW {
-        let target = avg - CAL_ADJUST_STEP as f64;
-        format!(
-            "Suggest −{} kcal/day T: {:.0}",
-            CAL_ADJUST_STEP as i32, target
-        )
-    } else if pct_loss_per_week > PCT_LOSS_HIGH {
-        let target = avg + CAL_ADJUST_STEP as f64;
-        format!(
-            "Suggest +{} kcal/day T: {:.0}",
-            CAL_ADJUST_STEP as i32, target
-        )
+    // backward pass: past days also see the weigh-ins that came after them
+    let smoothed: Vec<(f64, f64)> = kf
+        .smoothed()
+        .iter()
+        .map(|x| (display_weight(x[0]), x[1]))
+        .collect();
+    let three_state: Vec<(f64, f64)> = kf3
+        .smoothed()
+        .iter()
+        .map(|x| (display_weight(x[0]), x[1]))
+        .collect();
+
+    let filtered_weights: Vec<f64> = filtered.iter().map(|w| w.0).collect();
+    let smoothed_weights: Vec<f64> = smoothed.iter().map(|w| w.0).collect();
+    let three_state_weights: Vec<f64> = three_state.iter().map(|w| w.0).collect();
+
+    // guidance: the calorie target from the TDEE estimate
+    let recommendation = if days.is_empty() {
+        None
     } else {
-        "Keep calories steady".to_string()
+        Some(calorie_target::update(clock::today(), &kf)?)
     };
 
-    println!("{}", suggestion);
+    let report = RunReport {
+        unit: weight_unit(),
+        days: days
+            .iter()
+            .zip(&filtered)
+            .zip(&smoothed)
+            .zip(&three_state)
+            .map(|(((day, f), s), t)| DayEstimate {
+                date: day.date,
+                filtered_weight: f.0,
+                filtered_tdee: f.1,
+                smoothed_weight: s.0,
+                smoothed_tdee: s.1,
+                three_state_weight: t.0,
+                three_state_tdee: t.1,
+                weighed_in: !day.weights_kg.is_empty(),
+                intake_known: day.intake_known,
+            })
+            .collect(),
+        filtered: compute_kalman_deltas(&filtered_weights),
+        smoothed: compute_kalman_deltas(&smoothed_weights),
+        three_state: compute_kalman_deltas(&three_state_weights),
+        recommendation,
+    };
 
-    let today = NaiveDate::from_ymd_opt(today.year(), today.month(), today.day()).unwrap();
+    match format {
+        Format::Table => print_run(&report)?,
+        Format::Json => output::json(&report)?,
+        Format::Csv => output::csv(&report.days)?,
+    }
+    Ok(())
+}
 
-    let update = NaiveDate::from_ymd_opt(target.year, target.month, target.day).unwrap();
+/// One row of `model one-state`
+#[derive(Serialize)]
+pub struct OneStateEstimate {
+    pub date: NaiveDate,
+    pub weight: f64,
+    pub tdee: f64,
+}
 
-    Ok(())
+#[derive(Serialize)]
+pub struct OneStateReport {
+    pub unit: &'static str,
+    pub days: Vec<OneStateEstimate>,
 }
 
 // ---------------- main runner --------------------------------
-pub fn run_one_state<P: AsRef<std::path::Path>>(csv_path: P) -> Result<()> {
-    // ---------- ingest CSV ----------
+pub fn run_one_state(format: Format) -> Result<()> {
+    // ---------- ingest weigh-ins ----------
 
     // ---------- estimate R (scale variance) ----------
 
-    let file = File::open(csv_path)?;
-    let mut rdr = Reader::from_reader(file);
-    let mut kf = Kalman2D::new(127.0);
+    let days = calendar_days(&weight::model_rows()?)?;
+    let mut kf = Kalman2D::new(config::get().model.initial_weight_kg);
+
+    let mut estimates: Vec<OneStateEstimate> = Vec::new();
+    for day in &days {
+        kf.step(day);
+        estimates.push(OneStateEstimate {
+            date: day.date,
+            weight: display_weight(kf.w_kg()),
+            tdee: kf.tdee(),
+        });
+    }
 
-    let mut kalman_weights: Vec<f64> = Vec::new();
-    for result in rdr.deserialize() {
-        let row: Row = result?;
-        kf.step(&row);
-        println!(
-            "final est: {:.2} lb  TDEE {:.0} kcal",
-            kf.w_kg() * LB_PER_KG,
-            kf.tdee(),
-        );
-        kalman_weights.push(kf.w_kg() * LB_PER_KG);
+    match format {
+        Format::Table => {
+            for e in &estimates {
+                println!(
+                    "final est: {:.2} {}  TDEE {:.0} kcal",
+                    e.weight,
+                    weight_unit(),
+                    e.tdee,
+                );
+            }
+        }
+        Format::Json => output::json(&OneStateReport {
+            unit: weight_unit(),
+            days: estimates,
+        })?,
+        Format::Csv => output::csv(&estimates)?,
     }
 
     Ok(())
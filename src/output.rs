@@ -0,0 +1,25 @@
+//! Machine-readable output for the model and report commands
+//!
+//! Each command builds one serializable report and prints it as text,
+//! as JSON (the whole report) or as CSV (its main table, one row per
+//! record). Field names are part of the interface; the README lists them.
+
+use anyhow::Result;
+use serde::Serialize;
+use std::io;
+
+/// Print `report` as pretty JSON
+pub fn json<T: Serialize>(report: &T) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(report)?);
+    Ok(())
+}
+
+/// Print `rows` as CSV with a header taken from the field names
+pub fn csv<R: Serialize>(rows: &[R]) -> Result<()> {
+    let mut wtr = csv::Writer::from_writer(io::stdout());
+    for row in rows {
+        wtr.serialize(row)?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
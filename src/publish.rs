@@ -1,5 +1,6 @@
-use crate::VAULT_DIR;
-use chrono::{DateTime, Datelike, Days, Local, NaiveDate};
+use crate::config;
+use crate::util;
+use chrono::NaiveDate;
 use std::fs;
 use std::process::Command;
 use std::process::Output;
@@ -7,7 +8,7 @@ use std::time::{SystemTime, UNIX_EPOCH};
 
 fn handle_out(out: Output) {
     println!("{}", str::from_utf8(&out.stdout).unwrap());
-    if out.stderr.len() != 0 {
+    if !out.stderr.is_empty() {
         println!("len {}", out.stderr.len());
         println!("stderr: {}", str::from_utf8(&out.stderr).unwrap());
         // exit(69);
@@ -15,10 +16,11 @@ fn handle_out(out: Output) {
 }
 
 pub fn publish() {
-    let paths = fs::read_dir("data/vault/Weekly Reflection").unwrap();
+    let paths = fs::read_dir(util::data_path("vault/Weekly Reflection")).unwrap();
 
-    let bucket_name = "veryonlineguy.com";
-    let distribution_id = "E1FCC9GGT96QM6";
+    let site_dir = config::get().publish.site_dir.as_str();
+    let bucket_name = config::get().publish.bucket.as_str();
+    let distribution_id = config::get().publish.distribution_id.as_str();
 
     for path in paths {
         let path = path.unwrap().path().display().to_string();
@@ -36,10 +38,10 @@ pub fn publish() {
         let header = "+++\ntitle = \"".to_owned()
             + name
             + "\"\ndate = \""
-            + &&date
+            + &date
             + "\"\ndescription = \"\"\ntags = []\n+++\n";
 
-        let write_path = "veryonlineguy.com/content/".to_string() + &file_name;
+        let write_path = format!("{site_dir}/content/{file_name}");
 
         let today_str = header + &today_str;
         fs::write(write_path, &today_str).unwrap();
@@ -57,7 +59,7 @@ pub fn publish() {
         since_the_epoch
     );
     let out = Command::new("hugo")
-        .current_dir("veryonlineguy.com")
+        .current_dir(site_dir)
         .arg("build")
         .output()
         .expect("npm build failed");
@@ -67,7 +69,7 @@ pub fn publish() {
     let out = Command::new("aws")
         .arg("s3")
         .arg("rm")
-        .arg("s3://".to_owned() + &bucket_name)
+        .arg("s3://".to_owned() + bucket_name)
         .arg("--recursive")
         .output()
         .expect("Couldn't clean bucket");
@@ -76,8 +78,8 @@ pub fn publish() {
     let out = Command::new("aws")
         .arg("s3")
         .arg("sync")
-        .arg("veryonlineguy.com/public/")
-        .arg("s3://".to_owned() + &bucket_name)
+        .arg(format!("{site_dir}/public/"))
+        .arg("s3://".to_owned() + bucket_name)
         .output()
         .expect("Couldn't upload to bucket");
 
@@ -87,7 +89,7 @@ pub fn publish() {
         .arg("cloudfront")
         .arg("create-invalidation")
         .arg("--distribution-id")
-        .arg(&distribution_id)
+        .arg(distribution_id)
         .arg("--invalidation-batch")
         .arg(invalidation_cmd)
         .arg("--query")
@@ -105,7 +107,7 @@ pub fn publish() {
         .arg("wait")
         .arg("invalidation-completed")
         .arg("--distribution-id")
-        .arg(&distribution_id)
+        .arg(distribution_id)
         .arg("--id")
         .arg(invalidation_id)
         .output()
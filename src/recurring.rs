@@ -0,0 +1,362 @@
+//! Recurring expenses
+//!
+//! Rent, subscriptions and installments are stored once in `recurring` with
+//! their amount, cadence and first due date. Occurrence `n` falls `n * every`
+//! cadence units after `start`, and `paid` counts the ones already added to
+//! `spend`, so monthly dates stay on the start day instead of drifting.
+//! Every payment that has come due is added to spend before the budget,
+//! the finance statement, the dashboard and the vault note read it, and by
+//! `recurring materialize`; one already logged some other way on its due
+//! date for the same amount is counted without adding it again.
+
+use crate::audit::{self, Action};
+use crate::cli::{Cadence, Format};
+use crate::clock;
+use crate::db;
+use crate::output;
+use crate::statement;
+use anyhow::{Result, bail};
+use chrono::{Days, Months, NaiveDate};
+use clap::ValueEnum;
+use rusqlite::Connection;
+use serde::Serialize;
+use std::collections::HashSet;
+
+/// How far ahead the vault note lists payments
+const DUE_SOON_DAYS: u64 = 7;
+
+impl Cadence {
+    fn name(self) -> &'static str {
+        match self {
+            Cadence::Weekly => "weekly",
+            Cadence::Monthly => "monthly",
+            Cadence::Quarterly => "quarterly",
+            Cadence::Yearly => "yearly",
+        }
+    }
+
+    fn per_year(self) -> f64 {
+        match self {
+            Cadence::Weekly => 365.25 / 7.0,
+            Cadence::Monthly => 12.0,
+            Cadence::Quarterly => 4.0,
+            Cadence::Yearly => 1.0,
+        }
+    }
+
+    /// `units` cadence units after `start`
+    fn after(self, start: NaiveDate, units: u32) -> Option<NaiveDate> {
+        match self {
+            Cadence::Weekly => start.checked_add_days(Days::new(7 * units as u64)),
+            Cadence::Monthly => start.checked_add_months(Months::new(units)),
+            Cadence::Quarterly => start.checked_add_months(Months::new(3 * units)),
+            Cadence::Yearly => start.checked_add_months(Months::new(12 * units)),
+        }
+    }
+}
+
+struct Expense {
+    id: i64,
+    name: String,
+    amount: f64,
+    category: String,
+    cadence: Cadence,
+    every: u32,
+    start: NaiveDate,
+    until: Option<NaiveDate>,
+    paid: u32,
+}
+
+impl Expense {
+    /// Date of the next unpaid occurrence; `None` once past `until`
+    fn next_due(&self) -> Option<NaiveDate> {
+        let due = self.cadence.after(self.start, self.paid * self.every)?;
+        match self.until {
+            Some(until) if due > until => None,
+            _ => Some(due),
+        }
+    }
+
+    fn annual(&self) -> f64 {
+        self.amount * self.cadence.per_year() / self.every as f64
+    }
+
+    fn schedule(&self) -> String {
+        match self.every {
+            1 => self.cadence.name().to_string(),
+            n => format!("{} x{n}", self.cadence.name()),
+        }
+    }
+}
+
+fn load(conn: &Connection) -> Result<Vec<Expense>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, name, amount, category, cadence, every, start, until, paid
+            FROM recurring ORDER BY id",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, f64>(2)?,
+            row.get::<_, String>(3)?,
+            row.get::<_, String>(4)?,
+            row.get::<_, u32>(5)?,
+            row.get::<_, String>(6)?,
+            row.get::<_, Option<String>>(7)?,
+            row.get::<_, u32>(8)?,
+        ))
+    })?;
+
+    let mut expenses = Vec::new();
+    for row in rows {
+        let (id, name, amount, category, cadence, every, start, until, paid) = row?;
+        let Ok(cadence) = Cadence::from_str(&cadence, true) else {
+            bail!("recurring {id} ({name}): unknown cadence '{cadence}'");
+        };
+        let parse = |s: &str| NaiveDate::parse_from_str(s, "%Y-%m-%d");
+        expenses.push(Expense {
+            id,
+            name,
+            amount,
+            category,
+            cadence,
+            every: every.max(1),
+            start: parse(&start)?,
+            until: until.as_deref().map(parse).transpose()?,
+            paid,
+        });
+    }
+    Ok(expenses)
+}
+
+/// `recurring add`
+pub fn add(
+    name: &str,
+    amount: f64,
+    category: &str,
+    cadence: Cadence,
+    every: u32,
+    next: NaiveDate,
+    until: Option<NaiveDate>,
+) -> Result<()> {
+    if let Some(until) = until
+        && until < next
+    {
+        bail!("--until {until} is before the first due date {next}");
+    }
+    let conn = db::open()?;
+    conn.execute(
+        "INSERT INTO recurring (date_time, name, amount, category, cadence, every, start, until)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        (
+            clock::timestamp(),
+            name,
+            amount,
+            category,
+            cadence.name(),
+            every,
+            next.to_string(),
+            until.map(|d| d.to_string()),
+        ),
+    )?;
+    let id = conn.last_insert_rowid();
+    audit::record_insert(&conn, "recurring", "recurring", id)?;
+    println!("Added recurring {id}: {name} ${amount:.2}, next due {next}");
+    Ok(())
+}
+
+/// Add every occurrence due on or before `today` to spend. Returns the
+/// number of spend rows added.
+fn materialize_through(conn: &mut Connection, today: NaiveDate) -> Result<usize> {
+    let expenses = load(conn)?;
+    let tx = conn.transaction()?;
+    let mut batch = audit::Batch::new();
+    let mut used = HashSet::new();
+    let mut added = 0;
+    for mut expense in expenses {
+        let paid_before = expense.paid;
+        while let Some(due) = expense.next_due().filter(|due| *due <= today) {
+            let import_id = format!("recurring:{}:{due}", expense.id);
+            if !statement::already_logged(&tx, due, expense.amount, &import_id, &mut used)? {
+                tx.execute(
+                    "INSERT INTO spend (date_time, amount, name, category, import_id)
+                        VALUES (?1, ?2, ?3, ?4, ?5)",
+                    (
+                        format!("{due} 00:00:00"),
+                        expense.amount,
+                        &expense.name,
+                        &expense.category,
+                        &import_id,
+                    ),
+                )?;
+                batch.record_insert(&tx, "spend", "spend", tx.last_insert_rowid())?;
+                added += 1;
+            }
+            expense.paid += 1;
+        }
+        if expense.paid != paid_before {
+            // Recorded too, so undoing the batch brings the payments back
+            let before = audit::row_json(&tx, "recurring", expense.id)?;
+            tx.execute(
+                "UPDATE recurring SET paid = ?1 WHERE id = ?2",
+                (expense.paid, expense.id),
+            )?;
+            let after = audit::row_json(&tx, "recurring", expense.id)?;
+            batch.record(
+                &tx,
+                "recurring",
+                expense.id,
+                Action::Update,
+                before.as_ref(),
+                after.as_ref(),
+            )?;
+        }
+    }
+    tx.commit()?;
+    Ok(added)
+}
+
+/// Add the payments that have come due by today, so readers of spend count
+/// them without waiting for `recurring materialize`
+pub fn catch_up(conn: &mut Connection) -> Result<()> {
+    let added = materialize_through(conn, clock::today())?;
+    if added > 0 {
+        // stderr, to keep JSON and CSV output clean
+        eprintln!("Added {added} recurring payments to spend");
+    }
+    Ok(())
+}
+
+/// `recurring materialize`
+pub fn materialize() -> Result<()> {
+    let mut conn = db::open()?;
+    let added = materialize_through(&mut conn, clock::today())?;
+    println!("Added {added} recurring payments to spend");
+    Ok(())
+}
+
+#[derive(Serialize)]
+pub struct RecurringRow {
+    pub id: i64,
+    pub name: String,
+    pub category: String,
+    pub amount: f64,
+    pub cadence: String,
+    pub every: u32,
+    /// Empty once an installment plan is finished
+    pub next_due: Option<NaiveDate>,
+    pub until: Option<NaiveDate>,
+    pub annual: f64,
+}
+
+#[derive(Serialize)]
+pub struct RecurringReport {
+    pub expenses: Vec<RecurringRow>,
+    /// Yearly cost of the ones still running
+    pub annual: f64,
+    pub monthly: f64,
+}
+
+fn build_report(expenses: &[Expense]) -> RecurringReport {
+    let expenses: Vec<RecurringRow> = expenses
+        .iter()
+        .map(|e| RecurringRow {
+            id: e.id,
+            name: e.name.clone(),
+            category: e.category.clone(),
+            amount: e.amount,
+            cadence: e.cadence.name().to_string(),
+            every: e.every,
+            next_due: e.next_due(),
+            until: e.until,
+            annual: e.annual(),
+        })
+        .collect();
+    let annual = expenses
+        .iter()
+        .filter(|e| e.next_due.is_some())
+        .map(|e| e.annual)
+        .sum();
+    RecurringReport {
+        expenses,
+        annual,
+        monthly: annual / 12.0,
+    }
+}
+
+fn print_report(expenses: &[Expense], report: &RecurringReport) {
+    if expenses.is_empty() {
+        println!("No recurring expenses; add one with `recurring add`");
+        return;
+    }
+    println!(
+        " id  {:<20} {:>8}  {:<12} {:<15} {:<10}  {:>10}",
+        "name", "amount", "schedule", "category", "next due", "per year"
+    );
+    for (e, row) in expenses.iter().zip(&report.expenses) {
+        let next = match row.next_due {
+            Some(due) => due.to_string(),
+            None => "done".to_string(),
+        };
+        let line = format!(
+            "{:>3}  {:<20} {:>8.2}  {:<12} {:<15} {:<10}  {:>10.2}",
+            e.id,
+            e.name,
+            e.amount,
+            e.schedule(),
+            e.category,
+            next,
+            row.annual
+        );
+        println!("{}", line.trim_end());
+    }
+    println!(
+        "Total ${:.2} a year, ${:.2} a month",
+        report.annual, report.monthly
+    );
+}
+
+/// `recurring list`
+pub fn list(format: Format) -> Result<()> {
+    let conn = db::open()?;
+    let expenses = load(&conn)?;
+    let report = build_report(&expenses);
+    match format {
+        Format::Table => print_report(&expenses, &report),
+        Format::Json => output::json(&report)?,
+        Format::Csv => output::csv(&report.expenses)?,
+    }
+    Ok(())
+}
+
+/// Checklist lines for the vault note written for `day`: payments due
+/// through the week after it, including overdue ones not materialized yet.
+/// Only reads; `write_tomorrow_to_vault` catches up first.
+pub fn vault_lines(day: NaiveDate) -> Vec<String> {
+    let Ok(conn) = db::open() else {
+        return Vec::new();
+    };
+    let Ok(expenses) = load(&conn) else {
+        return Vec::new();
+    };
+    let last = day + Days::new(DUE_SOON_DAYS);
+
+    let mut due: Vec<(NaiveDate, &Expense)> = expenses
+        .iter()
+        .filter_map(|e| e.next_due().map(|d| (d, e)))
+        .filter(|(d, _)| *d <= last)
+        .collect();
+    due.sort_by_key(|(d, _)| *d);
+    due.iter()
+        .map(|(d, e)| {
+            let when = if *d < day { "overdue since" } else { "due" };
+            format!(
+                "- [ ] {} ${:.2} {when} {}",
+                e.name,
+                e.amount,
+                d.format("%a %b %-d")
+            )
+        })
+        .collect()
+}
@@ -0,0 +1,43 @@
+//! Rauch–Tung–Striebel smoother
+//!
+//! The forward filters in `model1` record every predict/update as a [`Step`];
+//! [`smooth`] runs back over them so each past day is estimated from the
+//! weigh-ins after it as well as before it.
+
+use nalgebra::{SMatrix, SVector};
+
+/// One filter step: the prediction into it and the posterior after the update
+#[derive(Clone, Debug)]
+pub struct Step<const N: usize> {
+    /// Transition used to predict into this step
+    pub f: SMatrix<f64, N, N>,
+    pub x_pred: SVector<f64, N>,
+    pub p_pred: SMatrix<f64, N, N>,
+    pub x_post: SVector<f64, N>,
+    pub p_post: SMatrix<f64, N, N>,
+}
+
+/// Smoothed state and covariance for every step, oldest first
+pub fn smooth<const N: usize>(steps: &[Step<N>]) -> Vec<(SVector<f64, N>, SMatrix<f64, N, N>)> {
+    let Some(last) = steps.last() else {
+        return Vec::new();
+    };
+    let mut out = vec![(last.x_post, last.p_post); steps.len()];
+
+    for k in (0..steps.len() - 1).rev() {
+        let (cur, next) = (&steps[k], &steps[k + 1]);
+        // A singular prediction carries nothing back; keep the filtered estimate
+        let Some(p_pred_inv) = next.p_pred.try_inverse() else {
+            out[k] = (cur.x_post, cur.p_post);
+            continue;
+        };
+        let gain = cur.p_post * next.f.transpose() * p_pred_inv;
+        let (x_next, p_next) = out[k + 1];
+
+        let x = cur.x_post + gain * (x_next - next.x_pred);
+        let p = cur.p_post + gain * (p_next - next.p_pred) * gain.transpose();
+        out[k] = (x, p);
+    }
+
+    out
+}
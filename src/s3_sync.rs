@@ -1,3 +1,4 @@
+use crate::config;
 use aws_config::meta::region::RegionProviderChain;
 use aws_sdk_s3::{Client, primitives::ByteStream};
 use chrono::{Datelike, Duration, Local, NaiveDate};
@@ -18,8 +19,8 @@ async fn sync_data_to_s3_async() -> Result<(), Box<dyn std::error::Error>> {
         .await;
     let client = Client::new(&config);
 
-    let bucket_name = "veryonlineguy";
-    let data_dir = "data";
+    let bucket_name = config::get().sync.bucket.as_str();
+    let data_dir = config::get().data_dir.as_str();
 
     // Create folder name with current date (MM-DD-YYYY-data format)
     let today = Local::now();
@@ -81,16 +82,16 @@ async fn cleanup_old_backups(
     for object in objects {
         if let Some(key) = object.key() {
             // Parse the folder name to extract the date
-            if let Some(folder_date) = parse_folder_date(key) {
-                if folder_date < cutoff_date {
-                    // Delete the object
-                    client
-                        .delete_object()
-                        .bucket(bucket_name)
-                        .key(key)
-                        .send()
-                        .await?;
-                }
+            if let Some(folder_date) = parse_folder_date(key)
+                && folder_date < cutoff_date
+            {
+                // Delete the object
+                client
+                    .delete_object()
+                    .bucket(bucket_name)
+                    .key(key)
+                    .send()
+                    .await?;
             }
         }
     }
@@ -1,6 +1,12 @@
 use crate::affirmations;
+use crate::budget;
+use crate::calorie_target;
+use crate::clock;
+use crate::db;
+use crate::debt;
 use crate::lift;
 use crate::menu::get_menu;
+use crate::recurring;
 use crate::schedule::friday;
 use crate::schedule::monday;
 use crate::schedule::saturday;
@@ -8,9 +14,9 @@ use crate::schedule::sunday;
 use crate::schedule::thursday;
 use crate::schedule::tuesday;
 use crate::schedule::wednesday;
-use crate::util::VAULT_DIR;
+use crate::util;
 use chrono::Duration;
-use chrono::{DateTime, Datelike, Days, Local, NaiveDate, Weekday};
+use chrono::{Datelike, Days, NaiveDate, Weekday};
 use rand::Rng;
 use rand::seq::IndexedRandom;
 use std::fs::File;
@@ -28,34 +34,56 @@ fn get_schedule_content(day: Weekday) -> String {
     }
 }
 
-pub fn days_since() -> i64 {
+pub fn days_since(today: NaiveDate) -> i64 {
     let earlier = NaiveDate::from_ymd_opt(2025, 7, 31).unwrap();
-    // Get today's date in UTC
-    let today = chrono::Local::now();
-    let today = NaiveDate::from_ymd_opt(today.year(), today.month(), today.day()).unwrap();
     // Compute the difference as a Duration
     let diff: Duration = today.signed_duration_since(earlier);
     // Return the number of days (truncating any fractional days)
     diff.num_days()
 }
 
+/// The day the vault note is being written for
+pub fn tomorrow() -> NaiveDate {
+    clock::today() + Days::new(1)
+}
+
 pub fn write_tomorrow_to_vault() {
-    let local: DateTime<Local> = Local::now();
-    let tomorrow: NaiveDate =
-        NaiveDate::from_ymd_opt(local.year(), local.month(), local.day()).unwrap();
-    let tomorrow = tomorrow + Days::new(1);
+    // before the budget line and "Due soon" read spend
+    if let Err(e) = db::open()
+        .map_err(Into::into)
+        .and_then(|mut conn| recurring::catch_up(&mut conn))
+    {
+        eprintln!("Error adding recurring payments: {:#}", e);
+    }
+    let tomorrow = tomorrow();
     let day = tomorrow.weekday();
 
     let content = get_schedule_content(day);
     let date_str = tomorrow.format("%Y-%m-%d").to_string();
-    let filename = format!("{}/{}.md", VAULT_DIR, date_str);
+    let filename = util::vault_path(&format!("{}.md", date_str));
 
     let mut full_content = String::new();
     full_content.push_str("# The way out is in\n\n");
     let rules = "## Rules\n- No Uber Eats (".to_owned()
-        + &days_since().to_string()
-        + "/100)\nTil out of debt and under 200lb\n\n";
+        + &days_since(clock::today()).to_string()
+        + "/100)\nTil out of debt and under 200lb\n";
     full_content.push_str(&rules);
+    if let Some(line) = debt::vault_line() {
+        full_content.push_str(&format!("{}\n", line));
+    }
+    if let Some(line) = budget::vault_line() {
+        full_content.push_str(&format!("{}\n", line));
+    }
+    full_content.push('\n');
+
+    let due = recurring::vault_lines(tomorrow);
+    if !due.is_empty() {
+        full_content.push_str(&format!("## Due soon\n{}\n\n", due.join("\n")));
+    }
+
+    if let Some(line) = calorie_target::vault_line() {
+        full_content.push_str(&format!("## Calories\n{}\n\n", line));
+    }
 
     full_content.push_str(&format!("## Day {}\n\n", day));
     full_content.push_str(&content);
@@ -79,52 +107,26 @@ pub fn write_tomorrow_to_vault() {
     match File::create(&filename) {
         Ok(mut file) => {
             if let Err(e) = file.write_all(full_content.as_bytes()) {
-                eprintln!("Error writing to file {}: {}", filename, e);
+                eprintln!("Error writing to file {}: {}", filename.display(), e);
             }
         }
         Err(e) => {
-            eprintln!("Error creating file {}: {}", filename, e);
+            eprintln!("Error creating file {}: {}", filename.display(), e);
         }
     }
 }
 
 fn format_day(day: Vec<String>) -> String {
-    let mut count = 1;
     let mut result = String::new();
-    for task in &day {
-        let todo = format!("{}. [ ] {}\n", count, task);
+    for (idx, task) in day.iter().enumerate() {
+        let todo = format!("{}. [ ] {}\n", idx + 1, task);
         result.push_str(&todo);
-        count += 1;
     }
     result
 }
 
-pub fn print_monday_schedule() {
-    print!("{}", format_day(monday::monday_schedule_str()));
-}
-
-pub fn print_tuesday_schedule() {
-    print!("{}", format_day(tuesday::tuesday_schedule_str()));
-}
-
-pub fn print_wednesday_schedule() {
-    print!("{}", format_day(wednesday::wednesday_schedule_str()));
-}
-
-pub fn print_thursday_schedule() {
-    print!("{}", format_day(thursday::thursday_schedule_str()));
-}
-
-pub fn print_friday_schedule() {
-    print!("{}", format_day(friday::friday_schedule_str()));
-}
-
-pub fn print_saturday_schedule() {
-    print!("{}", format_day(saturday::saturday_schedule_str()));
-}
-
-pub fn print_sunday_schedule() {
-    print!("{}", format_day(sunday::sunday_schedule_str()));
+pub fn print_schedule(day: Weekday) {
+    print!("{}", get_schedule_content(day));
 }
 
 // String versions of utility functions that normally print
@@ -139,7 +141,7 @@ pub fn shave_head_str() -> Vec<String> {
 pub fn clean_washer_str() -> Vec<String> {
     let week = current_week_number();
 
-    if week % 4 == 0 {
+    if week.is_multiple_of(4) {
         vec!["Clean washer 1 cup vinegar".to_string()]
     } else {
         vec![]
@@ -172,11 +174,7 @@ pub fn vid_str() -> Vec<String> {
 
 pub fn morning_str() -> Vec<String> {
     let mut result = Vec::new();
-    let local: DateTime<Local> = Local::now();
-    let tomorrow: NaiveDate =
-        NaiveDate::from_ymd_opt(local.year(), local.month(), local.day()).unwrap();
-    let tomorrow = tomorrow + Days::new(1);
-    let day = tomorrow.weekday();
+    let day = tomorrow().weekday();
     result.push("Wash Retainer");
     result.push("Wash face");
     result.push("Moisturize face");
@@ -211,11 +209,8 @@ pub fn morning_str() -> Vec<String> {
 
     let shot_str = get_shot_leg();
 
-    match day {
-        Weekday::Mon => {
-            result.push(&shot_str);
-        }
-        _ => (),
+    if day == Weekday::Mon {
+        result.push(&shot_str);
     }
     match day {
         Weekday::Mon | Weekday::Wed | Weekday::Fri => result.push("Keal vid"),
@@ -225,17 +220,15 @@ pub fn morning_str() -> Vec<String> {
 }
 
 pub fn evening_str() -> Vec<String> {
-    let mut result = Vec::new();
-
-    result.push("Take meds".to_string());
-    result.push("Floss".to_string());
-    result.push("Brush".to_string());
-    result.push("Retainer".to_string());
-    result.push("Log weight".to_string());
-    result.push("Read through tomorrow".to_string());
-    result.push("Reflection".to_string());
-
-    result
+    vec![
+        "Take meds".to_string(),
+        "Floss".to_string(),
+        "Brush".to_string(),
+        "Retainer".to_string(),
+        "Log weight".to_string(),
+        "Read through tomorrow".to_string(),
+        "Reflection".to_string(),
+    ]
 }
 
 pub fn tea() -> Vec<String> {
@@ -245,20 +238,10 @@ pub fn tea() -> Vec<String> {
     vec![]
 }
 
-pub fn goal_clothes_str() -> Vec<String> {
-    let weeks = get_current_week();
-
-    if weeks % 6 == 0 {
-        vec!["Try on goal clothes".to_string()]
-    } else {
-        vec![]
-    }
-}
-
 pub fn clean_silverware_drawer() -> Vec<String> {
     let weeks = get_current_week();
 
-    if weeks % 4 == 0 {
+    if weeks.is_multiple_of(4) {
         vec!["Clean silverware drawer".to_string()]
     } else {
         vec![]
@@ -269,85 +252,35 @@ pub fn gratitude() -> Vec<String> {
     vec!["Write on thing your grateful for".to_string()]
 }
 
-pub fn makeup_str() -> Vec<String> {
-    let lip_options = ["Kush Lip Oil", "Summer Fridays", "Peach Glaze"];
-    let scents = ["Glossier scent", "Britney Spears scent"];
-
-    let mut result: Vec<String> = Vec::new();
-    result.push("Mascara".to_string());
-    result.push("Eye Liner".to_string());
-    let lip_option = lip_options.choose(&mut rand::rng()).unwrap();
-    result.push(format!("lip: {}", lip_option));
-    let scent = scents.choose(&mut rand::rng()).unwrap();
-    result.push(format!("{}", scent));
-    result
-}
-
-pub fn nails() -> Vec<String> {
-    let mut content: Vec<String> = Vec::new();
-    let options = ["Crustacean", "Hula hoop"];
-
-    content.push(options.choose(&mut rand::rng()).unwrap().to_string());
-    content.push("File nails".to_string());
-    content.push("color".to_string());
-    content.push("10 min wait".to_string());
-    content.push("color".to_string());
-    content.push("10 min wait".to_string());
-    content.push("color".to_string());
-    content.push("20 min wait".to_string());
-    content.push("top coat".to_string());
-    content.push("wait 10 minutes".to_string());
-
-    let _content = content;
-
-    vec![]
-}
-
 pub fn change_razor_str() -> Vec<String> {
     let week = current_week_number();
 
-    if week % 4 == 0 {
+    if week.is_multiple_of(4) {
         vec!["Change leg razor".to_string()]
     } else {
         vec![]
     }
 }
 
-pub fn buy_stuff_str() -> Vec<String> {
-    let week = get_current_week();
-    if week % 4 == 1 {
-        vec!["BUY! BUY! BUY!".to_string()]
-    } else {
-        vec![]
-    }
-}
-
-pub fn empty_ice_bin() -> Vec<String> {
-    let week = get_current_week();
-    if week % 12 == 1 {
-        vec!["empty ice bin".to_string()]
-    } else {
-        vec![]
-    }
+pub fn week_number(date: NaiveDate) -> u32 {
+    date.iso_week().week()
 }
 
 pub fn current_week_number() -> u32 {
-    let today = Local::now().date_naive();
-    today.iso_week().week()
+    week_number(clock::today())
 }
 
 fn get_shot_leg() -> String {
     let week = current_week_number();
 
-    let shot = match week % 2 {
+    match week % 2 {
         0 => "Do shot L".to_string(),
         _ => "Do shot R".to_string(),
-    };
-    shot
+    }
 }
 
 pub fn _movie() -> Vec<String> {
-    let hulu_movies = vec![
+    let hulu_movies = [
         "Maxxxine",
         "Anora",
         "Paris is burning",
@@ -405,7 +338,7 @@ pub fn _movie() -> Vec<String> {
         "Jaws",
     ];
 
-    let _apple_tv = vec!["Trainspotting"];
+    let _apple_tv = ["Trainspotting"];
 
     let amazon_movies = vec![
         "Baby drive",
@@ -478,6 +411,5 @@ pub fn makeup() {
 }
 
 pub fn get_current_week() -> u32 {
-    let today = Local::now().date_naive();
-    today.iso_week().week()
+    current_week_number()
 }
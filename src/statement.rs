@@ -0,0 +1,408 @@
+//! Bank and credit-card statement import
+//!
+//! `import statement` reads OFX/QFX downloads, or CSV exports laid out as
+//! `[statement.csv]` in the config, and turns every purchase into a `spend`
+//! row; payments and refunds are left out. A transaction is skipped when
+//! its bank id was imported before, or when a spend logged by hand or from
+//! another kind of file has the same date and amount. Categories come from
+//! the first `category_rule` whose pattern matches the description
+//! (case-insensitive). Transactions no rule matches are asked about one by
+//! one, and the answer can be saved as a new rule; those skipped stay out
+//! until the next import.
+
+use crate::audit;
+use crate::clock;
+use crate::config;
+use crate::db;
+use anyhow::{Context, Result, bail};
+use chrono::NaiveDate;
+use csv::ReaderBuilder;
+use regex::{Regex, RegexBuilder};
+use rusqlite::{Connection, OptionalExtension};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::Path;
+
+/// Amounts closer than this are the same
+pub const CENT: f64 = 0.005;
+
+struct Transaction {
+    date: NaiveDate,
+    /// Positive for money going out
+    amount: f64,
+    description: String,
+    /// Bank id of the transaction, stored in `spend.import_id`
+    import_id: String,
+}
+
+/// `-12.34`, `$1,234.56` or `(12.34)`
+pub fn parse_amount(s: &str) -> Option<f64> {
+    let s = s.trim();
+    let (negative, s) = match s.strip_prefix('(').and_then(|s| s.strip_suffix(')')) {
+        Some(inner) => (true, inner),
+        None => (false, s),
+    };
+    let cleaned: String = s
+        .chars()
+        .filter(|c| !matches!(c, '$' | ',' | ' '))
+        .collect();
+    let value: f64 = cleaned.parse().ok()?;
+    Some(if negative { -value } else { value })
+}
+
+/// Text of the first `<tag>` element in `block`; OFX 1.x leaves them unclosed
+fn ofx_field(block: &str, tag: &str) -> Option<String> {
+    let re = Regex::new(&format!(r"(?i)<{tag}>([^<\r\n]*)")).ok()?;
+    let value = re.captures(block)?[1].trim().replace("&amp;", "&");
+    (!value.is_empty()).then_some(value)
+}
+
+fn parse_ofx(text: &str) -> Result<Vec<Transaction>> {
+    let account = ofx_field(text, "ACCTID").unwrap_or_default();
+    let re = Regex::new(r"(?is)<STMTTRN>(.*?)</STMTTRN>")?;
+
+    let mut transactions = Vec::new();
+    for block in re.captures_iter(text) {
+        let block = &block[1];
+        let (Some(posted), Some(amount), Some(id)) = (
+            ofx_field(block, "DTPOSTED"),
+            ofx_field(block, "TRNAMT"),
+            ofx_field(block, "FITID"),
+        ) else {
+            bail!(
+                "transaction without DTPOSTED, TRNAMT or FITID: {}",
+                block.trim()
+            );
+        };
+        let date = NaiveDate::parse_from_str(posted.get(..8).unwrap_or(&posted), "%Y%m%d")
+            .with_context(|| format!("bad DTPOSTED {posted}"))?;
+        let amount = parse_amount(&amount).with_context(|| format!("bad TRNAMT {amount}"))?;
+        let description = ofx_field(block, "NAME")
+            .or_else(|| ofx_field(block, "MEMO"))
+            .unwrap_or_default();
+        transactions.push(Transaction {
+            date,
+            amount: -amount,
+            description,
+            import_id: format!("ofx:{account}:{id}"),
+        });
+    }
+    Ok(transactions)
+}
+
+fn parse_csv(text: &str) -> Result<Vec<Transaction>> {
+    let cfg = &config::get().statement.csv;
+    let mut rdr = ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(text.as_bytes());
+    let headers: Vec<String> = rdr
+        .headers()?
+        .iter()
+        .map(|h| h.trim().to_lowercase())
+        .collect();
+    let column = |key: &str, header: &str| -> Result<Option<usize>> {
+        if header.is_empty() {
+            return Ok(None);
+        }
+        match headers.iter().position(|h| *h == header.to_lowercase()) {
+            Some(idx) => Ok(Some(idx)),
+            None => bail!(
+                "no '{header}' column (statement.csv.{key}); the file has: {}",
+                headers.join(", ")
+            ),
+        }
+    };
+    let date_col = column("date", &cfg.date)?.context("statement.csv.date is empty")?;
+    let desc_col =
+        column("description", &cfg.description)?.context("statement.csv.description is empty")?;
+    let amount_col = column("amount", &cfg.amount)?;
+    let debit_col = column("debit", &cfg.debit)?;
+    let credit_col = column("credit", &cfg.credit)?;
+    if amount_col.is_none() && debit_col.is_none() {
+        bail!("set statement.csv.amount, or statement.csv.debit and credit");
+    }
+
+    // identical rows in one file are told apart by how many came before
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    let mut transactions = Vec::new();
+    for record in rdr.records() {
+        let record = record?;
+        let field = |idx: usize| record.get(idx).unwrap_or("").trim();
+        if field(date_col).is_empty() {
+            continue;
+        }
+        let date = NaiveDate::parse_from_str(field(date_col), &cfg.date_format)
+            .with_context(|| format!("date '{}' isn't {}", field(date_col), cfg.date_format))?;
+        let amount = match amount_col {
+            Some(idx) => {
+                let signed = parse_amount(field(idx))
+                    .with_context(|| format!("bad amount '{}'", field(idx)))?;
+                if cfg.purchases_negative {
+                    -signed
+                } else {
+                    signed
+                }
+            }
+            None => {
+                let get = |col: Option<usize>| col.and_then(|idx| parse_amount(field(idx)));
+                get(debit_col).unwrap_or(0.0).abs() - get(credit_col).unwrap_or(0.0).abs()
+            }
+        };
+        let description = field(desc_col).to_string();
+
+        let key = format!("{date}:{amount:.2}:{description}");
+        let n = seen.entry(key.clone()).or_default();
+        *n += 1;
+        transactions.push(Transaction {
+            date,
+            amount,
+            description,
+            import_id: format!("csv:{key}#{n}"),
+        });
+    }
+    Ok(transactions)
+}
+
+struct Rule {
+    pattern: Regex,
+    category: String,
+}
+
+fn compile(pattern: &str) -> Result<Regex, regex::Error> {
+    RegexBuilder::new(pattern).case_insensitive(true).build()
+}
+
+fn load_rules(conn: &Connection) -> Result<Vec<Rule>> {
+    let mut stmt =
+        conn.prepare("SELECT pattern, category FROM category_rule ORDER BY position, id")?;
+    let rows = stmt.query_map([], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+    })?;
+    let mut rules = Vec::new();
+    for row in rows {
+        let (pattern, category) = row?;
+        let pattern = compile(&pattern).with_context(|| format!("category rule /{pattern}/"))?;
+        rules.push(Rule { pattern, category });
+    }
+    Ok(rules)
+}
+
+/// Append a rule after the existing ones
+fn save_rule(conn: &Connection, pattern: &str, category: &str) -> Result<()> {
+    conn.execute(
+        "INSERT INTO category_rule (date_time, position, pattern, category)
+            VALUES (?1, (SELECT COALESCE(MAX(position), 0) + 1 FROM category_rule), ?2, ?3)",
+        (clock::timestamp(), pattern, category),
+    )?;
+    audit::record_insert(conn, "rule", "category_rule", conn.last_insert_rowid())?;
+    Ok(())
+}
+
+fn categorize<'a>(rules: &'a [Rule], description: &str) -> Option<&'a str> {
+    rules
+        .iter()
+        .find(|rule| rule.pattern.is_match(description))
+        .map(|rule| rule.category.as_str())
+}
+
+/// Already imported, or logged some other way (by hand, or from another
+/// kind of file) on the same day for the same amount. Each existing row
+/// only stands in for one transaction; `used` tracks those taken.
+pub fn already_logged(
+    conn: &Connection,
+    date: NaiveDate,
+    amount: f64,
+    import_id: &str,
+    used: &mut HashSet<i64>,
+) -> Result<bool> {
+    let imported: Option<i64> = conn
+        .query_row(
+            "SELECT id FROM spend WHERE import_id = ?1",
+            [import_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    if imported.is_some() {
+        return Ok(true);
+    }
+
+    let source = import_id.split(':').next().unwrap_or_default();
+    let mut stmt = conn.prepare(
+        "SELECT id FROM spend WHERE (import_id IS NULL OR import_id NOT LIKE ?4)
+            AND date(date_time) = ?1 AND abs(amount - ?2) < ?3",
+    )?;
+    let ids = stmt.query_map(
+        (date.to_string(), amount, CENT, format!("{source}:%")),
+        |row| row.get::<_, i64>(0),
+    )?;
+    for id in ids {
+        let id = id?;
+        if used.insert(id) {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+fn read_line(prompt: &str) -> String {
+    print!("{prompt}");
+    io::stdout().flush().unwrap();
+    let mut input = String::new();
+    io::stdin().read_line(&mut input).unwrap();
+    input.trim().to_string()
+}
+
+/// A pattern matching descriptions like this one: its first word
+fn suggest_pattern(description: &str) -> String {
+    let word = description.split_whitespace().next().unwrap_or(description);
+    format!("^{}", regex::escape(word))
+}
+
+/// Ask for the category of one transaction, offering to save a rule for it.
+/// `None` leaves it for a later import.
+fn review(
+    conn: &Connection,
+    txn: &Transaction,
+    categories: &mut Vec<String>,
+    rules: &mut Vec<Rule>,
+) -> Result<Option<String>> {
+    println!();
+    println!("{}  ${:.2}  {}", txn.date, txn.amount, txn.description);
+    let input = read_line("Category (number, new name, empty to skip): ");
+    if input.is_empty() {
+        return Ok(None);
+    }
+    let category = match input.parse::<usize>() {
+        Ok(idx) if idx < categories.len() => categories[idx].clone(),
+        _ => input,
+    };
+    if !categories.contains(&category) {
+        println!("{}: {category}", categories.len());
+        categories.push(category.clone());
+    }
+
+    let suggestion = suggest_pattern(&txn.description);
+    let answer = read_line(&format!(
+        "Save rule /{suggestion}/ -> {category}? [y/N or another pattern]: "
+    ));
+    let pattern = match answer.as_str() {
+        "" | "n" | "N" => None,
+        "y" | "Y" => Some(suggestion),
+        other => Some(other.to_string()),
+    };
+    if let Some(pattern) = pattern {
+        match compile(&pattern) {
+            Ok(regex) => {
+                save_rule(conn, &pattern, &category)?;
+                rules.push(Rule {
+                    pattern: regex,
+                    category: category.clone(),
+                });
+            }
+            Err(e) => println!("Not saved, /{pattern}/ isn't a valid pattern: {e}"),
+        }
+    }
+    Ok(Some(category))
+}
+
+/// `import statement`
+pub fn import(path: &Path, dry_run: bool, no_review: bool) -> Result<()> {
+    let text = fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+    let transactions = if text.to_uppercase().contains("<OFX>") {
+        parse_ofx(&text)?
+    } else {
+        parse_csv(&text)?
+    };
+
+    let mut conn = db::open()?;
+    let mut rules = load_rules(&conn)?;
+    let interactive = !dry_run && !no_review && io::stdin().is_terminal();
+
+    let mut categories: Vec<String> = {
+        let mut stmt = conn.prepare("SELECT category FROM spend GROUP BY category")?;
+        stmt.query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()?
+    };
+    if interactive {
+        for (idx, category) in categories.iter().enumerate() {
+            println!("{idx}: {category}");
+        }
+    }
+
+    let mut used = HashSet::new();
+    let (mut credits, mut duplicates, mut by_rule, mut reviewed) = (0, 0, 0, 0);
+    let mut unmatched = Vec::new();
+    let mut accepted = Vec::new();
+    for txn in transactions {
+        if txn.amount <= CENT {
+            credits += 1;
+            continue;
+        }
+        if already_logged(&conn, txn.date, txn.amount, &txn.import_id, &mut used)? {
+            duplicates += 1;
+            continue;
+        }
+        if let Some(category) = categorize(&rules, &txn.description) {
+            by_rule += 1;
+            accepted.push((txn, category.to_string()));
+        } else if interactive {
+            match review(&conn, &txn, &mut categories, &mut rules)? {
+                Some(category) => {
+                    reviewed += 1;
+                    accepted.push((txn, category));
+                }
+                None => unmatched.push(txn),
+            }
+        } else {
+            unmatched.push(txn);
+        }
+    }
+
+    if dry_run {
+        for (txn, category) in &accepted {
+            println!(
+                "{}  {:>9.2}  {:<15} {}",
+                txn.date, txn.amount, category, txn.description
+            );
+        }
+        for txn in &unmatched {
+            println!(
+                "{}  {:>9.2}  {:<15} {}",
+                txn.date, txn.amount, "(no rule)", txn.description
+            );
+        }
+    } else {
+        let tx = conn.transaction()?;
+        let mut batch = audit::Batch::new();
+        for (txn, category) in &accepted {
+            tx.execute(
+                "INSERT INTO spend (date_time, amount, name, category, import_id) VALUES (?1, ?2, ?3, ?4, ?5)",
+                (
+                    format!("{} 00:00:00", txn.date),
+                    txn.amount,
+                    &txn.description,
+                    category,
+                    &txn.import_id,
+                ),
+            )?;
+            batch.record_insert(&tx, "spend", "spend", tx.last_insert_rowid())?;
+        }
+        tx.commit()?;
+    }
+
+    println!(
+        "{} {} purchases from {} ({by_rule} by rule, {reviewed} reviewed)",
+        if dry_run { "Would import" } else { "Imported" },
+        accepted.len(),
+        path.display()
+    );
+    println!("Skipped {duplicates} already logged and {credits} payments or refunds");
+    if !unmatched.is_empty() {
+        println!(
+            "{} had no matching rule and were left out; import again in a terminal to review them",
+            unmatched.len()
+        );
+    }
+    Ok(())
+}
@@ -1,96 +1,50 @@
-use crate::util;
+use crate::cli::Format;
+use crate::clock;
+use crate::config;
+use crate::db;
+use crate::events;
+use crate::output;
+use anyhow::{Result, bail};
+use chrono::NaiveDate;
 use polyfit_rs::polyfit_rs::polyfit;
-use rusqlite::{Connection, Result};
+use serde::Serialize;
 use std::collections::HashMap;
 
-pub fn caffeine_summary() -> Result<()> {
-    let db_path = util::get_database_path()?;
-    let conn = Connection::open(&db_path)?;
-
-    let mut stmt = conn.prepare(
-        "select sum(caffeine_mg), date(date_time) from caffeine group by date(date_time) limit 7",
-    )?;
-
-    let caff_iter = stmt.query_map([], |row| {
-        Ok((row.get::<_, u32>(0)?, row.get::<_, String>(1)?))
-    })?;
-
-    for caff in caff_iter {
-        let (amount, date) = caff?;
-        println!("{date}: {amount}");
-    }
-
-    Ok(())
-}
-
-fn query_work_table(name: String) -> Result<u32> {
-    let db_path = util::get_database_path()?;
-    let conn = Connection::open(&db_path)?;
-
-    let stmt = "select sum(time) from ".to_owned()
-        + &name
-        + " where date(date_time) == date(datetime('now','localtime'));";
-
-    let Ok(mut stmt) = conn.prepare(&(stmt)) else {
-        panic!("WTF");
-    };
-
-    if let Ok(iter) = stmt.query_map([], |row| Ok(row.get::<_, u32>(0))) {
-        for res in iter {
-            if let Ok(res) = res {
-                if let Ok(res) = res {
-                    return Ok(res);
-                }
-            }
-        }
-    }
-
-    Ok(0)
+/// Minutes on one timed activity today
+#[derive(Serialize)]
+pub struct WorkTime {
+    pub activity: String,
+    pub label: String,
+    pub minutes: f64,
 }
 
-fn query_reading() -> Result<Vec<(String, u32)>> {
-    let db_path = util::get_database_path()?;
-    let conn = Connection::open(&db_path)?;
-
-    let mut stmt = conn.prepare(
-       &("select type, sum(time) from reading where date(date_time) == date(datetime('now','localtime')) group by type;"),
-    )?;
-
-    let iter = stmt.query_map([], |row| {
-        Ok((row.get::<_, String>(0)?, row.get::<_, u32>(1)?))
-    })?;
-
-    let mut ret = Vec::new();
-    for res in iter {
-        ret.push(res?);
+pub fn work_summary(format: Format) -> Result<()> {
+    let conn = db::open()?;
+    let totals: HashMap<String, f64> = events::totals_on(&conn, clock::today())?
+        .into_iter()
+        .collect();
+
+    // Every timed activity that was worked on today, in config order
+    let times: Vec<WorkTime> = config::get()
+        .activities
+        .iter()
+        .filter(|activity| activity.unit == "min")
+        .filter_map(|activity| {
+            totals.get(&activity.name).map(|&minutes| WorkTime {
+                activity: activity.name.clone(),
+                label: activity.label.clone(),
+                minutes,
+            })
+        })
+        .collect();
+
+    match format {
+        Format::Table => {}
+        Format::Json => return output::json(&times),
+        Format::Csv => return output::csv(&times),
     }
 
-    Ok(ret)
-}
-pub fn work_summary() -> Result<()> {
-    let current_tasks = ["link", "pwn_college", "ccna", "exit"];
-
     println!("# Productivity stats:  ");
-    let mapping = HashMap::from([
-        ("link".to_string(), "Reading security articles".to_string()),
-        (
-            "pwn_college".to_string(),
-            "Studying on pwn.college".to_string(),
-        ),
-        ("exit".to_string(), "Working on todo app".to_string()),
-        ("pleasure".to_string(), "Reading fun book".to_string()),
-        ("technical".to_string(), "Reading the rust book".to_string()),
-    ]);
-
-    let mut res = Vec::new();
-    for task in current_tasks {
-        let time = query_work_table(task.to_string())?;
-        res.push((task.to_string(), time));
-    }
-
-    for task in query_reading()? {
-        res.push(task);
-    }
 
     fn format_time(mins: u32) -> String {
         if mins < 60 {
@@ -102,19 +56,23 @@ pub fn work_summary() -> Result<()> {
         }
     }
 
-    for (task, time) in &res {
-        let pretty = mapping.get(task).unwrap();
-        let time = format_time(*time);
-        println!("- {: <25} for {time}  ", pretty);
+    for work in &times {
+        let time = format_time(work.minutes as u32);
+        println!("- {: <25} for {time}  ", work.label);
     }
 
     Ok(())
 }
 
-pub fn spend_summary() -> Result<()> {
-    let db_path = util::get_database_path()?;
-    let conn = Connection::open(&db_path)?;
+/// Total spend on one day
+#[derive(Serialize)]
+pub struct DaySpend {
+    pub date: NaiveDate,
+    pub amount: f64,
+}
 
+// The last seven days with any spend, oldest first
+fn spend_days(conn: &rusqlite::Connection) -> Result<Vec<DaySpend>> {
     let mut stmt = conn.prepare(
         "select date(date_time), sum(amount) from spend group by date(date_time) order by date(date_time) desc limit 7;",
     )?;
@@ -126,21 +84,48 @@ pub fn spend_summary() -> Result<()> {
     let mut dat = vec![];
     for spend in spend_iter {
         let (date, amount) = spend?;
-        let amount = format!("{:.2}", amount);
+        dat.push(DaySpend {
+            date: NaiveDate::parse_from_str(&date, "%Y-%m-%d")?,
+            amount,
+        });
+    }
+    dat.reverse();
+    Ok(dat)
+}
+
+fn print_spend(days: &[DaySpend]) {
+    for spend in days {
+        let amount = format!("{:.2}", spend.amount);
         let amount = "$".to_owned() + &amount;
-        dat.push(format!("{date}: {amount: >7}  "));
+        println!("{}: {amount: >7}  ", spend.date);
     }
+}
 
-    for spend in dat.into_iter().rev() {
-        println!("{spend}");
+pub fn spend_summary(format: Format) -> Result<()> {
+    let conn = db::open()?;
+    let days = spend_days(&conn)?;
+
+    match format {
+        Format::Table => print_spend(&days),
+        Format::Json => output::json(&days)?,
+        Format::Csv => output::csv(&days)?,
     }
     Ok(())
 }
 
-fn fit_rowing() -> Result<()> {
-    let db_path = util::get_database_path()?;
-    let conn = Connection::open(&db_path)?;
+/// 2k erg prediction from the first 30 logged rows
+#[derive(Serialize)]
+pub struct Rowing {
+    pub two_k_seconds: f64,
+    pub split_seconds: f64,
+    pub vo2_estimate: f64,
+}
+
+/// Rows needed to fit the quadratic
+const MIN_ROWS: usize = 3;
 
+/// None until `MIN_ROWS` rows are logged
+fn rowing_prediction(conn: &rusqlite::Connection) -> Result<Option<Rowing>> {
     let mut stmt =
         conn.prepare("select distance, time from row  order by date_time asc limit 30;")?;
 
@@ -152,20 +137,20 @@ fn fit_rowing() -> Result<()> {
     for spend in spend_iter {
         let (distance, time) = spend?;
         distances.push(distance);
-        times.push(time);
+        // logged in minutes
+        times.push(time * 60.0);
     }
 
-    let [a, b, c] = polyfit(&distances, &times, 2).unwrap().try_into().unwrap();
+    if distances.len() < MIN_ROWS {
+        return Ok(None);
+    }
+    let coefficients = polyfit(&distances, &times, 2).map_err(anyhow::Error::msg)?;
+    let [a, b, c] = coefficients[..] else {
+        bail!("fitting the rows gave {} coefficients", coefficients.len());
+    };
 
     let x: f64 = 2000.0;
 
-    fn conv_time(seconds: u64) -> String {
-        let minutes = seconds / 60;
-        let second = seconds % 60;
-
-        format!("{}:{}", minutes, second).to_string()
-    }
-
     let twoktime = a + b * x + c * x.powf(2.0);
 
     let split = twoktime / 4.0;
@@ -176,7 +161,7 @@ fn fit_rowing() -> Result<()> {
     let sec = (twoktime as u64) % 60;
     let sec = (sec as f64) / 60.0;
 
-    let min = (twoktime as u64 / 60);
+    let min = twoktime as u64 / 60;
 
     let mins = min as f64 + sec;
 
@@ -184,18 +169,72 @@ fn fit_rowing() -> Result<()> {
 
     let vo2 = (y * 1000.0) / weight_kg;
 
+    Ok(Some(Rowing {
+        two_k_seconds: twoktime,
+        split_seconds: split,
+        vo2_estimate: vo2,
+    }))
+}
+
+fn print_rowing(rowing: &Rowing) {
+    fn conv_time(seconds: u64) -> String {
+        let minutes = seconds / 60;
+        let second = seconds % 60;
+
+        format!("{}:{}", minutes, second).to_string()
+    }
+
     println!(
         "2k time pred {} split {}  ",
-        conv_time(twoktime as u64),
-        conv_time(split as u64)
+        conv_time(rowing.two_k_seconds as u64),
+        conv_time(rowing.split_seconds as u64)
     );
-    println!("Vo2 Estimate {:.1}  ", vo2);
+    println!("Vo2 Estimate {:.1}  ", rowing.vo2_estimate);
+}
+
+pub fn fit_rowing(format: Format) -> Result<()> {
+    let conn = db::open()?;
+    let Some(rowing) = rowing_prediction(&conn)? else {
+        bail!("the 2k prediction needs at least {MIN_ROWS} rows logged with `log row`");
+    };
+
+    match format {
+        Format::Table => print_rowing(&rowing),
+        Format::Json => output::json(&rowing)?,
+        Format::Csv => output::csv(&[rowing])?,
+    }
     Ok(())
 }
 
-pub fn report() -> Result<()> {
-    spend_summary()?;
-    fit_rowing()?;
+/// `report all`
+#[derive(Serialize)]
+pub struct Report {
+    pub spend: Vec<DaySpend>,
+    /// Empty until enough rows are logged
+    pub rowing: Option<Rowing>,
+}
+
+pub fn report(format: Format) -> Result<()> {
+    if format == Format::Csv {
+        bail!(
+            "`report all` has more than one table; use `report spend` or `report rowing` for CSV"
+        );
+    }
+
+    let conn = db::open()?;
+    let report = Report {
+        spend: spend_days(&conn)?,
+        rowing: rowing_prediction(&conn)?,
+    };
+
+    if format == Format::Json {
+        return output::json(&report);
+    }
+    print_spend(&report.spend);
+    match &report.rowing {
+        Some(rowing) => print_rowing(rowing),
+        None => println!("Log at least {MIN_ROWS} rows for a 2k prediction"),
+    }
 
     Ok(())
 }
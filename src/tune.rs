@@ -0,0 +1,350 @@
+//! Noise fitting for the weight filters
+//!
+//! `model tune` runs a filter over the whole history and scores it by the
+//! log-likelihood of its weigh-in residuals (innovations). Nelder–Mead
+//! searches the scale noise (R), the process noise (Q) and the initial TDEE
+//! prior for the values that make the history most likely. Standard
+//! deviations are searched on a log scale so they stay positive.
+
+use crate::cli::Format;
+use crate::config::{self, Kalman2DNoise, Kalman3DNoise};
+use crate::model1::{self, Day, Kalman2D, Kalman3D};
+use crate::output;
+use crate::weight;
+use anyhow::{Result, bail};
+use serde::Serialize;
+use std::path::PathBuf;
+
+/// Objective evaluations before the search gives up
+const MAX_EVALS: usize = 4_000;
+
+/// Simplex spread, in objective units, at which the search stops
+const TOLERANCE: f64 = 1e-6;
+
+struct Param {
+    name: &'static str,
+    current: f64,
+    /// searched as ln(value)
+    log: bool,
+    /// initial simplex step
+    step: f64,
+    /// Plausible range. The likelihood is flat or degenerate in some
+    /// directions (a TDEE prior shrinking to nothing once the initial TDEE
+    /// fits), so the search is kept inside it.
+    range: (f64, f64),
+}
+
+impl Param {
+    fn searched(&self, value: f64) -> f64 {
+        let value = value.clamp(self.range.0, self.range.1);
+        if self.log { value.ln() } else { value }
+    }
+
+    /// Held at its current value rather than searched
+    fn fixed(&self) -> bool {
+        self.step == 0.0
+    }
+
+    fn value(&self, theta: f64) -> f64 {
+        let value = if self.log { theta.exp() } else { theta };
+        value.clamp(self.range.0, self.range.1)
+    }
+}
+
+/// Minimise `f` from `x0` with the Nelder–Mead simplex method.
+/// Returns the best point and its value.
+fn nelder_mead(f: impl Fn(&[f64]) -> f64, x0: &[f64], steps: &[f64]) -> (Vec<f64>, f64) {
+    let n = x0.len();
+    let mut simplex: Vec<(Vec<f64>, f64)> = Vec::with_capacity(n + 1);
+    simplex.push((x0.to_vec(), f(x0)));
+    for i in 0..n {
+        let mut x = x0.to_vec();
+        x[i] += steps[i];
+        let fx = f(&x);
+        simplex.push((x, fx));
+    }
+    let mut evals = n + 1;
+
+    // x_a + t (x_b − x_a)
+    let toward = |a: &[f64], b: &[f64], t: f64| -> Vec<f64> {
+        a.iter().zip(b).map(|(a, b)| a + t * (b - a)).collect()
+    };
+
+    while evals < MAX_EVALS {
+        simplex.sort_by(|a, b| a.1.total_cmp(&b.1));
+        if (simplex[n].1 - simplex[0].1).abs() < TOLERANCE {
+            break;
+        }
+
+        // centroid of all but the worst
+        let mut centroid = vec![0.0; n];
+        for (x, _) in &simplex[..n] {
+            for (c, v) in centroid.iter_mut().zip(x) {
+                *c += v / n as f64;
+            }
+        }
+        let worst = simplex[n].clone();
+
+        let reflected = toward(&centroid, &worst.0, -1.0);
+        let f_reflected = f(&reflected);
+        evals += 1;
+
+        if f_reflected < simplex[0].1 {
+            let expanded = toward(&centroid, &worst.0, -2.0);
+            let f_expanded = f(&expanded);
+            evals += 1;
+            simplex[n] = if f_expanded < f_reflected {
+                (expanded, f_expanded)
+            } else {
+                (reflected, f_reflected)
+            };
+        } else if f_reflected < simplex[n - 1].1 {
+            simplex[n] = (reflected, f_reflected);
+        } else {
+            let contracted = if f_reflected < worst.1 {
+                toward(&centroid, &reflected, 0.5)
+            } else {
+                toward(&centroid, &worst.0, 0.5)
+            };
+            let f_contracted = f(&contracted);
+            evals += 1;
+            if f_contracted < worst.1.min(f_reflected) {
+                simplex[n] = (contracted, f_contracted);
+            } else {
+                // shrink everything towards the best point
+                let best = simplex[0].0.clone();
+                for (x, fx) in simplex.iter_mut().skip(1) {
+                    *x = toward(&best, x, 0.5);
+                    *fx = f(x);
+                }
+                evals += n;
+            }
+        }
+    }
+
+    simplex.sort_by(|a, b| a.1.total_cmp(&b.1));
+    simplex.swap_remove(0)
+}
+
+fn noise_2d(v: &[f64]) -> Kalman2DNoise {
+    Kalman2DNoise {
+        scale_sd: v[0],
+        weight_drift_sd: v[1],
+        tdee_drift_sd: v[2],
+        tdee_prior_sd: v[4],
+    }
+}
+
+fn noise_3d(v: &[f64]) -> Kalman3DNoise {
+    Kalman3DNoise {
+        scale_sd: v[0],
+        weight_drift_sd: v[1],
+        tdee_drift_sd: v[2],
+        tdee_prior_sd: v[4],
+        re_drift_sd: v[5],
+    }
+}
+
+fn log_likelihood(days: &[Day], three_state: bool, v: &[f64]) -> f64 {
+    let initial_w_kg = config::get().model.initial_weight_kg;
+    if three_state {
+        let mut kf = Kalman3D::with_noise(initial_w_kg, v[3], &noise_3d(v));
+        days.iter().for_each(|d| kf.step(d));
+        kf.log_likelihood()
+    } else {
+        let mut kf = Kalman2D::with_noise(initial_w_kg, v[3], &noise_2d(v));
+        days.iter().for_each(|d| kf.step(d));
+        kf.log_likelihood()
+    }
+}
+
+/// One fitted parameter
+#[derive(Serialize)]
+pub struct Fitted {
+    pub name: &'static str,
+    pub current: f64,
+    pub fitted: f64,
+}
+
+#[derive(Serialize)]
+pub struct TuneReport {
+    pub model: &'static str,
+    pub days: usize,
+    pub weigh_ins: usize,
+    pub params: Vec<Fitted>,
+    pub log_likelihood_before: f64,
+    pub log_likelihood_after: f64,
+    /// Config file written with `--save`
+    pub saved_to: Option<PathBuf>,
+}
+
+/// `model tune`
+pub fn run(three_state: bool, save: bool, format: Format) -> Result<()> {
+    let days = model1::calendar_days(&weight::model_rows()?)?;
+    let weigh_ins: usize = days.iter().map(|d| d.weights_kg.len()).sum();
+    if weigh_ins < 30 {
+        bail!("only {weigh_ins} weigh-ins logged; tuning needs at least 30");
+    }
+
+    if three_state && days.iter().all(|d| d.activity == 0.0) {
+        bail!("no Strava relative effort logged; the 3-state filter has nothing to fit");
+    }
+
+    let model = &config::get().model;
+    let mut params = vec![
+        Param {
+            name: "scale_sd",
+            current: 0.0,
+            log: true,
+            step: 0.5,
+            range: (0.02, 3.0),
+        },
+        Param {
+            name: "weight_drift_sd",
+            current: 0.0,
+            log: true,
+            step: 1.0,
+            range: (1e-3, 2.0),
+        },
+        Param {
+            name: "tdee_drift_sd",
+            current: 0.0,
+            log: true,
+            step: 0.5,
+            range: (0.1, 200.0),
+        },
+        Param {
+            name: "initial_tdee",
+            current: model.initial_tdee,
+            log: false,
+            step: 200.0,
+            range: (1_000.0, 6_000.0),
+        },
+        Param {
+            name: "tdee_prior_sd",
+            current: 0.0,
+            log: true,
+            step: 0.5,
+            range: (50.0, 2_000.0),
+        },
+    ];
+    if three_state {
+        // initial_tdee is shared with the 2-state filter, which owns it
+        params[3].step = 0.0;
+        params[3].range = (model.initial_tdee, model.initial_tdee);
+        let noise = &model.kalman3d;
+        params[0].current = noise.scale_sd;
+        params[1].current = noise.weight_drift_sd;
+        params[2].current = noise.tdee_drift_sd;
+        params[4].current = noise.tdee_prior_sd;
+        params.push(Param {
+            name: "re_drift_sd",
+            current: noise.re_drift_sd,
+            log: true,
+            step: 0.5,
+            range: (0.1, 50.0),
+        });
+    } else {
+        let noise = &model.kalman2d;
+        params[0].current = noise.scale_sd;
+        params[1].current = noise.weight_drift_sd;
+        params[2].current = noise.tdee_drift_sd;
+        params[4].current = noise.tdee_prior_sd;
+    }
+
+    let current: Vec<f64> = params.iter().map(|p| p.current).collect();
+    let before = log_likelihood(&days, three_state, &current);
+
+    let to_values = |theta: &[f64]| -> Vec<f64> {
+        params.iter().zip(theta).map(|(p, &t)| p.value(t)).collect()
+    };
+    let objective = |theta: &[f64]| {
+        let ll = log_likelihood(&days, three_state, &to_values(theta));
+        if ll.is_finite() { -ll } else { f64::INFINITY }
+    };
+
+    let start: Vec<f64> = params.iter().map(|p| p.searched(p.current)).collect();
+    let steps: Vec<f64> = params.iter().map(|p| p.step).collect();
+    // a second run from the first result gets the simplex unstuck if it
+    // collapsed early
+    let (theta, _) = nelder_mead(objective, &start, &steps);
+    let (theta, _) = nelder_mead(objective, &theta, &steps);
+    // rounded so the config file stays readable
+    let fitted: Vec<f64> = params
+        .iter()
+        .zip(to_values(&theta))
+        .map(|(p, v)| {
+            if p.fixed() {
+                p.current
+            } else {
+                (v * 1000.0).round() / 1000.0
+            }
+        })
+        .collect();
+    let after = log_likelihood(&days, three_state, &fitted);
+
+    let improved = after > before;
+    let saved = if save && improved {
+        Some(config::update(|config| {
+            if three_state {
+                config.model.kalman3d = noise_3d(&fitted);
+            } else {
+                config.model.initial_tdee = fitted[3];
+                config.model.kalman2d = noise_2d(&fitted);
+            }
+        })?)
+    } else {
+        None
+    };
+
+    let report = TuneReport {
+        model: if three_state { "3-state" } else { "2-state" },
+        days: days.len(),
+        weigh_ins,
+        params: params
+            .iter()
+            .zip(&fitted)
+            .map(|(p, &fitted)| Fitted {
+                name: p.name,
+                current: p.current,
+                fitted,
+            })
+            .collect(),
+        log_likelihood_before: before,
+        log_likelihood_after: after,
+        saved_to: saved,
+    };
+
+    match format {
+        Format::Table => {}
+        Format::Json => return output::json(&report),
+        Format::Csv => return output::csv(&report.params),
+    }
+
+    println!(
+        "Fitted the {} filter to {} days ({weigh_ins} weigh-ins)",
+        report.model, report.days
+    );
+    println!("parameter          current     fitted");
+    for p in &report.params {
+        println!("{:<16} {:>9.3}  {:>9.3}", p.name, p.current, p.fitted);
+    }
+    println!(
+        "log-likelihood: {:.1} -> {:.1} ({:+.1})",
+        before,
+        after,
+        after - before
+    );
+
+    match &report.saved_to {
+        _ if !improved => {
+            println!("Current settings are already the best found; nothing to save")
+        }
+        Some(path) => println!("Saved to {}", path.display()),
+        None => println!(
+            "Run with --save to write these to {}",
+            config::path().display()
+        ),
+    }
+    Ok(())
+}
@@ -1,17 +1,27 @@
+use crate::config;
 use rusqlite::Result;
 use std::fs;
+use std::path::PathBuf;
 
-pub const DATA_DIR: &str = "data";
-pub const VAULT_DIR: &str = "/home/emma/Documents/life/Daily";
+/// Path to a file inside the configured data directory
+pub fn data_path(name: &str) -> PathBuf {
+    PathBuf::from(&config::get().data_dir).join(name)
+}
+
+/// Path to a file inside the configured vault directory
+pub fn vault_path(name: &str) -> PathBuf {
+    PathBuf::from(&config::get().vault_dir).join(name)
+}
 
 pub fn get_database_path() -> Result<String> {
-    if !std::path::Path::new(DATA_DIR).exists() {
-        fs::create_dir_all(DATA_DIR).map_err(|e| {
+    let data_dir = &config::get().data_dir;
+    if !std::path::Path::new(data_dir).exists() {
+        fs::create_dir_all(data_dir).map_err(|e| {
             rusqlite::Error::SqliteFailure(
                 rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_IOERR),
                 Some(format!("Failed to create directory: {}", e)),
             )
         })?;
     }
-    Ok(format!("{}/links.db", DATA_DIR))
+    Ok(format!("{}/links.db", data_dir))
 }
@@ -0,0 +1,312 @@
+//! Daily weigh-ins: weight, intake, Strava relative effort, protein and the
+//! scale's body-fat reading
+//!
+//! One row per date in the `weight` table, plus any extra scale readings
+//! logged with `--again`. Weight or intake may be missing for a day; the
+//! model copes. `import csv` loads the old `weight_energy.csv` (with or
+//! without a header), `export csv` writes the table back out in the same
+//! column order. Rows sharing a date are matched to that date's entries in
+//! the order they were logged, so the extra readings survive a round trip.
+
+use crate::audit::{self, Action, Batch};
+use crate::cli::WeightArgs;
+use crate::clock;
+use crate::db;
+use crate::model1::Row;
+use crate::prompt_or;
+use anyhow::{Context, Result, bail};
+use chrono::NaiveDate;
+use csv::{ReaderBuilder, Writer};
+use rusqlite::{Connection, OptionalExtension};
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+
+/// Column order of the CSV files read and written here
+const CSV_HEADER: [&str; 6] = [
+    "date",
+    "weight",
+    "intake_kcal",
+    "strava_re",
+    "protein",
+    "body_fat",
+];
+
+pub struct WeighIn {
+    pub date: NaiveDate,
+    pub weight: Option<f64>,
+    pub intake_kcal: Option<f64>,
+    pub strava_re: f64,
+    pub protein: Option<f64>,
+    pub body_fat: Option<f64>,
+}
+
+// Entry number `nth` logged for `date`, counting from 0
+fn existing_id(conn: &Connection, date: NaiveDate, nth: usize) -> rusqlite::Result<Option<i64>> {
+    conn.query_row(
+        "SELECT id FROM weight WHERE date = ?1 ORDER BY id LIMIT 1 OFFSET ?2",
+        (date.to_string(), nth),
+        |row| row.get(0),
+    )
+    .optional()
+}
+
+/// Insert the weigh-in as entry `nth` of its date, or replace the one
+/// already there when `overwrite` is set. Returns false if that entry
+/// exists and was left alone.
+fn save(
+    conn: &Connection,
+    batch: &mut Batch,
+    entry: &WeighIn,
+    nth: usize,
+    overwrite: bool,
+) -> Result<bool> {
+    match existing_id(conn, entry.date, nth)? {
+        Some(_) if !overwrite => Ok(false),
+        Some(id) => {
+            replace(conn, batch, id, entry)?;
+            Ok(true)
+        }
+        None => {
+            insert(conn, batch, entry)?;
+            Ok(true)
+        }
+    }
+}
+
+fn insert(conn: &Connection, batch: &mut Batch, entry: &WeighIn) -> Result<()> {
+    conn.execute(
+        "INSERT INTO weight (date, weight, intake_kcal, strava_re, protein, body_fat) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        (
+            entry.date.to_string(),
+            entry.weight,
+            entry.intake_kcal,
+            entry.strava_re,
+            entry.protein,
+            entry.body_fat,
+        ),
+    )?;
+    batch.record_insert(conn, "weight", "weight", conn.last_insert_rowid())?;
+    Ok(())
+}
+
+fn replace(conn: &Connection, batch: &mut Batch, id: i64, entry: &WeighIn) -> Result<()> {
+    let before = audit::row_json(conn, "weight", id)?;
+    conn.execute(
+        "UPDATE weight SET date = ?1, weight = ?2, intake_kcal = ?3, strava_re = ?4, protein = ?5, body_fat = ?6 WHERE id = ?7",
+        (
+            entry.date.to_string(),
+            entry.weight,
+            entry.intake_kcal,
+            entry.strava_re,
+            entry.protein,
+            entry.body_fat,
+            id,
+        ),
+    )?;
+    let after = audit::row_json(conn, "weight", id)?;
+    batch.record(
+        conn,
+        "weight",
+        id,
+        Action::Update,
+        before.as_ref(),
+        after.as_ref(),
+    )?;
+    Ok(())
+}
+
+/// `log weight`. `--again` adds another scale reading to a day that already
+/// has one; `--no-scale` logs the day's numbers without a weigh-in.
+pub fn log_weight(args: WeightArgs) -> Result<()> {
+    let conn = db::open()?;
+    let today = clock::today();
+
+    let existing = existing_id(&conn, today, 0)?;
+    if existing.is_some() && !args.overwrite && !args.again {
+        bail!(
+            "already have a weigh-in for {}; pass --again to add another reading or --overwrite to replace it",
+            today
+        );
+    }
+
+    let weight: Option<f64> = if args.no_scale {
+        None
+    } else {
+        Some(prompt_or(
+            args.weight,
+            "Enter today's am weight (e.g. 294.6): ",
+        ))
+    };
+
+    let entry = if args.again {
+        // The day's intake is already on the first entry
+        WeighIn {
+            date: today,
+            weight,
+            intake_kcal: None,
+            strava_re: 0.0,
+            protein: None,
+            body_fat: args.body_fat,
+        }
+    } else {
+        let cals_realized: u32 = prompt_or(args.cals, "Enter cals_realized (e.g. 1979): ");
+        let protein: u32 = prompt_or(args.protein, "Enter protein in g ");
+        let strava_re: u32 = prompt_or(args.strava_re, "Enter strava RE score ");
+        WeighIn {
+            date: today,
+            weight,
+            intake_kcal: Some(cals_realized as f64),
+            strava_re: strava_re as f64,
+            protein: Some(protein as f64),
+            body_fat: args.body_fat,
+        }
+    };
+
+    match existing {
+        Some(id) if args.overwrite && !args.again => replace(&conn, &mut Batch::new(), id, &entry)?,
+        _ => insert(&conn, &mut Batch::new(), &entry)?,
+    }
+
+    println!();
+    println!("Update calendar");
+
+    Ok(())
+}
+
+/// Every weigh-in, oldest first
+pub fn load(conn: &Connection) -> rusqlite::Result<Vec<WeighIn>> {
+    let mut stmt = conn.prepare(
+        "SELECT date, weight, intake_kcal, strava_re, protein, body_fat FROM weight ORDER BY date, id",
+    )?;
+    let iter = stmt.query_map([], |row| {
+        let date: String = row.get(0)?;
+        Ok(WeighIn {
+            date: NaiveDate::parse_from_str(&date, "%Y-%m-%d").map_err(|e| {
+                rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, e.into())
+            })?,
+            weight: row.get(1)?,
+            intake_kcal: row.get(2)?,
+            strava_re: row.get(3)?,
+            protein: row.get(4)?,
+            body_fat: row.get(5)?,
+        })
+    })?;
+    iter.collect()
+}
+
+/// The weigh-ins as model input rows
+pub fn model_rows() -> Result<Vec<Row>> {
+    let conn = db::open()?;
+    Ok(load(&conn)?
+        .into_iter()
+        .map(|w| Row {
+            date: w.date.to_string(),
+            weight: w.weight,
+            intake_kcal: w.intake_kcal,
+            activity: w.strava_re,
+            body_fat: w.body_fat,
+        })
+        .collect())
+}
+
+fn parse_record(record: &csv::StringRecord) -> Result<Option<WeighIn>> {
+    let field = |idx: usize| record.get(idx).map(str::trim).unwrap_or("");
+
+    // A header line (or anything else without a date up front) is skipped
+    let Ok(date) = NaiveDate::parse_from_str(field(0), "%Y-%m-%d") else {
+        return Ok(None);
+    };
+    let number = |idx: usize, name: &str| -> Result<Option<f64>> {
+        match field(idx) {
+            "" => Ok(None),
+            s => s
+                .parse()
+                .map(Some)
+                .with_context(|| format!("{date}: bad {name} '{s}'")),
+        }
+    };
+
+    Ok(Some(WeighIn {
+        date,
+        weight: number(1, "weight")?,
+        intake_kcal: number(2, "intake")?,
+        strava_re: number(3, "strava_re")?.unwrap_or(0.0),
+        protein: number(4, "protein")?,
+        body_fat: number(5, "body_fat")?,
+    }))
+}
+
+/// `import csv`
+pub fn import_csv(path: &Path, overwrite: bool) -> Result<()> {
+    let mut rdr = ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_path(path)
+        .with_context(|| format!("reading {}", path.display()))?;
+
+    let mut conn = db::open()?;
+    let tx = conn.transaction()?;
+    let mut batch = Batch::new();
+    let (mut imported, mut skipped) = (0, Vec::new());
+    // rows seen so far per date, to line up with the entries logged on it
+    let mut seen: HashMap<NaiveDate, usize> = HashMap::new();
+
+    for record in rdr.records() {
+        let Some(entry) = parse_record(&record?)? else {
+            continue;
+        };
+        let nth = seen.entry(entry.date).or_default();
+        let saved = save(&tx, &mut batch, &entry, *nth, overwrite)?;
+        *nth += 1;
+        if saved {
+            imported += 1;
+        } else {
+            skipped.push(entry.date);
+        }
+    }
+    tx.commit()?;
+
+    println!("Imported {} weigh-ins from {}", imported, path.display());
+    if !skipped.is_empty() {
+        let dates: Vec<String> = skipped.iter().map(|d| d.to_string()).collect();
+        println!(
+            "Skipped {} weigh-ins already logged (use --overwrite to replace): {}",
+            skipped.len(),
+            dates.join(", ")
+        );
+    }
+
+    Ok(())
+}
+
+/// `export csv`: to `out`, or stdout when not given
+pub fn export_csv(out: Option<&Path>) -> Result<()> {
+    let conn = db::open()?;
+    let rows = load(&conn)?;
+
+    let mut wtr: Writer<Box<dyn io::Write>> = match out {
+        Some(path) => Writer::from_writer(Box::new(
+            std::fs::File::create(path).with_context(|| format!("writing {}", path.display()))?,
+        )),
+        None => Writer::from_writer(Box::new(io::stdout())),
+    };
+
+    wtr.write_record(CSV_HEADER)?;
+    for w in &rows {
+        wtr.write_record([
+            w.date.to_string(),
+            w.weight.map(|v| v.to_string()).unwrap_or_default(),
+            w.intake_kcal.map(|v| v.to_string()).unwrap_or_default(),
+            w.strava_re.to_string(),
+            w.protein.map(|p| p.to_string()).unwrap_or_default(),
+            w.body_fat.map(|p| p.to_string()).unwrap_or_default(),
+        ])?;
+    }
+    wtr.flush()?;
+
+    if let Some(path) = out {
+        println!("Exported {} weigh-ins to {}", rows.len(), path.display());
+    }
+    Ok(())
+}
@@ -0,0 +1,352 @@
+//! YNAB exchange
+//!
+//! Works from files, so it needs no account access: `export ynab` writes
+//! spend rows in the CSV layout YNAB's file import takes, and `import ynab`
+//! reads a YNAB register export back. Its purchases become `spend` rows and
+//! its credit-card accounts become `debt_balance` snapshots. Both directions
+//! go through a mapping file (`ynab.toml` in the data directory):
+//!
+//! ```toml
+//! [categories]              # YNAB category -> spend category
+//! "Everyday: Groceries" = "food"
+//! "Dining Out" = "food"
+//!
+//! [accounts]                # YNAB account -> [[debts]] name
+//! "Discover It" = "discover"
+//! ```
+//!
+//! Spend that came from YNAB isn't exported again, and purchases already in
+//! `spend` (logged by hand, or imported from a statement) aren't imported
+//! again, so a purchase is only ever entered once.
+
+use crate::audit;
+use crate::config;
+use crate::db;
+use crate::statement::{self, CENT};
+use crate::util;
+use anyhow::{Context, Result, bail};
+use chrono::NaiveDate;
+use csv::{ReaderBuilder, Writer};
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Mapping {
+    /// YNAB category, as "Group: Category" or just "Category"
+    categories: BTreeMap<String, String>,
+    /// YNAB account to debt account
+    accounts: BTreeMap<String, String>,
+}
+
+impl Mapping {
+    fn path(given: Option<&Path>) -> PathBuf {
+        given
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| util::data_path("ynab.toml"))
+    }
+
+    /// An empty mapping when the default file doesn't exist
+    fn load(given: Option<&Path>) -> Result<Mapping> {
+        let path = Mapping::path(given);
+        if given.is_none() && !path.exists() {
+            return Ok(Mapping::default());
+        }
+        let text =
+            fs::read_to_string(&path).with_context(|| format!("reading {}", path.display()))?;
+        let mapping: Mapping =
+            toml::from_str(&text).with_context(|| format!("parsing {}", path.display()))?;
+        for (account, debt) in &mapping.accounts {
+            if config::get().debt(debt).is_none() {
+                bail!(
+                    "{}: account '{account}' maps to '{debt}', which isn't one of the [[debts]]",
+                    path.display()
+                );
+            }
+        }
+        Ok(mapping)
+    }
+
+    fn spend_category(&self, full: &str, category: &str) -> Option<&str> {
+        self.categories
+            .get(full)
+            .or_else(|| self.categories.get(category))
+            .map(String::as_str)
+    }
+
+    /// First YNAB category mapped to `category`, else the name itself
+    fn ynab_category<'a>(&'a self, category: &'a str) -> &'a str {
+        self.categories
+            .iter()
+            .find(|(_, spend)| *spend == category)
+            .map(|(ynab, _)| ynab.as_str())
+            .unwrap_or(category)
+    }
+}
+
+/// `export ynab`: Date, Payee, Memo, Outflow, Inflow, with the YNAB category
+/// in the memo since YNAB's file import doesn't take categories
+pub fn export(
+    out: Option<&Path>,
+    since: Option<NaiveDate>,
+    mapping: Option<&Path>,
+    date_format: &str,
+) -> Result<()> {
+    let mapping = Mapping::load(mapping)?;
+    let conn = db::open()?;
+    let mut stmt = conn.prepare(
+        "SELECT date(date_time), name, category, amount FROM spend
+            WHERE (import_id IS NULL OR import_id NOT LIKE 'ynab:%') AND date(date_time) >= ?1
+            ORDER BY date_time, id",
+    )?;
+    let since = since.map(|d| d.to_string()).unwrap_or_default();
+    let rows = stmt
+        .query_map([since], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, f64>(3)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut wtr: Writer<Box<dyn io::Write>> = match out {
+        Some(path) => Writer::from_writer(Box::new(
+            fs::File::create(path).with_context(|| format!("writing {}", path.display()))?,
+        )),
+        None => Writer::from_writer(Box::new(io::stdout())),
+    };
+    wtr.write_record(["Date", "Payee", "Memo", "Outflow", "Inflow"])?;
+    for (date, name, category, amount) in &rows {
+        let date = NaiveDate::parse_from_str(date, "%Y-%m-%d")?;
+        let (outflow, inflow) = if *amount >= 0.0 {
+            (format!("{amount:.2}"), String::new())
+        } else {
+            (String::new(), format!("{:.2}", -amount))
+        };
+        wtr.write_record([
+            date.format(date_format).to_string(),
+            name.clone(),
+            mapping.ynab_category(category).to_string(),
+            outflow,
+            inflow,
+        ])?;
+    }
+    wtr.flush()?;
+
+    if let Some(path) = out {
+        println!("Exported {} spend rows to {}", rows.len(), path.display());
+    }
+    Ok(())
+}
+
+struct RegisterRow {
+    account: String,
+    date: NaiveDate,
+    payee: String,
+    /// "Group: Category"
+    full_category: String,
+    category: String,
+    outflow: f64,
+    inflow: f64,
+}
+
+impl RegisterRow {
+    /// Money going out to a payee: not a transfer between YNAB accounts
+    /// (e.g. a card payment), an account's opening balance or income
+    fn is_purchase(&self) -> bool {
+        self.outflow > CENT
+            && !self.payee.starts_with("Transfer : ")
+            && self.payee != "Starting Balance"
+            && !self.full_category.starts_with("Inflow:")
+    }
+}
+
+fn read_register(path: &Path, date_format: &str) -> Result<Vec<RegisterRow>> {
+    let text = fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+    let text = text.trim_start_matches('\u{feff}');
+    let mut rdr = ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(text.as_bytes());
+    let headers: Vec<String> = rdr
+        .headers()?
+        .iter()
+        .map(|h| h.trim().to_lowercase())
+        .collect();
+    if headers.iter().any(|h| h == "budgeted") {
+        bail!("this is a budget export; import ynab needs the register export");
+    }
+    let column = |name: &str| headers.iter().position(|h| h == name);
+    let required = |name: &str| {
+        column(name).with_context(|| {
+            format!(
+                "no '{name}' column; is this a YNAB register export? The file has: {}",
+                headers.join(", ")
+            )
+        })
+    };
+    let account_col = required("account")?;
+    let date_col = required("date")?;
+    let payee_col = required("payee")?;
+    let outflow_col = required("outflow")?;
+    let inflow_col = required("inflow")?;
+    let full_col = column("category group/category");
+    let category_col = column("category");
+
+    let mut rows = Vec::new();
+    for record in rdr.records() {
+        let record = record?;
+        let field = |idx: Option<usize>| {
+            idx.and_then(|idx| record.get(idx))
+                .unwrap_or("")
+                .trim()
+                .to_string()
+        };
+        let date_str = field(Some(date_col));
+        if date_str.is_empty() {
+            continue;
+        }
+        let date = NaiveDate::parse_from_str(&date_str, date_format)
+            .with_context(|| format!("date '{date_str}' isn't {date_format}"))?;
+        let money = |idx: usize| -> Result<f64> {
+            let s = field(Some(idx));
+            if s.is_empty() {
+                return Ok(0.0);
+            }
+            statement::parse_amount(&s).with_context(|| format!("{date}: bad amount '{s}'"))
+        };
+        rows.push(RegisterRow {
+            account: field(Some(account_col)),
+            date,
+            payee: field(Some(payee_col)),
+            full_category: field(full_col),
+            category: field(category_col),
+            outflow: money(outflow_col)?,
+            inflow: money(inflow_col)?,
+        });
+    }
+    Ok(rows)
+}
+
+/// `import ynab`
+pub fn import(
+    path: &Path,
+    mapping_path: Option<&Path>,
+    date_format: &str,
+    dry_run: bool,
+) -> Result<()> {
+    let mapping = Mapping::load(mapping_path)?;
+    let rows = read_register(path, date_format)?;
+
+    let purchases: Vec<&RegisterRow> = rows.iter().filter(|r| r.is_purchase()).collect();
+    let unmapped: BTreeSet<&str> = purchases
+        .iter()
+        .filter(|r| {
+            mapping
+                .spend_category(&r.full_category, &r.category)
+                .is_none()
+        })
+        .map(|r| r.full_category.as_str())
+        .collect();
+    if !unmapped.is_empty() {
+        bail!(
+            "add these YNAB categories to [categories] in {}: {}",
+            Mapping::path(mapping_path).display(),
+            unmapped
+                .iter()
+                .map(|c| format!("\"{c}\""))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+
+    // Balance of each mapped account over the whole register, as of its last day
+    let mut balances: BTreeMap<&str, (NaiveDate, f64)> = BTreeMap::new();
+    for row in &rows {
+        if let Some(debt) = mapping.accounts.get(&row.account) {
+            let entry = balances.entry(debt.as_str()).or_insert((row.date, 0.0));
+            entry.0 = entry.0.max(row.date);
+            entry.1 += row.outflow - row.inflow;
+        }
+    }
+
+    let mut conn = db::open()?;
+    let tx = conn.transaction()?;
+    let mut batch = audit::Batch::new();
+    let mut used = HashSet::new();
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    let (mut imported, mut duplicates) = (0, 0);
+    for row in &purchases {
+        let key = format!(
+            "ynab:{}:{}:{:.2}:{}",
+            row.account, row.date, row.outflow, row.payee
+        );
+        let n = seen.entry(key.clone()).or_default();
+        *n += 1;
+        let import_id = format!("{key}#{n}");
+        if statement::already_logged(&tx, row.date, row.outflow, &import_id, &mut used)? {
+            duplicates += 1;
+            continue;
+        }
+        let category = mapping
+            .spend_category(&row.full_category, &row.category)
+            .unwrap_or_default();
+        if dry_run {
+            println!(
+                "{}  {:>9.2}  {:<15} {}",
+                row.date, row.outflow, category, row.payee
+            );
+        } else {
+            tx.execute(
+                "INSERT INTO spend (date_time, amount, name, category, import_id) VALUES (?1, ?2, ?3, ?4, ?5)",
+                (
+                    format!("{} 00:00:00", row.date),
+                    row.outflow,
+                    &row.payee,
+                    category,
+                    &import_id,
+                ),
+            )?;
+            batch.record_insert(&tx, "spend", "spend", tx.last_insert_rowid())?;
+        }
+        imported += 1;
+    }
+
+    let mut snapshots = Vec::new();
+    for (debt, (date, balance)) in &balances {
+        let balance = (balance * 100.0).round() / 100.0;
+        let logged: bool = tx.query_row(
+            "SELECT EXISTS (SELECT 1 FROM debt_balance WHERE account = ?1 AND date(date_time) = ?2)",
+            (debt, date.to_string()),
+            |row| row.get(0),
+        )?;
+        if logged {
+            continue;
+        }
+        if !dry_run {
+            tx.execute(
+                "INSERT INTO debt_balance (date_time, account, balance) VALUES (?1, ?2, ?3)",
+                (format!("{date} 00:00:00"), debt, balance),
+            )?;
+            batch.record_insert(&tx, "debt", "debt_balance", tx.last_insert_rowid())?;
+        }
+        snapshots.push(format!("{debt} ${balance:.2} on {date}"));
+    }
+    if !dry_run {
+        tx.commit()?;
+    }
+
+    println!(
+        "{} {imported} purchases from {} (skipped {duplicates} already logged)",
+        if dry_run { "Would import" } else { "Imported" },
+        path.display()
+    );
+    if !snapshots.is_empty() {
+        println!("Debt balances: {}", snapshots.join(", "));
+    }
+    Ok(())
+}